@@ -1,21 +1,34 @@
 use super::AntPlugin;
+use crate::aphid::AphidPlugin;
 use crate::colony::ColonyPlugin;
-use crate::terrain::TerrainPlugin;
+use crate::food::FoodPlugin;
+use crate::predator::PredatorPlugin;
+use crate::terrain::surface::SKY_HEIGHT;
+use crate::terrain::{TerrainPlugin, SURFACE_Y, TILE_SIZE, WORLD_HALF_SIZE};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
+const WINDOW_HEIGHT: f32 = 600.0;
+
 pub fn run_app() {
     App::new()
         .add_plugins(DefaultPlugins.set(WindowPlugin {
             primary_window: Some(Window {
                 title: "Ant Farm".to_string(),
-                resolution: (800., 600.).into(),
+                resolution: (800., WINDOW_HEIGHT).into(),
                 ..default()
             }),
             ..default()
         }))
         .add_plugins(RapierPhysicsPlugin::<NoUserData>::pixels_per_meter(100.0))
-        .add_plugins((TerrainPlugin, ColonyPlugin, AntPlugin))
+        .add_plugins((
+            TerrainPlugin,
+            ColonyPlugin,
+            FoodPlugin,
+            AphidPlugin,
+            PredatorPlugin,
+            AntPlugin::default(),
+        ))
         .add_systems(Startup, setup_camera)
         .insert_resource(ClearColor(Color::BLACK))
         .insert_resource(RapierConfiguration {
@@ -25,13 +38,18 @@ pub fn run_app() {
         .run();
 }
 
+// Frame the whole world, from the deepest tiles up to the top of the sky
 fn setup_camera(mut commands: Commands) {
-    commands.spawn(Camera2dBundle {
-        transform: Transform::from_xyz(0.0, 0.0, 1000.0),
+    let bottom = -(WORLD_HALF_SIZE as f32 + 0.5) * TILE_SIZE;
+    let top = SURFACE_Y + (SKY_HEIGHT as f32 + 0.5) * TILE_SIZE;
+    let mut camera = Camera2dBundle {
+        transform: Transform::from_xyz(0.0, (top + bottom) / 2.0, 1000.0),
         camera: Camera {
             order: 0,
             ..default()
         },
         ..default()
-    });
+    };
+    camera.projection.scale = (top - bottom) / WINDOW_HEIGHT;
+    commands.spawn(camera);
 }
@@ -0,0 +1,228 @@
+use crate::ant::components::{Traffic, ANT_SPEED};
+use crate::ant::pathfinding::GridPos;
+use bevy::prelude::*;
+
+// Neighbours closer than this are taken into account for avoidance
+pub const AVOIDANCE_RADIUS: f32 = 12.0;
+// Ants closer than this push away from each other
+pub const SEPARATION_DISTANCE: f32 = 7.0;
+// Strength of the separation push relative to the ant's speed
+pub const SEPARATION_STRENGTH: f32 = 0.8;
+// Speed (as a fraction of normal speed) used to back away while yielding
+pub const YIELD_BACKOFF_FACTOR: f32 = 0.4;
+// Give up yielding after this long, then push on for YIELD_COOLDOWN before
+// yielding again, so an ant that can't get past doesn't back away forever
+pub const MAX_YIELD_TIME: f32 = 2.0;
+pub const YIELD_COOLDOWN: f32 = 1.5;
+
+#[derive(Debug, Clone, Copy)]
+pub struct Neighbor {
+    pub entity: Entity,
+    pub position: Vec2,
+    pub velocity: Vec2,
+}
+
+// A passage is narrow when the tiles on both sides of the travel direction are
+// solid, which means two ants can't pass each other there.
+pub fn is_narrow_passage(
+    position: Vec2,
+    heading: Vec2,
+    is_blocked: impl Fn(GridPos) -> bool,
+) -> bool {
+    let heading = heading.normalize_or_zero();
+    if heading == Vec2::ZERO {
+        return false;
+    }
+
+    let here = GridPos::from_vec2(position);
+    let side = heading.perp();
+    let offset_x = side.x.round() as i32;
+    let offset_y = side.y.round() as i32;
+
+    let left = GridPos {
+        x: here.x + offset_x,
+        y: here.y + offset_y,
+    };
+    let right = GridPos {
+        x: here.x - offset_x,
+        y: here.y - offset_y,
+    };
+    is_blocked(left) && is_blocked(right)
+}
+
+// Adjust a desired velocity so the ant keeps its distance from nearby ants.
+//
+// In open areas this is plain separation. In narrow tunnels two ants meeting
+// head-on follow a lane rule: the ant with the higher entity index yields by
+// backing away until the other has passed, or until MAX_YIELD_TIME runs out
+// and it pushes through instead.
+pub fn avoid_neighbors(
+    entity: Entity,
+    position: Vec2,
+    desired: Vec2,
+    neighbors: &[Neighbor],
+    narrow: bool,
+    traffic: &mut Traffic,
+    delta_seconds: f32,
+) -> Vec2 {
+    let speed = desired.length();
+    let heading = desired.normalize_or_zero();
+    traffic.yield_cooldown = (traffic.yield_cooldown - delta_seconds).max(0.0);
+
+    if let Some(other) = traffic.yielding_to {
+        traffic.yield_time += delta_seconds;
+        let blocker = neighbors
+            .iter()
+            .find(|n| n.entity == other && n.position.distance(position) < AVOIDANCE_RADIUS);
+
+        match blocker {
+            Some(blocker) if traffic.yield_time < MAX_YIELD_TIME => {
+                let away = (position - blocker.position).normalize_or_zero();
+                return if narrow {
+                    away * ANT_SPEED * YIELD_BACKOFF_FACTOR
+                } else {
+                    // There's room here, so step to the side and let it through
+                    let side = away.perp();
+                    let side = if side.dot(heading) < 0.0 { -side } else { side };
+                    side * ANT_SPEED * YIELD_BACKOFF_FACTOR
+                };
+            }
+            Some(_) => {
+                println!("Ant {:?} gave up yielding to {:?}", entity, other);
+                traffic.yielding_to = None;
+                traffic.yield_time = 0.0;
+                traffic.yield_cooldown = YIELD_COOLDOWN;
+            }
+            None => {
+                traffic.yielding_to = None;
+                traffic.yield_time = 0.0;
+            }
+        }
+    }
+
+    let mut separation = Vec2::ZERO;
+    let mut speed_limit = speed;
+
+    for neighbor in neighbors {
+        if neighbor.entity == entity {
+            continue;
+        }
+
+        let offset = neighbor.position - position;
+        let distance = offset.length();
+        if distance >= AVOIDANCE_RADIUS || distance <= f32::EPSILON {
+            continue;
+        }
+
+        let direction = offset / distance;
+        let ahead = direction.dot(heading) > 0.5;
+
+        if narrow
+            && ahead
+            && neighbor.velocity.dot(heading) < 0.0
+            && entity > neighbor.entity
+            && traffic.yield_cooldown <= 0.0
+        {
+            println!(
+                "Ant {:?} yielding to {:?} in narrow tunnel",
+                entity, neighbor.entity
+            );
+            traffic.yielding_to = Some(neighbor.entity);
+            traffic.yield_time = 0.0;
+            return -heading * ANT_SPEED * YIELD_BACKOFF_FACTOR;
+        }
+
+        if ahead && neighbor.velocity.dot(heading) > 0.0 && distance < SEPARATION_DISTANCE * 1.5 {
+            // Queue up behind ants moving the same way instead of shoving them
+            speed_limit = speed_limit.min(neighbor.velocity.dot(heading));
+        }
+
+        if distance < SEPARATION_DISTANCE {
+            separation -= direction * (1.0 - distance / SEPARATION_DISTANCE);
+        }
+    }
+
+    if narrow {
+        // No room to move sideways, only keep the component along the tunnel
+        separation = heading * separation.dot(heading);
+    }
+
+    heading * speed_limit + separation * ANT_SPEED * SEPARATION_STRENGTH
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const FRAME: f32 = 0.1;
+    const DESIRED: Vec2 = Vec2::new(ANT_SPEED, 0.0);
+
+    fn ant() -> Entity {
+        Entity::from_raw(2)
+    }
+
+    // An ant one tile ahead in the tunnel, with a lower index so `ant` gives way
+    fn blocker(velocity: Vec2) -> Neighbor {
+        Neighbor {
+            entity: Entity::from_raw(1),
+            position: Vec2::new(8.0, 0.0),
+            velocity,
+        }
+    }
+
+    fn step(neighbors: &[Neighbor], traffic: &mut Traffic) -> Vec2 {
+        avoid_neighbors(ant(), Vec2::ZERO, DESIRED, neighbors, true, traffic, FRAME)
+    }
+
+    #[test]
+    fn backs_away_from_an_oncoming_ant_in_a_narrow_tunnel() {
+        let mut traffic = Traffic::default();
+        let velocity = step(&[blocker(Vec2::new(-ANT_SPEED, 0.0))], &mut traffic);
+        assert!(velocity.x < 0.0);
+        assert_eq!(traffic.yielding_to, Some(blocker(Vec2::ZERO).entity));
+    }
+
+    #[test]
+    fn gives_up_on_a_stationary_blocker_after_max_yield_time() {
+        let neighbors = [blocker(Vec2::ZERO)];
+        let mut traffic = Traffic {
+            yielding_to: Some(neighbors[0].entity),
+            ..default()
+        };
+
+        let mut waited = 0.0;
+        while traffic.yielding_to.is_some() {
+            let velocity = step(&neighbors, &mut traffic);
+            waited += FRAME;
+            if traffic.yielding_to.is_some() {
+                assert!(velocity.x < 0.0);
+            } else {
+                assert_eq!(velocity, DESIRED);
+            }
+            assert!(waited < MAX_YIELD_TIME + 2.0 * FRAME, "still yielding");
+        }
+        assert!(waited >= MAX_YIELD_TIME);
+    }
+
+    #[test]
+    fn pushes_through_for_a_while_before_yielding_again() {
+        let neighbors = [blocker(Vec2::new(-ANT_SPEED, 0.0))];
+        let mut traffic = Traffic {
+            yielding_to: Some(neighbors[0].entity),
+            yield_time: MAX_YIELD_TIME,
+            ..default()
+        };
+
+        // Times out, then keeps going forward for the whole cooldown
+        let frames = (YIELD_COOLDOWN / FRAME) as usize;
+        for _ in 0..frames {
+            let velocity = step(&neighbors, &mut traffic);
+            assert_eq!(traffic.yielding_to, None);
+            assert!(velocity.x > 0.0);
+        }
+
+        step(&neighbors, &mut traffic);
+        step(&neighbors, &mut traffic);
+        assert_eq!(traffic.yielding_to, Some(neighbors[0].entity));
+    }
+}
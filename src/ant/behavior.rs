@@ -0,0 +1,343 @@
+use crate::ant::components::{AntRole, Behavior};
+use crate::ant::systems::combat::{BITE_REACH, ENEMY_SCENT_RADIUS};
+
+// Ants go looking for food below this satiety. The queen waits for nurses to
+// feed her, and only eats from the store herself once she's starving.
+pub const HUNGRY_THRESHOLD: f32 = 0.3;
+const QUEEN_STARVING_THRESHOLD: f32 = 0.1;
+// Score added to the behavior an ant is already doing, so it doesn't flip
+// between two that score about the same
+const COMMITMENT: f32 = 0.05;
+
+// What an ant knows about itself and its colony when deciding what to do
+#[derive(Debug, Clone, Copy)]
+pub struct Senses {
+    pub satiety: f32,
+    // Distance from the colony as a fraction of how far the ant's role may go
+    pub range_used: f32,
+    pub stored_food: f32,
+    pub has_orders: bool,
+    // Health left, as a fraction of the role's full health
+    pub health: f32,
+    // Distance to the nearest predator or ant from another colony, infinite
+    // if there are none close enough to smell
+    pub enemy_distance: f32,
+}
+
+// One of the senses, as a number
+#[derive(Debug, Clone, Copy)]
+pub enum Input {
+    Satiety,
+    RangeUsed,
+    StoredFood,
+    Orders,
+    Health,
+    EnemyDistance,
+}
+
+impl Input {
+    fn read(&self, senses: &Senses) -> f32 {
+        match self {
+            Input::Satiety => senses.satiety,
+            Input::RangeUsed => senses.range_used,
+            Input::StoredFood => senses.stored_food,
+            Input::Orders => f32::from(u8::from(senses.has_orders)),
+            Input::Health => senses.health,
+            Input::EnemyDistance => senses.enemy_distance,
+        }
+    }
+}
+
+// Turns an input into a score from 0.0 to 1.0
+#[derive(Debug, Clone, Copy)]
+pub enum Curve {
+    Below(f32),
+    Above(f32),
+}
+
+impl Curve {
+    fn apply(&self, value: f32) -> f32 {
+        let passed = match *self {
+            Curve::Below(threshold) => value < threshold,
+            Curve::Above(threshold) => value > threshold,
+        };
+        f32::from(u8::from(passed))
+    }
+}
+
+#[derive(Debug, Clone, Copy)]
+pub struct Consideration {
+    pub input: Input,
+    pub curve: Curve,
+}
+
+// A behavior an ant may choose, and what it takes for that to be worth doing.
+// Its score is its weight scaled by every consideration in turn, so any one
+// of them can rule it out.
+#[derive(Debug, Clone, Copy)]
+pub struct BehaviorOption {
+    pub behavior: Behavior,
+    pub weight: f32,
+    pub considerations: &'static [Consideration],
+}
+
+impl BehaviorOption {
+    pub fn score(&self, senses: &Senses) -> f32 {
+        self.considerations
+            .iter()
+            .fold(self.weight, |score, consideration| {
+                score * consideration.curve.apply(consideration.input.read(senses))
+            })
+    }
+}
+
+const FOLLOW_ORDERS: BehaviorOption = BehaviorOption {
+    behavior: Behavior::FollowOrders,
+    weight: 1.0,
+    considerations: &[Consideration {
+        input: Input::Orders,
+        curve: Curve::Above(0.5),
+    }],
+};
+
+// Soldiers go after any predator or foreign ant they can smell
+const FIGHT: BehaviorOption = BehaviorOption {
+    behavior: Behavior::Fight,
+    weight: 0.95,
+    considerations: &[Consideration {
+        input: Input::EnemyDistance,
+        curve: Curve::Below(ENEMY_SCENT_RADIUS),
+    }],
+};
+
+// Everyone else leaves strangers alone, and only fights back once one close
+// enough to bite has hurt them
+const DEFEND: BehaviorOption = BehaviorOption {
+    considerations: &[
+        Consideration {
+            input: Input::EnemyDistance,
+            curve: Curve::Below(BITE_REACH),
+        },
+        Consideration {
+            input: Input::Health,
+            curve: Curve::Below(1.0),
+        },
+    ],
+    ..FIGHT
+};
+
+const EAT: BehaviorOption = BehaviorOption {
+    behavior: Behavior::Eat,
+    weight: 0.9,
+    considerations: &[
+        Consideration {
+            input: Input::Satiety,
+            curve: Curve::Below(HUNGRY_THRESHOLD),
+        },
+        Consideration {
+            input: Input::StoredFood,
+            curve: Curve::Above(0.0),
+        },
+    ],
+};
+
+const QUEEN_EAT: BehaviorOption = BehaviorOption {
+    considerations: &[
+        Consideration {
+            input: Input::Satiety,
+            curve: Curve::Below(QUEEN_STARVING_THRESHOLD),
+        },
+        Consideration {
+            input: Input::StoredFood,
+            curve: Curve::Above(0.0),
+        },
+    ],
+    ..EAT
+};
+
+const RETURN_TO_RANGE: BehaviorOption = BehaviorOption {
+    behavior: Behavior::ReturnToRange,
+    weight: 0.8,
+    considerations: &[Consideration {
+        input: Input::RangeUsed,
+        curve: Curve::Above(1.0),
+    }],
+};
+
+// The role's own work, whenever nothing more pressing comes up
+const WORK: BehaviorOption = BehaviorOption {
+    behavior: Behavior::Work,
+    weight: 0.5,
+    considerations: &[],
+};
+
+// Everything an ant of a role may decide to do. New behaviors are added here,
+// with a system of their own that carries them out.
+pub fn options_for(role: AntRole) -> &'static [BehaviorOption] {
+    match role {
+        AntRole::Queen => &[DEFEND, QUEEN_EAT, WORK],
+        AntRole::Soldier => &[FOLLOW_ORDERS, FIGHT, EAT, RETURN_TO_RANGE, WORK],
+        AntRole::Worker | AntRole::Forager | AntRole::Nurse | AntRole::Scout => {
+            &[FOLLOW_ORDERS, DEFEND, EAT, RETURN_TO_RANGE, WORK]
+        }
+    }
+}
+
+// The highest scoring behavior open to an ant, favoring what it's already doing
+pub fn choose(role: AntRole, current: Behavior, senses: &Senses) -> Behavior {
+    options_for(role)
+        .iter()
+        .map(|option| (option.behavior, option.score(senses)))
+        .filter(|&(_, score)| score > 0.0)
+        .map(|(behavior, score)| {
+            if behavior == current {
+                (behavior, score + COMMITMENT)
+            } else {
+                (behavior, score)
+            }
+        })
+        .max_by(|a, b| a.1.total_cmp(&b.1))
+        .map_or(Behavior::Work, |(behavior, _)| behavior)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const ROLES: [AntRole; 6] = [
+        AntRole::Queen,
+        AntRole::Worker,
+        AntRole::Forager,
+        AntRole::Nurse,
+        AntRole::Soldier,
+        AntRole::Scout,
+    ];
+
+    fn calm() -> Senses {
+        Senses {
+            satiety: 1.0,
+            range_used: 0.0,
+            stored_food: 10.0,
+            has_orders: false,
+            health: 1.0,
+            enemy_distance: f32::INFINITY,
+        }
+    }
+
+    #[test]
+    fn every_role_can_work_and_only_the_queen_ignores_orders() {
+        for role in ROLES {
+            let behaviors: Vec<Behavior> = options_for(role)
+                .iter()
+                .map(|option| option.behavior)
+                .collect();
+            assert!(behaviors.contains(&Behavior::Work));
+            assert_eq!(
+                behaviors.contains(&Behavior::FollowOrders),
+                role != AntRole::Queen
+            );
+        }
+    }
+
+    #[test]
+    fn ants_with_nothing_pressing_get_on_with_their_work() {
+        for role in ROLES {
+            assert_eq!(choose(role, Behavior::Eat, &calm()), Behavior::Work);
+        }
+    }
+
+    #[test]
+    fn orders_come_before_eating_and_eating_before_heading_back() {
+        let senses = Senses {
+            satiety: 0.1,
+            range_used: 1.5,
+            has_orders: true,
+            ..calm()
+        };
+        assert_eq!(
+            choose(AntRole::Worker, Behavior::Work, &senses),
+            Behavior::FollowOrders
+        );
+        let senses = Senses {
+            has_orders: false,
+            ..senses
+        };
+        assert_eq!(
+            choose(AntRole::Worker, Behavior::Work, &senses),
+            Behavior::Eat
+        );
+        let senses = Senses {
+            satiety: 1.0,
+            ..senses
+        };
+        assert_eq!(
+            choose(AntRole::Worker, Behavior::Work, &senses),
+            Behavior::ReturnToRange
+        );
+    }
+
+    #[test]
+    fn hungry_ants_only_eat_while_there_is_food_in_store() {
+        let hungry = Senses {
+            satiety: 0.2,
+            ..calm()
+        };
+        assert_eq!(
+            choose(AntRole::Forager, Behavior::Work, &hungry),
+            Behavior::Eat
+        );
+        let empty_store = Senses {
+            stored_food: 0.0,
+            ..hungry
+        };
+        assert_eq!(
+            choose(AntRole::Forager, Behavior::Work, &empty_store),
+            Behavior::Work
+        );
+    }
+
+    #[test]
+    fn the_queen_waits_to_be_fed_until_she_is_starving() {
+        let hungry = Senses {
+            satiety: 0.2,
+            ..calm()
+        };
+        assert_eq!(
+            choose(AntRole::Queen, Behavior::Work, &hungry),
+            Behavior::Work
+        );
+        let starving = Senses {
+            satiety: 0.05,
+            ..calm()
+        };
+        assert_eq!(
+            choose(AntRole::Queen, Behavior::Work, &starving),
+            Behavior::Eat
+        );
+    }
+
+    #[test]
+    fn soldiers_go_after_enemies_others_only_fight_back() {
+        let enemy_nearby = Senses {
+            enemy_distance: ENEMY_SCENT_RADIUS - 1.0,
+            ..calm()
+        };
+        assert_eq!(
+            choose(AntRole::Soldier, Behavior::Work, &enemy_nearby),
+            Behavior::Fight
+        );
+        assert_eq!(
+            choose(AntRole::Worker, Behavior::Work, &enemy_nearby),
+            Behavior::Work
+        );
+
+        let bitten = Senses {
+            enemy_distance: BITE_REACH - 1.0,
+            health: 0.5,
+            ..calm()
+        };
+        for role in ROLES {
+            assert_eq!(choose(role, Behavior::Work, &bitten), Behavior::Fight);
+        }
+    }
+}
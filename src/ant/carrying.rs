@@ -0,0 +1,34 @@
+use crate::ant::components::{CarriedItem, Carrying};
+use bevy::prelude::*;
+
+// Size of the sprite drawn for a carried item
+const CARRIED_ITEM_SIZE: f32 = 3.0;
+// Carried items are drawn just in front of the ant's head
+const CARRIED_ITEM_OFFSET: Vec3 = Vec3::new(0.0, 3.5, 0.1);
+
+// Give an ant an item to carry, along with a small sprite showing it
+pub fn pick_up(commands: &mut Commands, ant: Entity, item: CarriedItem) {
+    let visual = commands
+        .spawn(SpriteBundle {
+            sprite: Sprite {
+                color: item.color(),
+                custom_size: Some(Vec2::splat(CARRIED_ITEM_SIZE)),
+                ..default()
+            },
+            transform: Transform::from_translation(CARRIED_ITEM_OFFSET),
+            ..default()
+        })
+        .id();
+
+    commands
+        .entity(ant)
+        .add_child(visual)
+        .insert(Carrying { item, visual });
+}
+
+// Take the carried item away from an ant and remove its sprite
+pub fn drop_carried(commands: &mut Commands, ant: Entity, carrying: &Carrying) {
+    commands.entity(ant).remove_children(&[carrying.visual]);
+    commands.entity(carrying.visual).despawn_recursive();
+    commands.entity(ant).remove::<Carrying>();
+}
@@ -1,44 +1,192 @@
+use crate::food::FoodKind;
 use bevy::prelude::*;
+use std::collections::VecDeque;
 
 // Constants
-pub const MAX_COLONY_DISTANCE: f32 = 500.0;
-#[allow(dead_code)]
+pub const MAX_COLONY_DISTANCE: f32 = 600.0;
 pub const COMMAND_COMPLETE_DISTANCE: f32 = 10.0;
-#[allow(dead_code)]
 pub const WORKER_WORK_RADIUS: f32 = 400.0;
-#[allow(dead_code)]
 pub const DIG_CHANCE: f32 = 0.8;
-#[allow(dead_code)]
 pub const BRANCH_CHANCE: f32 = 0.05;
-#[allow(dead_code)]
 pub const PREFERRED_DIG_ANGLE: f32 = std::f32::consts::FRAC_PI_4;
-#[allow(dead_code)]
 pub const SEARCH_RADIUS: f32 = 100.0;
-#[allow(dead_code)]
 pub const MAX_SEARCH_ATTEMPTS: i32 = 8;
 pub const ANT_SPEED: f32 = 100.0;
+pub const CORPSE_COLOR: Color = Color::rgb(0.3, 0.25, 0.2);
+pub const BROOD_COLOR: Color = Color::rgb(0.95, 0.92, 0.8);
 
+// Orders given to an ant by the player, carried out one after another before
+// it goes back to its own work
 #[derive(Debug, Clone, Copy, PartialEq)]
 pub enum AntCommand {
-    #[allow(dead_code)]
     MoveTo(Vec2),
-    Work,
+    // Dig out the tile at this point, tunnelling to it if it's buried
+    DigAt(Vec2),
+    // Dig a straight tunnel from one point to the other
+    DigTunnel { from: Vec2, to: Vec2 },
+    // Pick up food, a corpse or brood and take it somewhere
+    Carry { item: Entity, to: Vec2 },
+    // Stay in an area for a while, answering any alarm raised inside it
+    Guard { center: Vec2, radius: f32 },
+    ReturnHome,
 }
 
-impl Default for AntCommand {
-    fn default() -> Self {
-        Self::Work
+// How far an ant has got with the command at the front of its queue
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum CommandProgress {
+    #[default]
+    NotStarted,
+    Underway,
+    Digging {
+        plan: u32,
+    },
+    Guarding {
+        remaining: f32,
+    },
+}
+
+// The orders an ant has yet to carry out, front first
+#[derive(Component, Default)]
+pub struct CommandQueue {
+    pub commands: VecDeque<AntCommand>,
+    pub progress: CommandProgress,
+    // Something the ant picked up to carry for an order
+    pub holding: Option<CarriedItem>,
+}
+
+impl CommandQueue {
+    pub fn current(&self) -> Option<AntCommand> {
+        self.commands.front().copied()
+    }
+
+    pub fn push(&mut self, command: AntCommand) {
+        self.commands.push_back(command);
+    }
+
+    // Drop every order for a new one, returning the orders that were dropped
+    pub fn replace(&mut self, command: AntCommand) -> Vec<AntCommand> {
+        let dropped = self.commands.drain(..).collect();
+        self.commands.push_back(command);
+        self.progress = CommandProgress::NotStarted;
+        dropped
+    }
+
+    // Move on from the command at the front, returning it
+    pub fn finish(&mut self) -> Option<AntCommand> {
+        self.progress = CommandProgress::NotStarted;
+        self.commands.pop_front()
     }
 }
 
-#[derive(Debug, Clone, Copy, PartialEq)]
+// What an ant has decided to spend its time on. Role systems only act for
+// ants that are at `Work`; everything else is carried out by a system of its
+// own, whatever the ant's role.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Behavior {
+    #[default]
+    Work,
+    Eat,
+    ReturnToRange,
+    FollowOrders,
+    // Bite the nearest ant from another colony
+    Fight,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
 pub enum AntRole {
+    #[default]
     Worker,
+    Forager,
+    Queen,
+    Nurse,
+    Soldier,
+    Scout,
 }
 
-impl Default for AntRole {
-    fn default() -> Self {
-        Self::Worker
+// What an ant of a given role is born with
+#[derive(Debug, Clone, Copy)]
+pub struct RoleStats {
+    pub speed: f32,
+    pub size: f32,
+    pub color: Color,
+    // Seconds it lives before dying of old age, picked at random in this range
+    pub min_lifespan: f32,
+    pub max_lifespan: f32,
+    // How far from the colony it may be sent before being called back
+    pub range: f32,
+    // Damage it can take before dying, and the damage each bite does
+    pub max_health: f32,
+    pub attack: f32,
+}
+
+impl AntRole {
+    pub fn stats(&self) -> RoleStats {
+        match self {
+            AntRole::Worker => RoleStats {
+                speed: ANT_SPEED,
+                size: 5.0,
+                color: Color::rgb(0.8, 0.6, 0.2),
+                min_lifespan: 1200.0,
+                max_lifespan: 2400.0,
+                range: MAX_COLONY_DISTANCE,
+                max_health: 10.0,
+                attack: 2.0,
+            },
+            AntRole::Forager => RoleStats {
+                speed: ANT_SPEED,
+                size: 5.0,
+                color: Color::rgb(0.7, 0.5, 0.15),
+                min_lifespan: 1000.0,
+                max_lifespan: 2000.0,
+                range: MAX_COLONY_DISTANCE,
+                max_health: 10.0,
+                attack: 2.0,
+            },
+            // Larger, slower and far longer lived than her daughters
+            AntRole::Queen => RoleStats {
+                speed: ANT_SPEED * 0.3,
+                size: 8.0,
+                color: Color::rgb(0.7, 0.35, 0.15),
+                min_lifespan: 36000.0,
+                max_lifespan: 36000.0,
+                range: MAX_COLONY_DISTANCE,
+                max_health: 40.0,
+                attack: 3.0,
+            },
+            // Young, small ants that stay in the nest
+            AntRole::Nurse => RoleStats {
+                speed: ANT_SPEED * 0.7,
+                size: 4.0,
+                color: Color::rgb(0.9, 0.75, 0.45),
+                min_lifespan: 1500.0,
+                max_lifespan: 2700.0,
+                range: MAX_COLONY_DISTANCE,
+                max_health: 6.0,
+                attack: 1.0,
+            },
+            // Big, dark and heavy
+            AntRole::Soldier => RoleStats {
+                speed: ANT_SPEED * 0.8,
+                size: 7.0,
+                color: Color::rgb(0.5, 0.18, 0.1),
+                min_lifespan: 1500.0,
+                max_lifespan: 3000.0,
+                range: MAX_COLONY_DISTANCE,
+                max_health: 25.0,
+                attack: 5.0,
+            },
+            // Quick and light, and allowed to roam the furthest
+            AntRole::Scout => RoleStats {
+                speed: ANT_SPEED * 1.3,
+                size: 4.5,
+                color: Color::rgb(0.4, 0.3, 0.15),
+                min_lifespan: 900.0,
+                max_lifespan: 1800.0,
+                range: MAX_COLONY_DISTANCE * 2.0,
+                max_health: 8.0,
+                attack: 1.5,
+            },
+        }
     }
 }
 
@@ -47,21 +195,100 @@ pub enum WorkerState {
     SearchingForDigSite,
     MovingToDigSite(Vec2),
     Digging(Vec2),
+    HaulingSpoil,
+    MovingToCorpse(Entity),
+    HaulingCorpse,
+    PluggingEntrance,
+}
+
+// Foragers find food by following scent rather than planned paths. The
+// timers track how long ago the ant left home or found food, and weaken the
+// trail it lays the further it gets from where the trail leads. On the way
+// they may stop to tend a herd of aphids for a while before exploring on.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum ForagerState {
+    Exploring {
+        since_home: f32,
+    },
+    ReturningWithFood {
+        since_food: f32,
+    },
+    TendingAphids {
+        herd: Entity,
+        remaining: f32,
+        since_home: f32,
+    },
+}
+
+impl Default for ForagerState {
+    fn default() -> Self {
+        ForagerState::Exploring { since_home: 0.0 }
+    }
+}
+
+// Nurses look after one thing at a time: feeding the queen or a larva,
+// grooming brood that has been left alone, or carrying brood to whichever
+// chamber suits its stage best
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum NurseState {
+    #[default]
+    Idle,
+    Feeding(Entity),
+    Tending(Entity),
+    FeedingQueen(Entity),
+    FetchingBrood {
+        brood: Entity,
+        to: Vec2,
+    },
+    CarryingBrood {
+        brood: Entity,
+        to: Vec2,
+    },
+}
+
+// Soldiers guard the nest entrance for a while, then walk a patrol through
+// the nest and back. Alarm scent nearby draws them to wherever it is coming
+// from. A hungry colony sends a party of them to raid another colony's store;
+// raiders set off again after a fight, a few times before giving up.
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum SoldierState {
+    Guarding { remaining: f32 },
+    Patrolling { leg: usize },
+    RespondingToAlarm(Vec2),
+    Raiding { colony: Entity, attempts_left: u32 },
+    CarryingLoot { attempts_left: u32 },
+}
+
+impl Default for SoldierState {
+    fn default() -> Self {
+        SoldierState::Patrolling { leg: 0 }
+    }
+}
+
+// Scouts range far over the surface looking for food nobody has found yet,
+// and lay a trail home to it for the foragers to follow
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum ScoutState {
+    #[default]
+    Exploring,
+    ReportingFood {
+        since_food: f32,
+    },
 }
 
 #[derive(Component)]
 pub struct Ant {
     pub speed: f32,
-    #[allow(dead_code)]
     pub direction: Vec2,
     #[allow(dead_code)]
     pub on_ground: bool,
-    #[allow(dead_code)]
-    pub command: AntCommand,
-    #[allow(dead_code)]
+    pub behavior: Behavior,
     pub role: AntRole,
     pub worker_state: WorkerState,
-    #[allow(dead_code)]
+    pub forager_state: ForagerState,
+    pub nurse_state: NurseState,
+    pub soldier_state: SoldierState,
+    pub scout_state: ScoutState,
     pub search_timer: Timer,
     pub target_position: Option<Vec2>,
     pub current_path: Option<Vec<Vec2>>,
@@ -74,9 +301,13 @@ impl Default for Ant {
             speed: ANT_SPEED,
             direction: Vec2::ZERO,
             on_ground: false,
-            command: AntCommand::default(),
+            behavior: Behavior::default(),
             role: AntRole::default(),
             worker_state: WorkerState::SearchingForDigSite,
+            forager_state: ForagerState::default(),
+            nurse_state: NurseState::default(),
+            soldier_state: SoldierState::default(),
+            scout_state: ScoutState::default(),
             search_timer: Timer::from_seconds(0.1, TimerMode::Repeating),
             target_position: None,
             current_path: None,
@@ -84,3 +315,265 @@ impl Default for Ant {
         }
     }
 }
+
+impl Ant {
+    // Send the ant somewhere new. Its path there is planned afresh.
+    pub fn head_to(&mut self, position: Vec2) {
+        self.target_position = Some(position);
+        self.current_path = None;
+        self.current_path_index = 0;
+    }
+}
+
+// How readily an ant takes up each task, by the role that does it. The lower
+// a threshold, the less demand it takes for the ant to switch to that task.
+// No two ants have quite the same thresholds, so the colony never switches
+// all at once.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct ResponseThresholds {
+    pub dig: f32,
+    pub forage: f32,
+    pub nurse: f32,
+    pub defend: f32,
+    pub scout: f32,
+}
+
+impl ResponseThresholds {
+    // The queen never changes task
+    pub fn for_role(&self, role: AntRole) -> Option<f32> {
+        match role {
+            AntRole::Worker => Some(self.dig),
+            AntRole::Forager => Some(self.forage),
+            AntRole::Nurse => Some(self.nurse),
+            AntRole::Soldier => Some(self.defend),
+            AntRole::Scout => Some(self.scout),
+            AntRole::Queen => None,
+        }
+    }
+
+    pub fn for_role_mut(&mut self, role: AntRole) -> Option<&mut f32> {
+        match role {
+            AntRole::Worker => Some(&mut self.dig),
+            AntRole::Forager => Some(&mut self.forage),
+            AntRole::Nurse => Some(&mut self.nurse),
+            AntRole::Soldier => Some(&mut self.defend),
+            AntRole::Scout => Some(&mut self.scout),
+            AntRole::Queen => None,
+        }
+    }
+}
+
+// Marks an ant the player has selected to give orders to
+#[derive(Component)]
+pub struct Selected;
+
+// Local traffic state used by the avoidance layer
+#[derive(Component, Default)]
+pub struct Traffic {
+    pub yielding_to: Option<Entity>,
+    pub yield_time: f32,
+    // Seconds left before an ant that gave up yielding will yield again
+    pub yield_cooldown: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CarriedItem {
+    Dirt,
+    Food(FoodKind),
+    Corpse,
+    Brood(Entity),
+}
+
+impl CarriedItem {
+    pub fn color(&self) -> Color {
+        match self {
+            CarriedItem::Dirt => Color::rgb(0.55, 0.4, 0.25),
+            CarriedItem::Food(kind) => kind.color(),
+            CarriedItem::Corpse => CORPSE_COLOR,
+            CarriedItem::Brood(_) => BROOD_COLOR,
+        }
+    }
+}
+
+// Something an ant is holding in its mandibles, drawn by the `visual` child sprite
+#[derive(Component)]
+pub struct Carrying {
+    pub item: CarriedItem,
+    pub visual: Entity,
+}
+
+// How well fed and rested an ant is, both from 1.0 (full) down to 0.0, and
+// how long it has lived. An ant starves when satiety runs out and dies of old
+// age once `age` passes its `lifespan`.
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Vitals {
+    pub satiety: f32,
+    pub energy: f32,
+    pub age: f32,
+    pub lifespan: f32,
+}
+
+impl Vitals {
+    pub fn new(lifespan: f32) -> Self {
+        Self {
+            satiety: 1.0,
+            energy: 1.0,
+            age: 0.0,
+            lifespan,
+        }
+    }
+}
+
+// Damage an ant can still take before it dies, and how long until it can
+// bite again
+#[derive(Component, Debug, Clone, Copy)]
+pub struct Health {
+    pub current: f32,
+    pub bite_cooldown: f32,
+}
+
+impl Health {
+    pub fn new(role: AntRole) -> Self {
+        Self {
+            current: role.stats().max_health,
+            bite_cooldown: 0.0,
+        }
+    }
+}
+
+// The body of a dead ant, waiting to be carried to the midden
+#[derive(Component, Default)]
+pub struct Corpse {
+    pub claimed_by: Option<Entity>,
+    pub in_midden: bool,
+}
+
+// The colony's egg layer. Eggs are laid whenever `egg_progress` reaches 1.0.
+#[derive(Component, Default)]
+pub struct Queen {
+    pub egg_progress: f32,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum BroodStage {
+    Egg,
+    Larva,
+    Pupa,
+}
+
+impl BroodStage {
+    // Seconds a stage lasts when the brood is well cared for
+    pub fn duration(&self) -> f32 {
+        match self {
+            BroodStage::Egg => 40.0,
+            BroodStage::Larva => 60.0,
+            BroodStage::Pupa => 50.0,
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            BroodStage::Egg => BROOD_COLOR,
+            BroodStage::Larva => Color::rgb(0.95, 0.9, 0.7),
+            BroodStage::Pupa => Color::rgb(0.85, 0.75, 0.55),
+        }
+    }
+
+    pub fn size(&self) -> Vec2 {
+        match self {
+            BroodStage::Egg => Vec2::new(2.0, 3.0),
+            BroodStage::Larva => Vec2::new(3.0, 4.0),
+            BroodStage::Pupa => Vec2::new(3.0, 5.0),
+        }
+    }
+}
+
+// A developing ant: an egg laid by the queen, then a larva that has to be
+// fed, then a pupa that hatches into an adult
+#[derive(Component)]
+pub struct Brood {
+    pub colony_id: Entity,
+    pub stage: BroodStage,
+    // Progress through the current stage, from 0.0 to 1.0
+    pub development: f32,
+    // How well fed a larva is, from 1.0 (full) down to 0.0
+    pub satiety: f32,
+    // Seconds a larva has gone without food
+    pub starving_for: f32,
+    // Seconds it has lain under water
+    pub flooded_for: f32,
+    // Food eaten as a larva, which decides what the adult grows up to be
+    pub nutrition: f32,
+    // Grooming by nurses, from 1.0 (just tended) down to 0.0 (neglected)
+    pub care: f32,
+    // The nurse currently looking after it, and whether she's carrying it
+    pub claimed_by: Option<Entity>,
+    pub carried: bool,
+}
+
+impl Brood {
+    pub fn new(colony_id: Entity) -> Self {
+        Self {
+            colony_id,
+            stage: BroodStage::Egg,
+            development: 0.0,
+            satiety: 1.0,
+            starving_for: 0.0,
+            flooded_for: 0.0,
+            nutrition: 0.0,
+            care: 1.0,
+            claimed_by: None,
+            carried: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HERE: AntCommand = AntCommand::MoveTo(Vec2::ZERO);
+    const THERE: AntCommand = AntCommand::MoveTo(Vec2::ONE);
+
+    #[test]
+    fn pushed_orders_queue_up_behind_the_current_one() {
+        let mut queue = CommandQueue::default();
+        assert_eq!(queue.current(), None);
+
+        queue.push(HERE);
+        queue.progress = CommandProgress::Underway;
+        queue.push(AntCommand::ReturnHome);
+
+        assert_eq!(queue.current(), Some(HERE));
+        assert_eq!(queue.progress, CommandProgress::Underway);
+        assert_eq!(queue.commands.len(), 2);
+    }
+
+    #[test]
+    fn a_replacing_order_drops_the_others_and_starts_afresh() {
+        let mut queue = CommandQueue::default();
+        queue.push(HERE);
+        queue.push(AntCommand::ReturnHome);
+        queue.progress = CommandProgress::Underway;
+
+        let dropped = queue.replace(THERE);
+
+        assert_eq!(dropped, vec![HERE, AntCommand::ReturnHome]);
+        assert_eq!(queue.commands, [THERE]);
+        assert_eq!(queue.progress, CommandProgress::NotStarted);
+    }
+
+    #[test]
+    fn finishing_an_order_moves_on_to_the_next() {
+        let mut queue = CommandQueue::default();
+        queue.push(HERE);
+        queue.push(THERE);
+        queue.progress = CommandProgress::Underway;
+
+        assert_eq!(queue.finish(), Some(HERE));
+        assert_eq!(queue.current(), Some(THERE));
+        assert_eq!(queue.progress, CommandProgress::NotStarted);
+        assert_eq!(queue.finish(), Some(THERE));
+        assert_eq!(queue.finish(), None);
+    }
+}
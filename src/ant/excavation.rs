@@ -0,0 +1,819 @@
+use crate::ant::carrying::pick_up;
+use crate::ant::components::{
+    Ant, AntRole, Behavior, CarriedItem, Carrying, WorkerState, PREFERRED_DIG_ANGLE,
+};
+use crate::ant::pathfinding::{find_nearest_accessible_point, grid_line, GridPos};
+use crate::colony::{nearest_colony, Colony, ColonyMember};
+use crate::terrain::designation::{Designation, Designations};
+use crate::terrain::{update_tile, AirTile, TileStore, TileUpdateEvent, TILE_SIZE};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::collections::HashSet;
+
+// Width (in tiles) of tunnels dug on command
+pub const DEFAULT_TUNNEL_WIDTH: i32 = 2;
+// Horizontal length of each leg when a steep tunnel switches back and forth
+pub const SWITCHBACK_LENGTH: f32 = 6.0 * TILE_SIZE;
+// Seconds an ant needs to loosen and remove one tile
+pub const DIG_TIME: f32 = 0.4;
+// Ants start digging once they are this close to their tile
+pub const DIG_REACH: f32 = TILE_SIZE * 1.6;
+// A job is dropped after this many ants failed to reach it
+pub const MAX_DIG_ATTEMPTS: u32 = 3;
+// Seconds a job nobody could reach is left alone before it's handed out
+// again, for each failed attempt
+pub const DIG_RETRY_DELAY: f32 = 5.0;
+// Jobs for tiles the player designated for digging, which belong to no plan
+pub const DESIGNATED_PLAN_ID: u32 = u32::MAX;
+
+// A tunnel to be excavated, as the ordered list of solid tiles to remove
+#[derive(Debug, Clone)]
+pub struct TunnelPlan {
+    pub id: u32,
+    pub colony_id: Entity,
+    pub target: Vec2,
+    pub direction: Vec2,
+    pub width: i32,
+    pub slope: f32,
+    pub tiles: Vec<GridPos>,
+}
+
+// A single tile of a tunnel plan waiting to be dug
+#[derive(Debug, Clone, Copy)]
+pub struct DigJob {
+    pub tile: GridPos,
+    pub plan_id: u32,
+    pub colony_id: Entity,
+    pub assigned_to: Option<Entity>,
+    pub progress: f32,
+    pub attempts: u32,
+    // Seconds left before the job can be claimed again after a failed attempt
+    pub retry_in: f32,
+}
+
+// The far end of a finished tunnel, where it can be extended later
+#[derive(Debug, Clone, Copy)]
+pub struct TunnelEnd {
+    pub colony_id: Entity,
+    pub position: Vec2,
+    pub direction: Vec2,
+}
+
+#[derive(Resource, Default)]
+pub struct ExcavationPlans {
+    next_id: u32,
+    pub plans: Vec<TunnelPlan>,
+    pub jobs: Vec<DigJob>,
+    pub tunnel_ends: Vec<TunnelEnd>,
+    // Tiles the player has forbidden digging
+    forbidden: HashSet<GridPos>,
+    // Tiles filled in on purpose, such as a plugged entrance, left alone
+    // until they're unsealed
+    sealed: HashSet<GridPos>,
+}
+
+impl ExcavationPlans {
+    // Plan a tunnel for a colony and queue its tiles as dig jobs. Returns the
+    // plan id, or None when there is nothing to dig between the two points.
+    pub fn add_plan(
+        &mut self,
+        colony_id: Entity,
+        start: Vec2,
+        target: Vec2,
+        width: i32,
+        max_slope: f32,
+        tile_store: &TileStore,
+    ) -> Option<u32> {
+        let mut plan = plan_tunnel(start, target, width, max_slope, |pos| {
+            tile_store.is_solid(&pos.to_vec2())
+                && !self.forbidden.contains(&pos)
+                && !self.sealed.contains(&pos)
+        });
+        if plan.tiles.is_empty() {
+            return None;
+        }
+
+        plan.id = self.next_id;
+        plan.colony_id = colony_id;
+        self.next_id += 1;
+
+        println!(
+            "Planned tunnel {} for colony {:?} from {:?} to {:?}: {} tiles, width {}, slope {:.2}",
+            plan.id,
+            colony_id,
+            start,
+            target,
+            plan.tiles.len(),
+            plan.width,
+            plan.slope
+        );
+
+        self.jobs.extend(plan.tiles.iter().map(|&tile| DigJob {
+            tile,
+            plan_id: plan.id,
+            colony_id,
+            assigned_to: None,
+            progress: 0.0,
+            attempts: 0,
+            retry_in: 0.0,
+        }));
+        self.plans.push(plan);
+        Some(self.next_id - 1)
+    }
+
+    // Plans are dug in order from their start, a few tiles at a time, so the
+    // next tiles are always reachable through the part already dug. Designated
+    // tiles may be dug in any order, since only the ones next to open ground
+    // can be reached anyway.
+    fn is_job_open(&self, index: usize) -> bool {
+        let job = &self.jobs[index];
+        if job.plan_id == DESIGNATED_PLAN_ID {
+            return true;
+        }
+        let width = self
+            .plans
+            .iter()
+            .find(|plan| plan.id == job.plan_id)
+            .map_or(1, |plan| plan.width);
+        let earlier = self.jobs[..index]
+            .iter()
+            .filter(|other| other.plan_id == job.plan_id)
+            .count();
+        earlier < (width * 2) as usize
+    }
+
+    // Queue a single tile for a colony to dig on its own, like a designated
+    // one, unless it's off limits or already queued
+    pub fn queue_dig(&mut self, colony_id: Entity, tile: GridPos) {
+        if self.forbidden.contains(&tile)
+            || self.sealed.contains(&tile)
+            || self.jobs.iter().any(|job| job.tile == tile)
+        {
+            return;
+        }
+        self.jobs.push(DigJob {
+            tile,
+            plan_id: DESIGNATED_PLAN_ID,
+            colony_id,
+            assigned_to: None,
+            progress: 0.0,
+            attempts: 0,
+            retry_in: 0.0,
+        });
+    }
+
+    // Keep ants from digging out a tile that was filled in on purpose
+    pub fn seal(&mut self, tile: GridPos, tile_store: &TileStore) {
+        self.sealed.insert(tile);
+        self.jobs.retain(|job| job.tile != tile);
+        self.remove_finished_plans(tile_store);
+    }
+
+    // Let a sealed tile be dug again, and queue it for the colony to dig
+    pub fn unseal(&mut self, colony_id: Entity, tile: GridPos) {
+        self.sealed.remove(&tile);
+        self.queue_dig(colony_id, tile);
+    }
+
+    pub fn has_plan(&self, plan_id: u32) -> bool {
+        self.plans.iter().any(|plan| plan.id == plan_id)
+    }
+
+    pub fn plans_of(&self, colony_id: Entity) -> impl Iterator<Item = &TunnelPlan> + '_ {
+        self.plans
+            .iter()
+            .filter(move |plan| plan.colony_id == colony_id)
+    }
+
+    pub fn jobs_of(&self, colony_id: Entity) -> impl Iterator<Item = &DigJob> + '_ {
+        self.jobs
+            .iter()
+            .filter(move |job| job.colony_id == colony_id)
+    }
+
+    // Give an ant the open job of its colony nearest to it, from one plan or
+    // any, and send it to a spot next to the tile. Returns false when there
+    // was none.
+    pub fn claim_nearest_job(
+        &mut self,
+        entity: Entity,
+        colony_id: Entity,
+        ant: &mut Ant,
+        position: Vec2,
+        plan_id: Option<u32>,
+        tile_store: &TileStore,
+    ) -> bool {
+        let choice = (0..self.jobs.len())
+            .filter(|&index| {
+                let job = &self.jobs[index];
+                job.assigned_to.is_none()
+                    && job.colony_id == colony_id
+                    && job.retry_in <= 0.0
+                    && plan_id.map_or(true, |id| job.plan_id == id)
+                    && self.is_job_open(index)
+            })
+            .filter_map(|index| {
+                let spot = standing_spots(self.jobs[index].tile, tile_store)
+                    .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))?;
+                Some((index, spot))
+            })
+            .min_by(|(_, a), (_, b)| a.distance(position).total_cmp(&b.distance(position)));
+
+        let Some((index, spot)) = choice else {
+            return false;
+        };
+        let job = &mut self.jobs[index];
+        println!(
+            "Assigning dig job at {:?} (plan {}) to ant {:?}",
+            job.tile, job.plan_id, entity
+        );
+        job.assigned_to = Some(entity);
+        ant.worker_state = WorkerState::MovingToDigSite(job.tile.to_vec2());
+        ant.head_to(spot);
+        true
+    }
+
+    // Drop plans with no tiles left to dig, remembering where they ended.
+    // A plan whose last tiles were abandoned or sealed never broke through, so
+    // there's no tunnel end to extend.
+    fn remove_finished_plans(&mut self, tile_store: &TileStore) {
+        let jobs = &self.jobs;
+        let tunnel_ends = &mut self.tunnel_ends;
+        self.plans.retain(|plan| {
+            let finished = !jobs.iter().any(|job| job.plan_id == plan.id);
+            if finished && tile_store.is_solid(&plan.target) {
+                println!("Tunnel plan {} given up before it was dug through", plan.id);
+            } else if finished {
+                println!("Tunnel plan {} complete", plan.id);
+                tunnel_ends.push(TunnelEnd {
+                    colony_id: plan.colony_id,
+                    position: plan.target,
+                    direction: plan.direction,
+                });
+            }
+            !finished
+        });
+    }
+}
+
+// Lay out the centre line of a tunnel. Tunnels steeper than `max_slope`
+// switch back and forth at that angle until the target can be reached
+// directly, the way real ants avoid digging straight down. A limit of a right
+// angle or more digs straight there, however steep.
+fn tunnel_centerline(start: Vec2, target: Vec2, max_slope: f32) -> Vec<Vec2> {
+    if max_slope >= std::f32::consts::FRAC_PI_2 {
+        return vec![start, target];
+    }
+    let max_gradient = max_slope.tan();
+    let mut points = vec![start];
+    let mut current = start;
+    let mut heading_x = if target.x < start.x { -1.0 } else { 1.0 };
+    let leg_drop = SWITCHBACK_LENGTH * max_gradient;
+
+    // Bounded so a degenerate slope can never loop forever
+    for _ in 0..64 {
+        let remaining = target - current;
+        if remaining.y.abs() <= remaining.x.abs() * max_gradient {
+            break;
+        }
+
+        let drop = remaining.y.abs().min(leg_drop) * remaining.y.signum();
+        current += Vec2::new(heading_x * SWITCHBACK_LENGTH, drop);
+        points.push(current);
+        heading_x = -heading_x;
+    }
+
+    points.push(target);
+    points
+}
+
+// Produce a tunnel plan between two points as the solid tiles to remove, in
+// digging order, with each centre line cell widened to `width` tiles.
+pub fn plan_tunnel(
+    start: Vec2,
+    target: Vec2,
+    width: i32,
+    max_slope: f32,
+    is_solid: impl Fn(GridPos) -> bool,
+) -> TunnelPlan {
+    let width = width.max(1);
+    let centerline = tunnel_centerline(start, target, max_slope);
+
+    let mut tiles = Vec::new();
+    let mut slope: f32 = 0.0;
+
+    for leg in centerline.windows(2) {
+        let delta = leg[1] - leg[0];
+        slope = slope.max(delta.y.abs().atan2(delta.x.abs()));
+
+        // Widen across the direction of travel
+        let widen_vertically = delta.x.abs() >= delta.y.abs();
+
+        for cell in grid_line(GridPos::from_vec2(leg[0]), GridPos::from_vec2(leg[1])) {
+            for offset in 0..width {
+                let offset = offset - (width - 1) / 2;
+                let tile = if widen_vertically {
+                    GridPos {
+                        x: cell.x,
+                        y: cell.y + offset,
+                    }
+                } else {
+                    GridPos {
+                        x: cell.x + offset,
+                        y: cell.y,
+                    }
+                };
+                if is_solid(tile) && !tiles.contains(&tile) {
+                    tiles.push(tile);
+                }
+            }
+        }
+    }
+
+    TunnelPlan {
+        id: 0,
+        colony_id: Entity::PLACEHOLDER,
+        target,
+        direction: (target - start).normalize_or_zero(),
+        width,
+        slope,
+        tiles,
+    }
+}
+
+// The open tiles next to `tile` that an ant could stand on while digging it
+fn standing_spots(tile: GridPos, tile_store: &TileStore) -> impl Iterator<Item = Vec2> + '_ {
+    [(1, 0), (-1, 0), (0, 1), (0, -1)]
+        .into_iter()
+        .map(move |(dx, dy)| {
+            GridPos {
+                x: tile.x + dx,
+                y: tile.y + dy,
+            }
+            .to_vec2()
+        })
+        .filter(|pos| {
+            tile_store
+                .get_tile(pos)
+                .is_some_and(|t| !t.tile_type.is_solid())
+        })
+}
+
+// Turn the player's designations into work: tiles marked for digging or for
+// a chamber become dig jobs for the nearest colony, and no tile marked off
+// limits is dug
+pub fn apply_designations(
+    designations: Res<Designations>,
+    tile_store: Res<TileStore>,
+    colony_query: Query<(Entity, &Colony)>,
+    mut plans: ResMut<ExcavationPlans>,
+) {
+    if !designations.is_changed() {
+        return;
+    }
+    let plans = &mut *plans;
+
+    plans.forbidden = designations
+        .iter()
+        .filter(|&(_, designation)| designation == Designation::NoDig)
+        .map(|(position, _)| GridPos::from_vec2(position))
+        .collect();
+    let forbidden = &plans.forbidden;
+    plans.jobs.retain(|job| !forbidden.contains(&job.tile));
+    plans.remove_finished_plans(&tile_store);
+
+    let queued: HashSet<GridPos> = plans.jobs.iter().map(|job| job.tile).collect();
+    let wanted: HashSet<GridPos> = designations
+        .iter()
+        .filter(|&(position, designation)| {
+            designation.wants_digging() && tile_store.is_solid(&position)
+        })
+        .map(|(position, _)| GridPos::from_vec2(position))
+        .collect();
+    let mut added = 0;
+    for &tile in wanted.difference(&queued) {
+        let Some((colony_id, _)) = nearest_colony(colony_query.iter(), tile.to_vec2()) else {
+            continue;
+        };
+        plans.jobs.push(DigJob {
+            tile,
+            plan_id: DESIGNATED_PLAN_ID,
+            colony_id,
+            assigned_to: None,
+            progress: 0.0,
+            attempts: 0,
+            retry_in: 0.0,
+        });
+        added += 1;
+    }
+    if added > 0 {
+        println!("Queued {} designated tiles for digging", added);
+    }
+
+    // Areas buried away from any tunnel get one dug out to them, from the
+    // nearest colony, which the designated tiles went to as well
+    let solid_tiles = tile_store.get_solid_tiles();
+    for area in connected_areas(&wanted) {
+        let reachable = area
+            .iter()
+            .any(|&tile| standing_spots(tile, &tile_store).next().is_some());
+        if reachable {
+            continue;
+        }
+        let Some((colony_id, colony)) = nearest_colony(colony_query.iter(), area[0].to_vec2())
+        else {
+            continue;
+        };
+        let Some(closest) = area
+            .iter()
+            .min_by(|a, b| {
+                let (a, b) = (a.to_vec2(), b.to_vec2());
+                a.distance(colony.position)
+                    .total_cmp(&b.distance(colony.position))
+            })
+            .map(|tile| tile.to_vec2())
+        else {
+            continue;
+        };
+        if let Some(start) = find_nearest_accessible_point(colony.position, closest, &solid_tiles) {
+            plans.add_plan(
+                colony_id,
+                start,
+                closest,
+                DEFAULT_TUNNEL_WIDTH,
+                PREFERRED_DIG_ANGLE,
+                &tile_store,
+            );
+        }
+    }
+}
+
+// Split tiles into groups that touch each other
+fn connected_areas(tiles: &HashSet<GridPos>) -> Vec<Vec<GridPos>> {
+    let mut seen = HashSet::new();
+    let mut areas = Vec::new();
+    for &first in tiles {
+        if !seen.insert(first) {
+            continue;
+        }
+        let mut area = vec![first];
+        let mut index = 0;
+        while index < area.len() {
+            let tile = area[index];
+            index += 1;
+            for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+                let next = GridPos {
+                    x: tile.x + dx,
+                    y: tile.y + dy,
+                };
+                if tiles.contains(&next) && seen.insert(next) {
+                    area.push(next);
+                }
+            }
+        }
+        areas.push(area);
+    }
+    areas
+}
+
+// Hand out reachable dig jobs to idle workers of the colony they belong to
+pub fn assign_dig_jobs(
+    time: Res<Time>,
+    mut plans: ResMut<ExcavationPlans>,
+    tile_store: Res<TileStore>,
+    mut ants: Query<(Entity, &Transform, &mut Ant, &ColonyMember)>,
+) {
+    let plans = &mut *plans;
+
+    // Tiles that were opened some other way don't need digging any more
+    plans
+        .jobs
+        .retain(|job| tile_store.is_solid(&job.tile.to_vec2()));
+    plans.remove_finished_plans(&tile_store);
+
+    // Release jobs whose ant has moved on to something else
+    for job in plans.jobs.iter_mut() {
+        job.retry_in = (job.retry_in - time.delta_seconds()).max(0.0);
+        if let Some(ant_entity) = job.assigned_to {
+            let still_working = ants.get(ant_entity).is_ok_and(|(_, _, ant, _)| {
+                matches!(
+                    ant.worker_state,
+                    WorkerState::MovingToDigSite(site) | WorkerState::Digging(site)
+                        if GridPos::from_vec2(site) == job.tile
+                )
+            });
+            if !still_working {
+                job.assigned_to = None;
+                job.progress = 0.0;
+            }
+        }
+    }
+
+    for (entity, transform, mut ant, colony_member) in ants.iter_mut() {
+        if ant.role != AntRole::Worker
+            || ant.behavior != Behavior::Work
+            || ant.worker_state != WorkerState::SearchingForDigSite
+            || ant.target_position.is_some()
+        {
+            continue;
+        }
+
+        let current_pos = transform.translation.truncate();
+        plans.claim_nearest_job(
+            entity,
+            colony_member.colony_id,
+            &mut ant,
+            current_pos,
+            None,
+            &tile_store,
+        );
+    }
+}
+
+// Dig assigned tiles once the ant has reached them. The loosened dirt is
+// picked up and hauled out of the nest.
+pub fn excavate_tiles(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut plans: ResMut<ExcavationPlans>,
+    mut tile_store: ResMut<TileStore>,
+    mut tile_update_events: EventWriter<TileUpdateEvent>,
+    mut ants: Query<(Entity, &Transform, &mut Ant, &mut Velocity, Has<Carrying>)>,
+) {
+    for (entity, transform, mut ant, mut velocity, carrying) in ants.iter_mut() {
+        let current_pos = transform.translation.truncate();
+
+        match ant.worker_state {
+            WorkerState::MovingToDigSite(site) => {
+                if current_pos.distance(site) <= DIG_REACH {
+                    ant.worker_state = WorkerState::Digging(site);
+                    ant.target_position = None;
+                    ant.current_path = None;
+                    ant.current_path_index = 0;
+                    velocity.linvel = Vec2::ZERO;
+                } else if ant.target_position.is_none() {
+                    ant.worker_state = WorkerState::SearchingForDigSite;
+                    let tile = GridPos::from_vec2(site);
+                    let claimed = plans
+                        .jobs
+                        .iter()
+                        .position(|job| job.tile == tile && job.assigned_to == Some(entity));
+
+                    // Fighting or heading back into range clears the target
+                    // too, which says nothing about whether the site can be
+                    // reached. The job is handed back for someone else.
+                    if ant.behavior != Behavior::Work {
+                        if let Some(index) = claimed {
+                            plans.jobs[index].assigned_to = None;
+                        }
+                        continue;
+                    }
+
+                    println!("Ant {:?} could not reach dig site {:?}", entity, site);
+                    if let Some(index) = claimed {
+                        let job = &mut plans.jobs[index];
+                        job.assigned_to = None;
+                        job.attempts += 1;
+                        // Leave it be for a while rather than sending the
+                        // next idle ant straight back down the same dead end
+                        job.retry_in = DIG_RETRY_DELAY * job.attempts as f32;
+                        if job.attempts >= MAX_DIG_ATTEMPTS {
+                            println!("Abandoning unreachable dig job at {:?}", tile);
+                            plans.jobs.remove(index);
+                            plans.remove_finished_plans(&tile_store);
+                        }
+                    }
+                }
+            }
+            WorkerState::Digging(site) => {
+                let tile = GridPos::from_vec2(site);
+                let Some(index) = plans
+                    .jobs
+                    .iter()
+                    .position(|job| job.tile == tile && job.assigned_to == Some(entity))
+                else {
+                    ant.worker_state = WorkerState::SearchingForDigSite;
+                    continue;
+                };
+
+                velocity.linvel = Vec2::ZERO;
+                plans.jobs[index].progress += time.delta_seconds();
+                if plans.jobs[index].progress < DIG_TIME {
+                    continue;
+                }
+
+                plans.jobs.remove(index);
+                if update_tile(
+                    &mut tile_store,
+                    &mut tile_update_events,
+                    site,
+                    Box::new(AirTile),
+                ) {
+                    println!("Ant {:?} dug tile at {:?}", entity, tile);
+                    if !carrying {
+                        pick_up(&mut commands, entity, CarriedItem::Dirt);
+                    }
+                    ant.worker_state = WorkerState::HaulingSpoil;
+                } else {
+                    ant.worker_state = WorkerState::SearchingForDigSite;
+                }
+                plans.remove_finished_plans(&tile_store);
+            }
+            WorkerState::SearchingForDigSite
+            | WorkerState::HaulingSpoil
+            | WorkerState::MovingToCorpse(_)
+            | WorkerState::HaulingCorpse
+            | WorkerState::PluggingEntrance => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::DirtTile;
+    use std::f32::consts::{FRAC_PI_2, FRAC_PI_4};
+
+    fn cell(x: i32, y: i32) -> GridPos {
+        GridPos { x, y }
+    }
+
+    #[test]
+    fn plan_tunnel_digs_a_level_tunnel_from_its_start() {
+        let plan = plan_tunnel(Vec2::ZERO, cell(5, 0).to_vec2(), 2, FRAC_PI_4, |_| true);
+
+        // Six centre line cells, each widened by the tile above it
+        assert_eq!(plan.tiles.len(), 12);
+        assert_eq!(plan.tiles[..2], [cell(0, 0), cell(0, 1)]);
+        assert_eq!(plan.tiles[10..], [cell(5, 0), cell(5, 1)]);
+        assert_eq!(plan.slope, 0.0);
+        assert_eq!(plan.direction, Vec2::X);
+    }
+
+    #[test]
+    fn plan_tunnel_skips_tiles_that_are_already_open() {
+        let plan = plan_tunnel(Vec2::ZERO, cell(5, 0).to_vec2(), 1, FRAC_PI_4, |pos| {
+            pos.x >= 3
+        });
+        assert_eq!(plan.tiles, vec![cell(3, 0), cell(4, 0), cell(5, 0)]);
+    }
+
+    #[test]
+    fn plan_tunnel_switches_back_on_steep_descents() {
+        let target = cell(0, -20).to_vec2();
+        let plan = plan_tunnel(Vec2::ZERO, target, 1, FRAC_PI_4, |_| true);
+
+        assert!(plan.slope <= FRAC_PI_4 + 1e-4, "slope {}", plan.slope);
+        assert!(plan.tiles.iter().any(|tile| tile.x != 0));
+        assert_eq!(plan.tiles.last(), Some(&GridPos::from_vec2(target)));
+    }
+
+    #[test]
+    fn plan_tunnel_digs_straight_down_when_allowed() {
+        let plan = plan_tunnel(Vec2::ZERO, cell(0, -6).to_vec2(), 1, FRAC_PI_2, |_| true);
+        assert_eq!(
+            plan.tiles,
+            (-6..=0).rev().map(|y| cell(0, y)).collect::<Vec<_>>()
+        );
+        assert_eq!(plan.slope, FRAC_PI_2);
+    }
+
+    // A level row of dirt with an open tile at the origin to dig from
+    fn dirt_row(length: i32) -> TileStore {
+        let mut tile_store = TileStore::new();
+        tile_store.add_tile(Vec2::ZERO, Box::new(AirTile));
+        for x in 1..=length {
+            tile_store.add_tile(cell(x, 0).to_vec2(), Box::new(DirtTile));
+        }
+        tile_store
+    }
+
+    #[test]
+    fn a_dug_through_plan_leaves_a_tunnel_end() {
+        let mut tile_store = dirt_row(5);
+        let mut plans = ExcavationPlans::default();
+        let target = cell(5, 0).to_vec2();
+        plans.add_plan(
+            Entity::PLACEHOLDER,
+            Vec2::ZERO,
+            target,
+            1,
+            FRAC_PI_4,
+            &tile_store,
+        );
+
+        for job in plans.jobs.drain(..) {
+            tile_store.add_tile(job.tile.to_vec2(), Box::new(AirTile));
+        }
+        plans.remove_finished_plans(&tile_store);
+
+        assert!(plans.plans.is_empty());
+        assert_eq!(plans.tunnel_ends.len(), 1);
+        assert_eq!(plans.tunnel_ends[0].position, target);
+        assert_eq!(plans.tunnel_ends[0].colony_id, Entity::PLACEHOLDER);
+    }
+
+    #[test]
+    fn an_abandoned_or_sealed_plan_leaves_no_tunnel_end() {
+        let tile_store = dirt_row(5);
+        let mut plans = ExcavationPlans::default();
+        plans.add_plan(
+            Entity::PLACEHOLDER,
+            Vec2::ZERO,
+            cell(5, 0).to_vec2(),
+            1,
+            FRAC_PI_4,
+            &tile_store,
+        );
+
+        // The first tiles were dug, the rest given up on
+        plans.jobs.truncate(1);
+        plans.seal(plans.jobs[0].tile, &tile_store);
+
+        assert!(plans.plans.is_empty());
+        assert!(plans.tunnel_ends.is_empty());
+    }
+
+    #[test]
+    fn ants_only_claim_jobs_of_their_own_colony() {
+        let tile_store = dirt_row(1);
+        let (ours, theirs) = (Entity::from_raw(1), Entity::from_raw(2));
+        let mut plans = ExcavationPlans::default();
+        plans.queue_dig(theirs, cell(1, 0));
+
+        let mut ant = Ant::default();
+        let ant_entity = Entity::from_raw(3);
+        assert!(!plans.claim_nearest_job(
+            ant_entity,
+            ours,
+            &mut ant,
+            Vec2::ZERO,
+            None,
+            &tile_store
+        ));
+        assert_eq!(plans.jobs_of(ours).count(), 0);
+
+        assert!(plans.claim_nearest_job(
+            ant_entity,
+            theirs,
+            &mut ant,
+            Vec2::ZERO,
+            None,
+            &tile_store
+        ));
+        assert_eq!(plans.jobs[0].assigned_to, Some(ant_entity));
+    }
+
+    // Runs `excavate_tiles` once for an ant on its way to a dig job that has
+    // just lost its target while doing `behavior`, and returns the job
+    fn lose_target_while(behavior: Behavior) -> DigJob {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .insert_resource(dirt_row(5))
+            .add_event::<TileUpdateEvent>()
+            .add_systems(Update, excavate_tiles);
+        let site = cell(5, 0).to_vec2();
+        let ant = app
+            .world
+            .spawn((
+                Transform::default(),
+                Ant {
+                    behavior,
+                    worker_state: WorkerState::MovingToDigSite(site),
+                    ..default()
+                },
+                Velocity::default(),
+            ))
+            .id();
+        let mut plans = ExcavationPlans::default();
+        plans.queue_dig(Entity::PLACEHOLDER, GridPos::from_vec2(site));
+        plans.jobs[0].assigned_to = Some(ant);
+        app.insert_resource(plans);
+
+        app.update();
+
+        assert_eq!(
+            app.world.get::<Ant>(ant).unwrap().worker_state,
+            WorkerState::SearchingForDigSite
+        );
+        app.world.resource::<ExcavationPlans>().jobs[0]
+    }
+
+    #[test]
+    fn a_working_ant_that_loses_its_way_counts_a_failed_attempt() {
+        let job = lose_target_while(Behavior::Work);
+        assert_eq!(job.assigned_to, None);
+        assert_eq!(job.attempts, 1);
+        assert!(job.retry_in > 0.0);
+    }
+
+    #[test]
+    fn an_ant_called_away_hands_its_dig_job_back_without_a_failure() {
+        for behavior in [Behavior::Fight, Behavior::ReturnToRange] {
+            let job = lose_target_while(behavior);
+            assert_eq!(job.assigned_to, None);
+            assert_eq!(job.attempts, 0);
+            assert_eq!(job.retry_in, 0.0);
+        }
+    }
+}
@@ -1,21 +1,112 @@
 mod app;
-mod components;
-mod pathfinding;
+mod avoidance;
+mod behavior;
+mod carrying;
+pub(crate) mod components;
+mod excavation;
+pub(crate) mod pathfinding;
+mod steering;
 mod systems;
 
 pub use app::run_app;
 use bevy::prelude::*;
+use excavation::{apply_designations, assign_dig_jobs, excavate_tiles, ExcavationPlans};
 use systems::ant_movement::ant_movement;
-use systems::mouse_click::handle_mouse_click;
-use systems::{handle_spacebar_spawn, spawn_initial_ant};
+use systems::brood::develop_brood;
+use systems::chambers::{survey_chambers, ChamberSurvey};
+use systems::combat::{fight, suffer_predator_bites, CombatRng, DEFAULT_COMBAT_SEED};
+use systems::corpse_disposal::dispose_of_corpses;
+use systems::decision::decide_behavior;
+use systems::dig_site_search::search_for_dig_sites;
+use systems::eating::eat_at_nest;
+use systems::flood_defense::{clear_cave_ins, plug_entrances};
+use systems::foraging::forage;
+use systems::homing::return_to_range;
+use systems::mouse_click::{handle_mouse_click, handle_order_keys};
+use systems::nursing::tend_brood;
+use systems::orders::{carry_out_orders, report_finished_commands, CommandFinished};
+use systems::painting::{choose_paint_tool, paint_designations, DesignationTool};
+use systems::queen::{keep_queen_in_chamber, lay_eggs};
+use systems::raiding::{plan_raids, raid, RaidPlanner};
+use systems::scouting::scout;
+use systems::selection::{
+    draw_selection, handle_control_groups, handle_selection, ControlGroups, SelectionDrag,
+};
+use systems::soldiering::soldier_duty;
+use systems::spoil_hauling::haul_spoil;
+use systems::task_allocation::{allocate_tasks, answer_food_shortage, TaskAllocator};
+use systems::vitals::update_vitals;
+use systems::{mark_colony_members, spawn_initial_ants};
 
-pub struct AntPlugin;
+pub struct AntPlugin {
+    // Every bite in a fight is rolled from a generator seeded with this, so
+    // the same seed plays the same fights out the same way
+    pub combat_seed: u64,
+}
+
+impl Default for AntPlugin {
+    fn default() -> Self {
+        Self {
+            combat_seed: DEFAULT_COMBAT_SEED,
+        }
+    }
+}
 
 impl Plugin for AntPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_initial_ant).add_systems(
-            Update,
-            (ant_movement, handle_mouse_click, handle_spacebar_spawn),
-        );
+        app.init_resource::<ExcavationPlans>()
+            .init_resource::<TaskAllocator>()
+            .init_resource::<ControlGroups>()
+            .init_resource::<SelectionDrag>()
+            .init_resource::<DesignationTool>()
+            .init_resource::<ChamberSurvey>()
+            .insert_resource(CombatRng::seeded(self.combat_seed))
+            .init_resource::<RaidPlanner>()
+            .add_event::<CommandFinished>()
+            // Colonies are founded during startup, so their ants follow once
+            // the colonies exist
+            .add_systems(PostStartup, spawn_initial_ants)
+            .add_systems(
+                Update,
+                (
+                    (
+                        allocate_tasks,
+                        decide_behavior,
+                        carry_out_orders,
+                        fight,
+                        eat_at_nest,
+                        return_to_range,
+                        keep_queen_in_chamber,
+                        search_for_dig_sites,
+                        haul_spoil,
+                        dispose_of_corpses,
+                        tend_brood,
+                        plan_raids,
+                        raid,
+                        soldier_duty,
+                        scout,
+                        apply_designations,
+                        assign_dig_jobs,
+                        ant_movement,
+                        excavate_tiles,
+                    )
+                        .chain(),
+                    answer_food_shortage.before(allocate_tasks),
+                    forage.after(ant_movement),
+                    update_vitals.after(forage).after(excavate_tiles),
+                    suffer_predator_bites.before(update_vitals),
+                    survey_chambers.before(allocate_tasks),
+                    (plug_entrances, clear_cave_ins).before(search_for_dig_sites),
+                    (lay_eggs, develop_brood),
+                    (handle_mouse_click, handle_order_keys).before(decide_behavior),
+                    (handle_selection, handle_control_groups, draw_selection),
+                    (choose_paint_tool, paint_designations).chain(),
+                    report_finished_commands.after(carry_out_orders),
+                    mark_colony_members,
+                ),
+            );
+
+        #[cfg(feature = "debug")]
+        app.add_systems(Update, systems::debug_spawn::handle_spacebar_spawn);
     }
 }
@@ -3,8 +3,7 @@ use std::cmp::Ordering;
 use std::collections::{BinaryHeap, HashMap, HashSet};
 
 const GRID_SIZE: f32 = 8.0; // Same as TILE_SIZE
-const BASE_DIG_COST: i32 = 10; // Base cost for digging
-const MAX_DIG_DISTANCE: f32 = 50.0; // Maximum distance to consider direct digging
+const MAX_SEARCH_NODES: usize = 20_000; // Upper bound on nodes expanded per search
 
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
 pub struct GridPos {
@@ -50,7 +49,52 @@ impl PartialOrd for Node {
     }
 }
 
-// Find the nearest accessible point to a target
+// Neighbouring grid cells an ant can step to from `pos`, with their movement cost.
+// Diagonal steps are only allowed when both orthogonal cells are open, so ants
+// never cut the corner of a solid tile.
+fn walkable_neighbors(pos: GridPos, obstacles: &HashSet<GridPos>) -> Vec<(GridPos, i32)> {
+    let mut neighbors = Vec::with_capacity(8);
+    for dx in -1..=1 {
+        for dy in -1..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+
+            let neighbor_pos = GridPos {
+                x: pos.x + dx,
+                y: pos.y + dy,
+            };
+            if obstacles.contains(&neighbor_pos) {
+                continue;
+            }
+
+            // Calculate movement cost - diagonal movement costs more
+            let movement_cost = if dx != 0 && dy != 0 {
+                let side_a = GridPos {
+                    x: pos.x + dx,
+                    y: pos.y,
+                };
+                let side_b = GridPos {
+                    x: pos.x,
+                    y: pos.y + dy,
+                };
+                if obstacles.contains(&side_a) || obstacles.contains(&side_b) {
+                    continue;
+                }
+                14 // Approximately sqrt(2) * 10
+            } else {
+                10 // Base movement cost
+            };
+
+            neighbors.push((neighbor_pos, movement_cost));
+        }
+    }
+    neighbors
+}
+
+// Find the open point closest to a target that can be reached from `start`
+// without digging. Solid tiles are impassable; digging is planned separately
+// by the excavation planner.
 pub fn find_nearest_accessible_point(
     start: Vec2,
     target: Vec2,
@@ -58,12 +102,6 @@ pub fn find_nearest_accessible_point(
 ) -> Option<Vec2> {
     let start_pos = GridPos::from_vec2(start);
     let target_pos = GridPos::from_vec2(target);
-    let direct_distance = start_pos.distance(&target_pos);
-
-    // If target is very close, prefer digging directly
-    if direct_distance * GRID_SIZE <= MAX_DIG_DISTANCE {
-        return Some(target);
-    }
 
     // Convert solid tiles to grid positions
     let obstacles: HashSet<GridPos> = solid_tiles
@@ -71,9 +109,14 @@ pub fn find_nearest_accessible_point(
         .map(|&pos| GridPos::from_vec2(pos))
         .collect();
 
+    // If the target is in plain view there is nothing to search for
+    let target_blocked = obstacles.contains(&target_pos);
+    if !target_blocked && has_line_of_sight(start_pos, target_pos, |pos| obstacles.contains(&pos)) {
+        return Some(target);
+    }
+
     let mut open_set = BinaryHeap::new();
     let mut closed_set = HashSet::new();
-    let mut came_from = HashMap::new();
     let mut g_costs = HashMap::new();
     let mut nearest_point = None;
     let mut min_distance = f32::MAX;
@@ -88,6 +131,10 @@ pub fn find_nearest_accessible_point(
     g_costs.insert(start_pos, 0);
 
     while let Some(current) = open_set.pop() {
+        if !closed_set.insert(current.pos) {
+            continue;
+        }
+
         // Check if this is the nearest point we've found so far
         let distance_to_target = current.pos.distance(&target_pos);
         if distance_to_target < min_distance {
@@ -95,59 +142,32 @@ pub fn find_nearest_accessible_point(
             nearest_point = Some(current.pos.to_vec2());
         }
 
-        // If we're close enough to the target, we can stop
-        if distance_to_target < 2.0 {
+        // Stop on the target itself, or right next to it when it's solid,
+        // or once we have searched long enough
+        if distance_to_target < 0.5
+            || (target_blocked && distance_to_target < 1.5)
+            || closed_set.len() >= MAX_SEARCH_NODES
+        {
             break;
         }
 
-        closed_set.insert(current.pos);
-
-        // Check neighbors
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
-
-                let neighbor_pos = GridPos {
-                    x: current.pos.x + dx,
-                    y: current.pos.y + dy,
-                };
-
-                if closed_set.contains(&neighbor_pos) {
-                    continue;
-                }
-
-                // Calculate movement cost - diagonal movement costs more
-                let movement_cost = if dx.abs() == 1 && dy.abs() == 1 {
-                    14 // Approximately sqrt(2) * 10
-                } else {
-                    10 // Base movement cost
-                };
-
-                // Calculate digging cost based on distance to target
-                let total_cost = if obstacles.contains(&neighbor_pos) {
-                    let distance_factor = (direct_distance * GRID_SIZE / MAX_DIG_DISTANCE).min(1.0);
-                    let dig_cost = (BASE_DIG_COST as f32 * (1.0 + distance_factor)) as i32;
-                    movement_cost + dig_cost
-                } else {
-                    movement_cost
-                };
+        for (neighbor_pos, movement_cost) in walkable_neighbors(current.pos, &obstacles) {
+            if closed_set.contains(&neighbor_pos) {
+                continue;
+            }
 
-                let new_g_cost = g_costs[&current.pos] + total_cost;
-                if !g_costs.contains_key(&neighbor_pos) || new_g_cost < g_costs[&neighbor_pos] {
-                    came_from.insert(neighbor_pos, current.pos);
-                    g_costs.insert(neighbor_pos, new_g_cost);
-                    let h_cost = (neighbor_pos.distance(&target_pos) * 10.0) as i32;
-                    let f_cost = new_g_cost + h_cost;
-
-                    let neighbor = Node {
-                        pos: neighbor_pos,
-                        f_cost,
-                        g_cost: new_g_cost,
-                    };
-                    open_set.push(neighbor);
-                }
+            let new_g_cost = current.g_cost + movement_cost;
+            if g_costs
+                .get(&neighbor_pos)
+                .map_or(true, |&cost| new_g_cost < cost)
+            {
+                g_costs.insert(neighbor_pos, new_g_cost);
+                let h_cost = (neighbor_pos.distance(&target_pos) * 10.0) as i32;
+                open_set.push(Node {
+                    pos: neighbor_pos,
+                    f_cost: new_g_cost + h_cost,
+                    g_cost: new_g_cost,
+                });
             }
         }
     }
@@ -155,15 +175,10 @@ pub fn find_nearest_accessible_point(
     nearest_point
 }
 
+// Find a walkable path between two points through open tiles only
 pub fn find_path(start: Vec2, end: Vec2, solid_tiles: &[Vec2]) -> Option<Vec<Vec2>> {
     let start_pos = GridPos::from_vec2(start);
     let end_pos = GridPos::from_vec2(end);
-    let direct_distance = start_pos.distance(&end_pos);
-
-    // If target is very close, prefer digging directly
-    if direct_distance * GRID_SIZE <= MAX_DIG_DISTANCE {
-        return Some(vec![start, end]);
-    }
 
     // Convert solid tiles to grid positions
     let obstacles: HashSet<GridPos> = solid_tiles
@@ -171,6 +186,11 @@ pub fn find_path(start: Vec2, end: Vec2, solid_tiles: &[Vec2]) -> Option<Vec<Vec
         .map(|&pos| GridPos::from_vec2(pos))
         .collect();
 
+    // Walk straight there if nothing is in the way
+    if has_line_of_sight(start_pos, end_pos, |pos| obstacles.contains(&pos)) {
+        return Some(vec![start, end]);
+    }
+
     let mut open_set = BinaryHeap::new();
     let mut closed_set = HashSet::new();
     let mut came_from = HashMap::new();
@@ -199,57 +219,224 @@ pub fn find_path(start: Vec2, end: Vec2, solid_tiles: &[Vec2]) -> Option<Vec<Vec
             return Some(path);
         }
 
-        closed_set.insert(current.pos);
+        if !closed_set.insert(current.pos) {
+            continue;
+        }
+        if closed_set.len() >= MAX_SEARCH_NODES {
+            break;
+        }
 
-        // Check neighbors
-        for dx in -1..=1 {
-            for dy in -1..=1 {
-                if dx == 0 && dy == 0 {
-                    continue;
-                }
+        for (neighbor_pos, movement_cost) in walkable_neighbors(current.pos, &obstacles) {
+            if closed_set.contains(&neighbor_pos) {
+                continue;
+            }
 
-                let neighbor_pos = GridPos {
-                    x: current.pos.x + dx,
-                    y: current.pos.y + dy,
-                };
+            let new_g_cost = current.g_cost + movement_cost;
+            if g_costs
+                .get(&neighbor_pos)
+                .map_or(true, |&cost| new_g_cost < cost)
+            {
+                came_from.insert(neighbor_pos, current.pos);
+                g_costs.insert(neighbor_pos, new_g_cost);
+                let h_cost = (neighbor_pos.distance(&end_pos) * 10.0) as i32;
+                open_set.push(Node {
+                    pos: neighbor_pos,
+                    f_cost: new_g_cost + h_cost,
+                    g_cost: new_g_cost,
+                });
+            }
+        }
+    }
 
-                if closed_set.contains(&neighbor_pos) {
-                    continue;
-                }
+    None // No path found
+}
 
-                // Calculate movement cost - diagonal movement costs more
-                let movement_cost = if dx.abs() == 1 && dy.abs() == 1 {
-                    14 // Approximately sqrt(2) * 10
-                } else {
-                    10 // Base movement cost
-                };
+// Every grid cell touched by the straight segment between two cells, in order.
+// When the segment passes exactly through a corner both side cells are
+// included, so the result is always 4-connected.
+pub fn grid_line(from: GridPos, to: GridPos) -> Vec<GridPos> {
+    let dx = to.x - from.x;
+    let dy = to.y - from.y;
+    let nx = dx.abs();
+    let ny = dy.abs();
+    let step_x = dx.signum();
+    let step_y = dy.signum();
+
+    let mut cells = vec![from];
+    let mut current = from;
+    let mut ix = 0;
+    let mut iy = 0;
+
+    while ix < nx || iy < ny {
+        let decision = (1 + 2 * ix) * ny - (1 + 2 * iy) * nx;
+        if decision == 0 {
+            // Passing through a corner - include both side cells
+            cells.push(GridPos {
+                x: current.x + step_x,
+                y: current.y,
+            });
+            cells.push(GridPos {
+                x: current.x,
+                y: current.y + step_y,
+            });
+            current.x += step_x;
+            current.y += step_y;
+            ix += 1;
+            iy += 1;
+        } else if decision < 0 {
+            current.x += step_x;
+            ix += 1;
+        } else {
+            current.y += step_y;
+            iy += 1;
+        }
+        cells.push(current);
+    }
 
-                // Calculate digging cost based on distance to target
-                let total_cost = if obstacles.contains(&neighbor_pos) {
-                    let distance_factor = (direct_distance * GRID_SIZE / MAX_DIG_DISTANCE).min(1.0);
-                    let dig_cost = (BASE_DIG_COST as f32 * (1.0 + distance_factor)) as i32;
-                    movement_cost + dig_cost
-                } else {
-                    movement_cost
-                };
+    cells
+}
 
-                let new_g_cost = g_costs[&current.pos] + total_cost;
-                if !g_costs.contains_key(&neighbor_pos) || new_g_cost < g_costs[&neighbor_pos] {
-                    came_from.insert(neighbor_pos, current.pos);
-                    g_costs.insert(neighbor_pos, new_g_cost);
-                    let h_cost = (neighbor_pos.distance(&end_pos) * 10.0) as i32;
-                    let f_cost = new_g_cost + h_cost;
-
-                    let neighbor = Node {
-                        pos: neighbor_pos,
-                        f_cost,
-                        g_cost: new_g_cost,
-                    };
-                    open_set.push(neighbor);
-                }
+// True when no blocked cell lies on the straight segment between two cells
+pub fn has_line_of_sight(from: GridPos, to: GridPos, is_blocked: impl Fn(GridPos) -> bool) -> bool {
+    grid_line(from, to)
+        .into_iter()
+        .all(|cell| !is_blocked(cell))
+}
+
+// Remove redundant waypoints from a grid path by skipping ahead to the furthest
+// waypoint that is still in direct line of sight.
+pub fn smooth_path(path: &[Vec2], is_blocked: impl Fn(GridPos) -> bool) -> Vec<Vec2> {
+    if path.len() <= 2 {
+        return path.to_vec();
+    }
+
+    let mut smoothed = vec![path[0]];
+    let mut anchor = 0;
+
+    while anchor < path.len() - 1 {
+        let anchor_pos = GridPos::from_vec2(path[anchor]);
+        let mut furthest = anchor + 1;
+        for candidate in (anchor + 2..path.len()).rev() {
+            if has_line_of_sight(anchor_pos, GridPos::from_vec2(path[candidate]), &is_blocked) {
+                furthest = candidate;
+                break;
             }
         }
+        smoothed.push(path[furthest]);
+        anchor = furthest;
     }
 
-    None // No path found
+    smoothed
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn cell(x: i32, y: i32) -> GridPos {
+        GridPos { x, y }
+    }
+
+    // Every step moves to a neighbouring cell, and any diagonal step has both
+    // cells beside it on the line too
+    fn is_4_connected(cells: &[GridPos]) -> bool {
+        cells.windows(2).all(|pair| {
+            let (dx, dy) = (pair[1].x - pair[0].x, pair[1].y - pair[0].y);
+            if dx.abs() > 1 || dy.abs() > 1 {
+                return false;
+            }
+            dx == 0
+                || dy == 0
+                || (cells.contains(&cell(pair[0].x + dx, pair[0].y))
+                    && cells.contains(&cell(pair[0].x, pair[0].y + dy)))
+        })
+    }
+
+    #[test]
+    fn grid_line_runs_from_start_to_end() {
+        for to in [cell(5, 0), cell(0, -4), cell(3, 7), cell(-6, 2), cell(4, 4)] {
+            let line = grid_line(cell(0, 0), to);
+            assert_eq!(line.first(), Some(&cell(0, 0)));
+            assert_eq!(line.last(), Some(&to));
+            assert!(is_4_connected(&line), "{:?} is not 4-connected", line);
+        }
+    }
+
+    #[test]
+    fn grid_line_includes_both_cells_beside_a_corner() {
+        let line = grid_line(cell(0, 0), cell(2, 2));
+        assert_eq!(
+            line,
+            vec![
+                cell(0, 0),
+                cell(1, 0),
+                cell(0, 1),
+                cell(1, 1),
+                cell(2, 1),
+                cell(1, 2),
+                cell(2, 2)
+            ]
+        );
+    }
+
+    #[test]
+    fn grid_line_to_itself_is_one_cell() {
+        assert_eq!(grid_line(cell(3, -2), cell(3, -2)), vec![cell(3, -2)]);
+    }
+
+    #[test]
+    fn line_of_sight_is_blocked_by_a_cell_on_the_line() {
+        let wall = |pos: GridPos| pos == cell(3, 1);
+        assert!(!has_line_of_sight(cell(0, 0), cell(6, 2), wall));
+        assert!(has_line_of_sight(cell(0, 0), cell(6, -2), wall));
+    }
+
+    #[test]
+    fn line_of_sight_cannot_cut_a_corner() {
+        let corner = |pos: GridPos| pos == cell(1, 0);
+        assert!(!has_line_of_sight(cell(0, 0), cell(1, 1), corner));
+    }
+
+    #[test]
+    fn smooth_path_drops_waypoints_in_plain_view() {
+        let path: Vec<Vec2> = (0..6).map(|x| cell(x, 0).to_vec2()).collect();
+        let smoothed = smooth_path(&path, |_| false);
+        assert_eq!(smoothed, vec![path[0], path[5]]);
+    }
+
+    #[test]
+    fn smooth_path_keeps_the_corner_around_a_wall() {
+        // Up a shaft and along a tunnel, with solid ground inside the bend
+        let path: Vec<Vec2> = [(0, 0), (0, 1), (0, 2), (0, 3), (1, 3), (2, 3), (3, 3)]
+            .into_iter()
+            .map(|(x, y)| cell(x, y).to_vec2())
+            .collect();
+        let solid = |pos: GridPos| pos.x > 0 && pos.y < 3;
+        let smoothed = smooth_path(&path, solid);
+        assert_eq!(smoothed, vec![path[0], path[3], path[6]]);
+    }
+
+    // Solid tiles ringing the cell at the origin, two cells out
+    fn walled_in_origin() -> Vec<Vec2> {
+        (-2..=2)
+            .flat_map(|x| (-2..=2).map(move |y| cell(x, y)))
+            .filter(|pos| pos.x.abs() == 2 || pos.y.abs() == 2)
+            .map(GridPos::to_vec2)
+            .collect()
+    }
+
+    #[test]
+    fn find_path_gives_up_after_max_search_nodes() {
+        // The open map has no edge, so only the node cap ends this search
+        let path = find_path(cell(10, 0).to_vec2(), Vec2::ZERO, &walled_in_origin());
+        assert_eq!(path, None);
+    }
+
+    #[test]
+    fn nearest_accessible_point_stops_outside_an_enclosed_target() {
+        let point =
+            find_nearest_accessible_point(cell(10, 0).to_vec2(), Vec2::ZERO, &walled_in_origin())
+                .map(GridPos::from_vec2);
+        assert_eq!(point, Some(cell(3, 0)));
+    }
 }
@@ -0,0 +1,75 @@
+use bevy::prelude::*;
+
+// Distance at which a waypoint counts as reached and the follower moves on
+pub const WAYPOINT_REACHED_DISTANCE: f32 = 4.0;
+// Distance from a waypoint at which the follower starts blending toward the next one
+pub const LOOKAHEAD_DISTANCE: f32 = 12.0;
+// Distance from the final waypoint at which the follower starts slowing down
+pub const ARRIVAL_DISTANCE: f32 = 16.0;
+// How quickly the current velocity turns toward the desired velocity (per second)
+pub const STEERING_RESPONSIVENESS: f32 = 8.0;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct PathFollow {
+    pub velocity: Vec2,
+    pub next_index: usize,
+    pub finished: bool,
+}
+
+// Steer along a path of waypoints. Instead of driving straight at each waypoint,
+// the heading blends toward the following segment as the ant gets close, so
+// corners are rounded off and the ant keeps its momentum through them.
+pub fn follow_path(
+    position: Vec2,
+    velocity: Vec2,
+    path: &[Vec2],
+    index: usize,
+    speed: f32,
+    delta_seconds: f32,
+) -> PathFollow {
+    let mut next_index = index;
+    while next_index < path.len() && position.distance(path[next_index]) < WAYPOINT_REACHED_DISTANCE
+    {
+        next_index += 1;
+    }
+
+    if next_index >= path.len() {
+        return PathFollow {
+            velocity: Vec2::ZERO,
+            next_index,
+            finished: true,
+        };
+    }
+
+    let waypoint = path[next_index];
+    let distance = position.distance(waypoint);
+    let is_final = next_index == path.len() - 1;
+
+    let toward_waypoint = (waypoint - position).normalize_or_zero();
+    let heading = if is_final || distance >= LOOKAHEAD_DISTANCE {
+        toward_waypoint
+    } else {
+        // Blend at most halfway toward the following segment, so the pull
+        // toward the current waypoint always wins and it is still reached
+        let blend = (1.0 - distance / LOOKAHEAD_DISTANCE) * 0.5;
+        let toward_next = (path[next_index + 1] - waypoint).normalize_or_zero();
+        toward_waypoint
+            .lerp(toward_next, blend)
+            .try_normalize()
+            .unwrap_or(toward_waypoint)
+    };
+
+    let desired_speed = if is_final {
+        speed * (distance / ARRIVAL_DISTANCE).clamp(0.2, 1.0)
+    } else {
+        speed
+    };
+    let desired = heading * desired_speed;
+
+    let turn = (STEERING_RESPONSIVENESS * delta_seconds).min(1.0);
+    PathFollow {
+        velocity: velocity.lerp(desired, turn),
+        next_index,
+        finished: false,
+    }
+}
@@ -1,95 +1,176 @@
-use crate::ant::components::{Ant, AntCommand, AntRole, WorkerState, ANT_SPEED};
+use crate::ant::components::{
+    Ant, AntRole, Behavior, CommandQueue, ForagerState, Health, NurseState, Queen, ScoutState,
+    SoldierState, Traffic, Vitals, WorkerState,
+};
 use crate::colony::{Colony, ColonyMember};
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use task_allocation::innate_thresholds;
 
 pub mod ant_movement;
+pub mod brood;
+pub mod chambers;
+pub mod combat;
+pub mod corpse_disposal;
+#[cfg(feature = "debug")]
+pub mod debug_spawn;
+pub mod decision;
+pub mod dig_site_search;
+pub mod eating;
+pub mod flood_defense;
+pub mod foraging;
+pub mod homing;
 pub mod mouse_click;
+pub mod nursing;
+pub mod orders;
+pub mod painting;
+pub mod queen;
+pub mod raiding;
+pub mod scouting;
+pub mod selection;
+pub mod soldiering;
+pub mod spoil_hauling;
+pub mod task_allocation;
+pub mod vitals;
 
-pub fn spawn_initial_ant(mut commands: Commands, colony_query: Query<Entity, With<Colony>>) {
-    if let Ok(colony_id) = colony_query.get_single() {
-        commands.spawn((
+// Ants the colony starts out with besides the queen and its first worker
+const INITIAL_FORAGERS: usize = 2;
+const INITIAL_NURSES: usize = 1;
+const INITIAL_SCOUTS: usize = 1;
+// Width of the dot marking which colony an ant belongs to
+const COLONY_BADGE_SIZE: f32 = 2.0;
+
+fn spawn_ant(commands: &mut Commands, colony_id: Entity, role: AntRole, position: Vec2) -> Entity {
+    let stats = role.stats();
+    let mut rng = rand::thread_rng();
+    let lifespan = rng.gen_range(stats.min_lifespan..=stats.max_lifespan);
+
+    let ant = commands
+        .spawn((
             SpriteBundle {
                 sprite: Sprite {
-                    color: Color::rgb(0.8, 0.6, 0.2),
-                    custom_size: Some(Vec2::new(5.0, 5.0)),
+                    color: stats.color,
+                    custom_size: Some(Vec2::splat(stats.size)),
                     ..default()
                 },
-                transform: Transform::from_xyz(0.0, 0.0, 10.0),
+                transform: Transform::from_xyz(position.x, position.y, 10.0),
                 ..default()
             },
             Ant {
-                speed: ANT_SPEED,
+                speed: stats.speed,
                 direction: Vec2::new(1.0, 0.0),
                 on_ground: false,
-                command: AntCommand::Work,
-                role: AntRole::Worker,
+                behavior: Behavior::Work,
+                role,
                 worker_state: WorkerState::SearchingForDigSite,
+                forager_state: ForagerState::default(),
+                nurse_state: NurseState::default(),
+                soldier_state: SoldierState::default(),
+                scout_state: ScoutState::default(),
                 search_timer: Timer::from_seconds(2.0, TimerMode::Repeating),
                 target_position: None,
                 current_path: None,
                 current_path_index: 0,
             },
             ColonyMember { colony_id },
+            CommandQueue::default(),
+            Traffic::default(),
+            Vitals::new(lifespan),
+            Health::new(role),
+            innate_thresholds(role, &mut rng),
             RigidBody::Dynamic,
             Velocity::default(),
-            Collider::ball(2.5),         // Half the width of the sprite
-            LockedAxes::ROTATION_LOCKED, // Prevent rotation
+            Collider::ball(stats.size / 2.0), // Half the width of the sprite
+            LockedAxes::ROTATION_LOCKED,      // Prevent rotation
             Damping {
                 linear_damping: 0.5, // Reduced damping for faster movement
                 angular_damping: 1.0,
             },
-        ));
+        ))
+        .id();
+
+    println!(
+        "{:?} ant spawned at {:?} for colony {:?}",
+        role, position, colony_id
+    );
+    ant
+}
 
-        println!("Worker ant spawned at (0,0) for colony {:?}", colony_id);
-    } else {
-        println!("Failed to spawn ant: no colony found");
+// Put an ant to work at another task. It drops whatever it was doing, and
+// takes on the speed and look of its new role.
+fn change_role(ant: &mut Ant, sprite: &mut Sprite, collider: &mut Collider, role: AntRole) {
+    let stats = role.stats();
+    ant.role = role;
+    ant.speed = stats.speed;
+    ant.worker_state = WorkerState::SearchingForDigSite;
+    ant.forager_state = ForagerState::default();
+    ant.nurse_state = NurseState::default();
+    ant.soldier_state = SoldierState::default();
+    ant.scout_state = ScoutState::default();
+    ant.target_position = None;
+    ant.current_path = None;
+    ant.current_path_index = 0;
+    sprite.color = stats.color;
+    sprite.custom_size = Some(Vec2::splat(stats.size));
+    *collider = Collider::ball(stats.size / 2.0);
+}
+
+// Every new colony starts with its queen in the royal chamber and a few ants
+// to dig, forage, scout and raise her first brood
+pub fn spawn_initial_ants(mut commands: Commands, colony_query: Query<(Entity, &Colony)>) {
+    if colony_query.is_empty() {
+        println!("Failed to spawn ants: no colony found");
+    }
+    for (colony_id, colony) in colony_query.iter() {
+        let queen = spawn_ant(
+            &mut commands,
+            colony_id,
+            AntRole::Queen,
+            colony.royal_chamber,
+        );
+        commands.entity(queen).insert(Queen::default());
+        spawn_ant(&mut commands, colony_id, AntRole::Worker, colony.position);
+        for _ in 0..INITIAL_FORAGERS {
+            spawn_ant(&mut commands, colony_id, AntRole::Forager, colony.position);
+        }
+        for _ in 0..INITIAL_NURSES {
+            spawn_ant(
+                &mut commands,
+                colony_id,
+                AntRole::Nurse,
+                colony.royal_chamber,
+            );
+        }
+        for _ in 0..INITIAL_SCOUTS {
+            spawn_ant(&mut commands, colony_id, AntRole::Scout, colony.position);
+        }
     }
 }
 
-pub fn handle_spacebar_spawn(
-    keyboard_input: Res<Input<KeyCode>>,
+// Give every new ant a dot in its colony's color, so colonies can be told
+// apart whatever role their ants have
+pub fn mark_colony_members(
     mut commands: Commands,
-    colony_query: Query<Entity, With<Colony>>,
+    colony_query: Query<&Colony>,
+    ants: Query<(Entity, &ColonyMember), Added<Ant>>,
 ) {
-    if keyboard_input.just_pressed(KeyCode::Space) {
-        if let Ok(colony_id) = colony_query.get_single() {
-            commands.spawn((
-                SpriteBundle {
-                    sprite: Sprite {
-                        color: Color::rgb(0.8, 0.6, 0.2),
-                        custom_size: Some(Vec2::new(5.0, 5.0)),
-                        ..default()
-                    },
-                    transform: Transform::from_xyz(0.0, 0.0, 10.0),
+    for (entity, colony_member) in ants.iter() {
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+        let badge = commands
+            .spawn(SpriteBundle {
+                sprite: Sprite {
+                    color: colony.color,
+                    custom_size: Some(Vec2::splat(COLONY_BADGE_SIZE)),
                     ..default()
                 },
-                Ant {
-                    speed: ANT_SPEED,
-                    direction: Vec2::new(1.0, 0.0),
-                    on_ground: false,
-                    command: AntCommand::Work,
-                    role: AntRole::Worker,
-                    worker_state: WorkerState::SearchingForDigSite,
-                    search_timer: Timer::from_seconds(2.0, TimerMode::Repeating),
-                    target_position: None,
-                    current_path: None,
-                    current_path_index: 0,
-                },
-                ColonyMember { colony_id },
-                RigidBody::Dynamic,
-                Velocity::default(),
-                Collider::ball(2.5),         // Half the width of the sprite
-                LockedAxes::ROTATION_LOCKED, // Prevent rotation
-                Damping {
-                    linear_damping: 0.5, // Reduced damping for faster movement
-                    angular_damping: 1.0,
-                },
-            ));
-
-            println!("Worker ant spawned at (0,0) for colony {:?}", colony_id);
-        } else {
-            println!("Failed to spawn ant: no colony found");
-        }
+                // Just in front of the ant itself
+                transform: Transform::from_xyz(0.0, 0.0, 0.1),
+                ..default()
+            })
+            .id();
+        commands.entity(entity).add_child(badge);
     }
 }
@@ -1,92 +1,62 @@
-use crate::ant::components::{Ant, WorkerState, ANT_SPEED, MAX_COLONY_DISTANCE};
-use crate::ant::pathfinding::{find_nearest_accessible_point, find_path, GridPos};
-use crate::colony::{Colony, ColonyMember};
-use crate::terrain::{AirTile, Tile, TileStore, TileUpdateEvent, TILE_SIZE};
+use crate::ant::avoidance::{avoid_neighbors, is_narrow_passage, Neighbor};
+use crate::ant::components::{Ant, Traffic, COMMAND_COMPLETE_DISTANCE};
+use crate::ant::pathfinding::{
+    find_nearest_accessible_point, find_path, has_line_of_sight, smooth_path, GridPos,
+};
+use crate::ant::steering::follow_path;
+use crate::terrain::TileStore;
 use bevy::prelude::*;
 use bevy_rapier2d::prelude::*;
 
-pub fn ant_movement(
-    mut query: Query<(&Transform, &mut Ant, &mut Velocity, &ColonyMember)>,
-    colony_query: Query<&Colony>,
-    _time: Res<Time>,
-    mut tile_store: ResMut<TileStore>,
-    mut tile_update_events: EventWriter<TileUpdateEvent>,
-    tile_query: Query<(Entity, &Tile)>,
-) {
-    for (transform, mut ant, mut velocity, colony_member) in query.iter_mut() {
-        if let Some(target_pos) = ant.target_position {
-            let current_pos = transform.translation.truncate();
-
-            // Get colony position and check distance
-            if let Ok(colony) = colony_query.get(colony_member.colony_id) {
-                let distance_to_colony = (colony.position - current_pos).length();
-                if distance_to_colony > MAX_COLONY_DISTANCE {
-                    println!(
-                        "Target too far from colony ({} > {}), returning to colony",
-                        distance_to_colony, MAX_COLONY_DISTANCE
-                    );
-                    ant.target_position = Some(colony.position);
-                    continue;
-                }
-            }
-
-            // Handle digging state first
-            if let WorkerState::Digging(dig_target) = ant.worker_state {
-                // Calculate direction to dig target
-                let to_target = dig_target - current_pos;
-                let dig_direction = to_target.normalize();
-
-                // Find the next tile to dig in the direction of the target
-                let next_pos = current_pos + dig_direction * TILE_SIZE;
-                let grid_pos = GridPos::from_vec2(next_pos);
-
-                // Check if we've reached the target
-                if current_pos.distance(dig_target) < TILE_SIZE {
-                    println!("Reached dig target at {:?}", dig_target);
-                    velocity.linvel = Vec2::ZERO;
+// Ants stop once they are this close to their target
+const ARRIVAL_DISTANCE: f32 = 5.0;
 
-                    // Move to the next waypoint in the path
-                    if let Some(path) = &ant.current_path {
-                        if ant.current_path_index < path.len() {
-                            ant.current_path_index += 1;
-                            println!("Moving to next waypoint after digging");
-                        }
-                    }
+// Ants pushed this many tiles into a wall can still climb back out of it
+const WALL_ESCAPE_RADIUS: i32 = 2;
 
-                    ant.worker_state = WorkerState::SearchingForDigSite;
-                    continue;
-                }
+// The closest open cell to an ant stuck inside solid ground, if it is stuck
+fn nearest_open_cell(position: Vec2, tile_store: &TileStore) -> Option<Vec2> {
+    let here = GridPos::from_vec2(position);
+    if !tile_store.is_solid(&here.to_vec2()) {
+        return None;
+    }
 
-                // Try to dig the tile
-                if let Some(tile) = tile_store.get_tile_mut(&grid_pos.to_vec2()) {
-                    if tile.tile_type.is_solid() {
-                        println!("Digging tile at {:?}", grid_pos);
-                        // Find the entity for this tile to update its visual
-                        for (entity, tile) in tile_query.iter() {
-                            if tile.position == grid_pos.to_vec2() {
-                                tile_update_events.send(TileUpdateEvent {
-                                    entity,
-                                    new_type: Box::new(AirTile),
-                                });
-                                break;
-                            }
-                        }
-                        // Update the tile in the store
-                        tile.tile_type = Box::new(AirTile);
+    (-WALL_ESCAPE_RADIUS..=WALL_ESCAPE_RADIUS)
+        .flat_map(|dx| {
+            (-WALL_ESCAPE_RADIUS..=WALL_ESCAPE_RADIUS).map(move |dy| GridPos {
+                x: here.x + dx,
+                y: here.y + dy,
+            })
+        })
+        .map(GridPos::to_vec2)
+        .filter(|cell| !tile_store.is_solid(cell))
+        .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))
+}
 
-                        // Move towards the dug tile
-                        velocity.linvel = dig_direction * ANT_SPEED * 0.5; // Move slower while digging
-                    } else {
-                        // If the tile is already dug, move towards it
-                        velocity.linvel = dig_direction * ANT_SPEED;
-                    }
-                }
-                continue;
-            }
+// Locomotion only: ants walk to whatever target their behavior has set, and
+// stand still without one. Foragers steer themselves afterwards.
+pub fn ant_movement(
+    mut query: Query<(Entity, &Transform, &mut Ant, &mut Velocity, &mut Traffic)>,
+    time: Res<Time>,
+    tile_store: Res<TileStore>,
+) {
+    // Snapshot every ant's position and velocity for local avoidance
+    let neighbors: Vec<Neighbor> = query
+        .iter()
+        .map(|(entity, transform, _, velocity, _)| Neighbor {
+            entity,
+            position: transform.translation.truncate(),
+            velocity: velocity.linvel,
+        })
+        .collect();
+
+    for (entity, transform, mut ant, mut velocity, mut traffic) in query.iter_mut() {
+        if let Some(target_pos) = ant.target_position {
+            let current_pos = transform.translation.truncate();
 
             // Check if we're close enough to the final destination
             let distance_to_target = (target_pos - current_pos).length();
-            if distance_to_target < 5.0 {
+            if distance_to_target < ARRIVAL_DISTANCE {
                 println!(
                     "Reached final destination at {:?}, distance: {}",
                     current_pos, distance_to_target
@@ -111,11 +81,36 @@ pub fn ant_movement(
                 {
                     println!("Found nearest accessible point at {:?}", accessible_point);
 
+                    // Already as close as we can get without digging
+                    if accessible_point.distance(target_pos) > ARRIVAL_DISTANCE
+                        && accessible_point.distance(current_pos) < COMMAND_COMPLETE_DISTANCE
+                    {
+                        // Ants shoved into a wall can't plan a way out, so
+                        // first climb back out into the nearest open cell
+                        if let Some(open_cell) = nearest_open_cell(current_pos, &tile_store) {
+                            println!("Ant {:?} is stuck in a wall, climbing out", entity);
+                            velocity.linvel =
+                                (open_cell - current_pos).normalize_or_zero() * ant.speed;
+                            continue;
+                        }
+
+                        println!("Target at {:?} is out of reach, stopping", target_pos);
+                        velocity.linvel = Vec2::ZERO;
+                        ant.target_position = None;
+                        ant.current_path = None;
+                        ant.current_path_index = 0;
+                        continue;
+                    }
+
                     // Find a path to the accessible point
                     if let Some(path) = find_path(current_pos, accessible_point, &solid_tiles) {
+                        // Collapse the tile-by-tile zigzag into straight runs
+                        let raw_len = path.len();
+                        let path = smooth_path(&path, |pos| tile_store.is_solid(&pos.to_vec2()));
                         println!(
-                            "Found path with {} waypoints to accessible point",
-                            path.len()
+                            "Found path with {} waypoints ({} before smoothing) to accessible point",
+                            path.len(),
+                            raw_len
                         );
                         ant.current_path = Some(path);
                         ant.current_path_index = 0;
@@ -139,52 +134,50 @@ pub fn ant_movement(
 
             if current_index < path_len {
                 let next_waypoint = ant.current_path.as_ref().unwrap()[current_index];
-                let to_target = next_waypoint - current_pos;
-                let distance = to_target.length();
 
-                // Check if we need to dig to reach the next waypoint
-                let next_pos = GridPos::from_vec2(next_waypoint);
-                let solid_tiles = tile_store.get_solid_tiles();
+                // The ant's own cell never blocks it, even if it was pushed into a wall
+                let here = GridPos::from_vec2(current_pos);
+                let is_blocked = |pos: GridPos| pos != here && tile_store.is_solid(&pos.to_vec2());
 
-                // Check for solid tiles in a small area around the next waypoint
-                let needs_digging = solid_tiles.iter().any(|&pos| {
-                    let tile_pos = GridPos::from_vec2(pos);
-                    let dx = (tile_pos.x - next_pos.x).abs();
-                    let dy = (tile_pos.y - next_pos.y).abs();
-                    // Check if the tile is adjacent to or at the next waypoint
-                    dx <= 1 && dy <= 1
-                });
-
-                if needs_digging {
-                    println!("Need to dig to reach next waypoint at {:?}", next_waypoint);
-                    ant.worker_state = WorkerState::Digging(next_waypoint);
-                    velocity.linvel = Vec2::ZERO;
+                // Terrain can change under a path, and avoidance can push ants
+                // off it, so plan again once the next waypoint is out of sight
+                if !has_line_of_sight(here, GridPos::from_vec2(next_waypoint), is_blocked) {
+                    println!("Lost sight of next waypoint, recalculating path");
+                    ant.current_path = None;
+                    ant.current_path_index = 0;
                     continue;
                 }
 
-                // Increased threshold for waypoint detection
-                if distance < 2.0 {
+                let follow = follow_path(
+                    current_pos,
+                    velocity.linvel,
+                    ant.current_path.as_ref().unwrap(),
+                    current_index,
+                    ant.speed,
+                    time.delta_seconds(),
+                );
+
+                if follow.next_index != current_index {
                     println!(
-                        "Reached waypoint {} at {:?}, distance: {}",
-                        current_index, next_waypoint, distance
+                        "Reached waypoint {} at {:?}",
+                        follow.next_index - 1,
+                        current_pos
                     );
-
-                    // Update path index
-                    ant.current_path_index += 1;
-
-                    // Only zero velocity if we're at the final waypoint
-                    if ant.current_path_index >= path_len {
-                        velocity.linvel = Vec2::ZERO;
-                    }
-                } else {
-                    let direction = to_target.normalize();
-                    // Apply a stronger impulse when starting to move
-                    if velocity.linvel.length() < 10.0 {
-                        velocity.linvel = direction * ANT_SPEED * 1.5;
-                    } else {
-                        velocity.linvel = direction * ANT_SPEED;
-                    }
                 }
+
+                ant.current_path_index = follow.next_index;
+
+                // Keep clear of other ants and take turns in narrow tunnels
+                let narrow = is_narrow_passage(current_pos, follow.velocity, is_blocked);
+                velocity.linvel = avoid_neighbors(
+                    entity,
+                    current_pos,
+                    follow.velocity,
+                    &neighbors,
+                    narrow,
+                    &mut traffic,
+                    time.delta_seconds(),
+                );
             } else {
                 // We've reached the end of our path
                 println!("Reached final destination at {:?}", current_pos);
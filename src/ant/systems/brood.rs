@@ -0,0 +1,223 @@
+use super::spawn_ant;
+use crate::ant::components::{Ant, AntRole, Brood, BroodStage};
+use crate::colony::{Colony, ColonyMember};
+use crate::terrain::climate::Climate;
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::weather::Weather;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+// Brood develops best within this many degrees, and this much moisture, of
+// the conditions that suit its stage
+const TEMPERATURE_TOLERANCE: f32 = 5.0;
+const MOISTURE_TOLERANCE: f32 = 0.3;
+// Even in poor conditions brood keeps developing at this fraction of its
+// normal speed
+const MIN_COMFORT: f32 = 0.25;
+// Brood nobody has tended for a while develops at this fraction of the speed
+const NEGLECTED_DEVELOPMENT: f32 = 0.5;
+// Brood kept in a nursery develops up to this much faster, the warmer the
+// nursery is, with the full speed up at this temperature or above
+const NURSERY_SPEEDUP: f32 = 0.5;
+const WARM_NURSERY_TEMPERATURE: f32 = 24.0;
+// Seconds a nurse's grooming lasts
+const CARE_DURATION: f32 = 40.0;
+// Seconds for a fed larva to get hungry again, and to starve once it is
+const LARVA_HUNGER_TIME: f32 = 45.0;
+const LARVA_STARVATION_TIME: f32 = 30.0;
+// Brood left under water this many seconds drowns
+const DROWNING_TIME: f32 = 30.0;
+// Larvae that ate at least this much grow big enough to forage, and the
+// best fed of all grow into soldiers
+const WELL_FED_NUTRITION: f32 = 2.0;
+const SOLDIER_NUTRITION: f32 = 3.0;
+// The colony wants one nurse for every this many brood
+pub const BROOD_PER_NURSE: usize = 6;
+// Share of the colony's workforce kept out foraging, and on guard
+const FORAGER_SHARE: f32 = 0.4;
+const SOLDIER_SHARE: f32 = 0.15;
+// The colony keeps one scout for every this many workers and foragers
+const WORKFORCE_PER_SCOUT: usize = 15;
+
+// Temperature and moisture that suit each stage best. Eggs and larvae need
+// it cool and damp, pupae warm and dry.
+fn ideal_conditions(stage: BroodStage) -> (f32, f32) {
+    match stage {
+        BroodStage::Egg | BroodStage::Larva => (22.0, 0.6),
+        BroodStage::Pupa => (26.0, 0.3),
+    }
+}
+
+// How well conditions at `position` suit brood at `stage`, from 0.0 (not at
+// all) to 1.0 (ideal)
+pub fn site_comfort(stage: BroodStage, position: Vec2, climate: &Climate) -> f32 {
+    let (temperature, moisture) = ideal_conditions(stage);
+    let discomfort = (climate.temperature_at(position) - temperature).abs() / TEMPERATURE_TOLERANCE
+        + (climate.moisture_at(position) - moisture).abs() / MOISTURE_TOLERANCE;
+    (1.0 - discomfort / 2.0).clamp(0.0, 1.0)
+}
+
+// How much faster brood develops at `position` for being in one of the
+// colony's nurseries, 1.0 outside them
+pub fn nursery_speedup(colony: &Colony, position: Vec2, climate: &Climate) -> f32 {
+    if colony
+        .chamber_at(ChamberPurpose::Nursery, position)
+        .is_none()
+    {
+        return 1.0;
+    }
+    let warmth = (climate.temperature_at(position) - climate.deep_temperature)
+        / (WARM_NURSERY_TEMPERATURE - climate.deep_temperature);
+    1.0 + NURSERY_SPEEDUP * warmth.clamp(0.0, 1.0)
+}
+
+#[derive(Default)]
+struct Census {
+    workers: usize,
+    foragers: usize,
+    nurses: usize,
+    soldiers: usize,
+    scouts: usize,
+    brood: usize,
+}
+
+impl Census {
+    fn count(&mut self, role: AntRole) {
+        match role {
+            AntRole::Worker => self.workers += 1,
+            AntRole::Forager => self.foragers += 1,
+            AntRole::Nurse => self.nurses += 1,
+            AntRole::Soldier => self.soldiers += 1,
+            AntRole::Scout => self.scouts += 1,
+            AntRole::Queen => {}
+        }
+    }
+}
+
+// What a hatching ant grows up to be. The colony gets nurses first if its
+// brood is going short of care. After that the best fed larvae become
+// soldiers and well fed ones foragers, while the colony is short of them,
+// with a scout now and then; the rest become workers.
+fn adult_role(brood: &Brood, census: &Census) -> AntRole {
+    let nurses_needed = census.brood.div_ceil(BROOD_PER_NURSE);
+    let workforce = census.workers + census.foragers;
+    let scouts_needed = (workforce / WORKFORCE_PER_SCOUT).max(1);
+
+    if census.nurses < nurses_needed {
+        AntRole::Nurse
+    } else if brood.nutrition >= SOLDIER_NUTRITION
+        && (census.soldiers as f32) < workforce as f32 * SOLDIER_SHARE
+    {
+        AntRole::Soldier
+    } else if census.scouts < scouts_needed {
+        AntRole::Scout
+    } else if brood.nutrition >= WELL_FED_NUTRITION
+        && (census.foragers as f32) < workforce as f32 * FORAGER_SHARE
+    {
+        AntRole::Forager
+    } else {
+        AntRole::Worker
+    }
+}
+
+// Brood grows through its stages, faster when tended and kept where it is
+// comfortable, and faster still in a warm nursery. Larvae go hungry and
+// starve unless nurses feed them. Brood stops growing under water and drowns
+// if it isn't moved. Pupae hatch into adults where they lie.
+pub fn develop_brood(
+    mut commands: Commands,
+    time: Res<Time>,
+    climate: Res<Climate>,
+    weather: Res<Weather>,
+    colony_query: Query<&Colony>,
+    mut brood_query: Query<(Entity, &Transform, &mut Brood, &mut Sprite)>,
+    ants: Query<(&Ant, &ColonyMember)>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    let mut censuses: HashMap<Entity, Census> = HashMap::new();
+    for (ant, colony_member) in ants.iter() {
+        censuses
+            .entry(colony_member.colony_id)
+            .or_default()
+            .count(ant.role);
+    }
+    for (_, _, brood, _) in brood_query.iter() {
+        censuses.entry(brood.colony_id).or_default().brood += 1;
+    }
+
+    for (entity, transform, mut brood, mut sprite) in brood_query.iter_mut() {
+        // Brood being carried carries on once it's been put down
+        if brood.carried {
+            continue;
+        }
+        let position = transform.translation.truncate();
+
+        brood.care = (brood.care - delta_seconds / CARE_DURATION).max(0.0);
+
+        let mut speed = site_comfort(brood.stage, position, &climate).max(MIN_COMFORT);
+        if let Ok(colony) = colony_query.get(brood.colony_id) {
+            speed *= nursery_speedup(colony, position, &climate);
+        }
+        if brood.care <= 0.0 {
+            speed *= NEGLECTED_DEVELOPMENT;
+        }
+
+        if weather.is_flooded(position) {
+            brood.flooded_for += delta_seconds;
+            if brood.flooded_for >= DROWNING_TIME {
+                println!("{:?} at {:?} drowned", brood.stage, position);
+                commands.entity(entity).despawn_recursive();
+                continue;
+            }
+            speed = 0.0;
+        } else {
+            brood.flooded_for = 0.0;
+        }
+
+        if brood.stage == BroodStage::Larva {
+            brood.satiety = (brood.satiety - delta_seconds / LARVA_HUNGER_TIME).max(0.0);
+            if brood.satiety > 0.0 {
+                brood.starving_for = 0.0;
+            } else {
+                brood.starving_for += delta_seconds;
+                if brood.starving_for >= LARVA_STARVATION_TIME {
+                    println!("Larva at {:?} starved", position);
+                    commands.entity(entity).despawn_recursive();
+                    continue;
+                }
+                // Hungry larvae stop growing
+                speed = 0.0;
+            }
+        }
+
+        brood.development += speed * delta_seconds / brood.stage.duration();
+        if brood.development < 1.0 {
+            continue;
+        }
+
+        brood.development = 0.0;
+        let next_stage = match brood.stage {
+            BroodStage::Egg => BroodStage::Larva,
+            BroodStage::Larva => BroodStage::Pupa,
+            BroodStage::Pupa => {
+                let census = censuses.entry(brood.colony_id).or_default();
+                let role = adult_role(&brood, census);
+                census.count(role);
+                census.brood -= 1;
+
+                commands.entity(entity).despawn_recursive();
+                spawn_ant(&mut commands, brood.colony_id, role, position);
+                continue;
+            }
+        };
+
+        println!(
+            "{:?} at {:?} became a {:?}",
+            brood.stage, position, next_stage
+        );
+        brood.stage = next_stage;
+        sprite.color = next_stage.color();
+        sprite.custom_size = Some(next_stage.size());
+    }
+}
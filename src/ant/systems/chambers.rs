@@ -0,0 +1,163 @@
+use crate::ant::components::{Brood, Corpse};
+use crate::colony::{nearest_colony, Chamber, Colony, CENTRAL_CAVITY_RADIUS, ROYAL_CHAMBER_RADIUS};
+use crate::terrain::designation::{ChamberPurpose, Designations};
+use crate::terrain::{TileStore, TILE_SIZE};
+use bevy::prelude::*;
+
+// Seconds between surveys of the nest's chambers
+const SURVEY_INTERVAL: f32 = 2.0;
+// A painted chamber footprint counts as a chamber once this much of it has
+// been dug out
+const CHAMBER_DUG_FRACTION: f32 = 0.75;
+
+#[derive(Resource)]
+pub struct ChamberSurvey {
+    timer: Timer,
+}
+
+impl Default for ChamberSurvey {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(SURVEY_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+// A chamber made of the given open tiles
+fn chamber_from_tiles(purpose: ChamberPurpose, open: &[Vec2]) -> Option<Chamber> {
+    if open.is_empty() {
+        return None;
+    }
+    let center = open.iter().copied().sum::<Vec2>() / open.len() as f32;
+    let radius = open
+        .iter()
+        .map(|tile| tile.distance(center))
+        .fold(0.0, f32::max);
+    Some(Chamber {
+        purpose,
+        center,
+        radius,
+        tiles: open.len(),
+        occupants: 0,
+    })
+}
+
+// The open tiles within `radius` of `center`, as a chamber
+fn dug_chamber(
+    purpose: ChamberPurpose,
+    center: Vec2,
+    radius: f32,
+    tile_store: &TileStore,
+) -> Option<Chamber> {
+    let reach = (radius / TILE_SIZE).ceil() as i32;
+    let middle = (center / TILE_SIZE).round() * TILE_SIZE;
+    let mut open = Vec::new();
+    for y in -reach..=reach {
+        for x in -reach..=reach {
+            let tile = middle + Vec2::new(x as f32, y as f32) * TILE_SIZE;
+            if tile.distance(center) <= radius
+                && tile_store.get_tile(&tile).is_some()
+                && !tile_store.is_solid(&tile)
+            {
+                open.push(tile);
+            }
+        }
+    }
+    chamber_from_tiles(purpose, &open)
+}
+
+// Work out which chambers each colony has. The cavity around the nest is its
+// food store and the queen's chamber its royal chamber; painted chamber
+// footprints join the nearest colony once they're mostly dug out. Brood and
+// bodies are counted to see how full each chamber is.
+pub fn survey_chambers(
+    time: Res<Time>,
+    mut survey: ResMut<ChamberSurvey>,
+    tile_store: Res<TileStore>,
+    designations: Res<Designations>,
+    mut colonies: Query<(Entity, &mut Colony)>,
+    brood: Query<&Transform, With<Brood>>,
+    corpses: Query<&Transform, With<Corpse>>,
+) {
+    let first_survey = colonies
+        .iter()
+        .any(|(_, colony)| colony.chambers.is_empty());
+    if !survey.timer.tick(time.delta()).just_finished() && !first_survey {
+        return;
+    }
+
+    let mut designated = Vec::new();
+    for (purpose, footprint) in designations.chamber_footprints() {
+        let open: Vec<Vec2> = footprint
+            .iter()
+            .copied()
+            .filter(|tile| tile_store.get_tile(tile).is_some() && !tile_store.is_solid(tile))
+            .collect();
+        if (open.len() as f32) < footprint.len() as f32 * CHAMBER_DUG_FRACTION {
+            continue;
+        }
+        let Some(chamber) = chamber_from_tiles(purpose, &open) else {
+            continue;
+        };
+        if let Some((owner, _)) = nearest_colony(colonies.iter(), chamber.center) {
+            designated.push((owner, chamber));
+        }
+    }
+
+    for (colony_entity, mut colony) in colonies.iter_mut() {
+        let mut chambers: Vec<Chamber> = [
+            dug_chamber(
+                ChamberPurpose::FoodStorage,
+                colony.position,
+                CENTRAL_CAVITY_RADIUS,
+                &tile_store,
+            ),
+            dug_chamber(
+                ChamberPurpose::Royal,
+                colony.royal_chamber,
+                ROYAL_CHAMBER_RADIUS,
+                &tile_store,
+            ),
+        ]
+        .into_iter()
+        .flatten()
+        .collect();
+        chambers.extend(
+            designated
+                .iter()
+                .filter(|(owner, _)| *owner == colony_entity)
+                .map(|(_, chamber)| chamber.clone()),
+        );
+
+        for chamber in &mut chambers {
+            let inside = |transform: &Transform| chamber.contains(transform.translation.truncate());
+            chamber.occupants = match chamber.purpose {
+                ChamberPurpose::Nursery | ChamberPurpose::Royal => {
+                    brood.iter().filter(|transform| inside(transform)).count()
+                }
+                ChamberPurpose::Midden => {
+                    corpses.iter().filter(|transform| inside(transform)).count()
+                }
+                ChamberPurpose::FoodStorage | ChamberPurpose::FungusGarden => 0,
+            };
+        }
+
+        if chambers.len() != colony.chambers.len() {
+            println!(
+                "Colony {:?} now has {} chamber(s):",
+                colony_entity,
+                chambers.len()
+            );
+            for chamber in &chambers {
+                println!(
+                    "  {:?} at {:?}, {} tiles, room for {:.0}",
+                    chamber.purpose,
+                    chamber.center,
+                    chamber.tiles,
+                    chamber.capacity()
+                );
+            }
+        }
+        colony.chambers = chambers;
+    }
+}
@@ -0,0 +1,302 @@
+use crate::ant::components::{Ant, Behavior, Health, RoleStats};
+use crate::ant::systems::vitals::die;
+use crate::colony::ColonyMember;
+use crate::predator::{Predator, PredatorBite};
+use crate::terrain::pheromone::{ColonyPheromones, PheromoneChannel};
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+// Soldiers smell predators and foreign ants this far away and go after them
+pub const ENEMY_SCENT_RADIUS: f32 = 6.0 * TILE_SIZE;
+// Ants can bite anything this close to them
+pub const BITE_REACH: f32 = 1.5 * TILE_SIZE;
+// Seconds between one bite and the next
+const BITE_INTERVAL: f32 = 1.0;
+// Chance a bite lands, and how far its damage may stray from the ant's attack
+const BITE_HIT_CHANCE: f64 = 0.7;
+const BITE_DAMAGE_SPREAD: f32 = 0.25;
+// A chased enemy has to move this far before the chaser heads somewhere new
+const RETARGET_DISTANCE: f32 = TILE_SIZE;
+// Alarm scent released per second while fighting, and by each bite taken
+const FIGHTING_ALARM_RATE: f32 = 2.0;
+const BITTEN_ALARM: f32 = 5.0;
+// Health regained per second while not fighting
+const HEAL_RATE: f32 = 0.05;
+// Seed used unless `AntPlugin` is given another, so fights play out the same
+// every run
+pub const DEFAULT_COMBAT_SEED: u64 = 0x5eed;
+
+// Every roll made in a fight comes from here. Seeding it makes combat
+// repeatable.
+#[derive(Resource)]
+pub struct CombatRng(StdRng);
+
+impl CombatRng {
+    pub fn seeded(seed: u64) -> Self {
+        Self(StdRng::seed_from_u64(seed))
+    }
+}
+
+// The damage a bite from an ant with `stats` does, or None if it misses
+pub fn bite(stats: &RoleStats, rng: &mut impl Rng) -> Option<f32> {
+    if !rng.gen_bool(BITE_HIT_CHANCE) {
+        return None;
+    }
+    let spread = rng.gen_range(-BITE_DAMAGE_SPREAD..=BITE_DAMAGE_SPREAD);
+    Some(stats.attack * (1.0 + spread))
+}
+
+type FighterQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static mut Velocity,
+    &'static mut Health,
+    &'static ColonyMember,
+);
+
+// Fighting ants close in on the nearest predator or foreign ant and bite it,
+// calling for help with alarm scent as they go. Ants and predators are
+// handled in entity order and every bite is rolled from `CombatRng`, so the
+// same seed gives the same fight. Ants that aren't fighting slowly heal.
+pub fn fight(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut rng: ResMut<CombatRng>,
+    mut pheromones: ResMut<ColonyPheromones>,
+    mut ants: Query<FighterQuery>,
+    mut predators: Query<(Entity, &Transform, &mut Predator), Without<Ant>>,
+) {
+    let delta_seconds = time.delta_seconds();
+    // Predators belong to no colony, so they're everyone's enemy
+    let mut everyone: Vec<(Entity, Vec2, Option<Entity>)> = ants
+        .iter()
+        .map(|(entity, transform, _, _, _, colony_member)| {
+            (
+                entity,
+                transform.translation.truncate(),
+                Some(colony_member.colony_id),
+            )
+        })
+        .chain(
+            predators
+                .iter()
+                .map(|(entity, transform, _)| (entity, transform.translation.truncate(), None)),
+        )
+        .collect();
+    everyone.sort_by_key(|&(entity, _, _)| entity);
+
+    // Bites are all rolled before any is dealt, so an ant killed this frame
+    // still gets its last bite in
+    let mut bites = Vec::new();
+    for &(entity, position, colony) in &everyone {
+        let Some(colony_id) = colony else {
+            continue;
+        };
+        let Ok((_, _, mut ant, mut velocity, mut health, _)) = ants.get_mut(entity) else {
+            continue;
+        };
+        health.bite_cooldown = (health.bite_cooldown - delta_seconds).max(0.0);
+        if ant.behavior != Behavior::Fight {
+            health.current =
+                (health.current + HEAL_RATE * delta_seconds).min(ant.role.stats().max_health);
+            continue;
+        }
+
+        let enemy = everyone
+            .iter()
+            .filter(|&&(_, _, other_colony)| other_colony != Some(colony_id))
+            .min_by(|a, b| {
+                position
+                    .distance_squared(a.1)
+                    .total_cmp(&position.distance_squared(b.1))
+            });
+        let Some(&(enemy, enemy_position, _)) = enemy else {
+            ant.target_position = None;
+            continue;
+        };
+
+        pheromones.colony_mut(colony_id).deposit(
+            position,
+            PheromoneChannel::Alarm,
+            FIGHTING_ALARM_RATE * delta_seconds,
+        );
+
+        if position.distance(enemy_position) > BITE_REACH {
+            let chasing = ant
+                .target_position
+                .is_some_and(|target| target.distance(enemy_position) <= RETARGET_DISTANCE);
+            if !chasing {
+                ant.head_to(enemy_position);
+            }
+            continue;
+        }
+
+        ant.target_position = None;
+        velocity.linvel = Vec2::ZERO;
+        if health.bite_cooldown > 0.0 {
+            continue;
+        }
+        health.bite_cooldown = BITE_INTERVAL;
+        if let Some(damage) = bite(&ant.role.stats(), &mut rng.0) {
+            bites.push((entity, enemy, damage));
+        }
+    }
+
+    for (attacker, target, damage) in bites {
+        if let Ok((_, _, mut predator)) = predators.get_mut(target) {
+            predator.health -= damage;
+            println!(
+                "Ant {:?} bit {:?} {:?} for {:.1}, {:.1} health left",
+                attacker, predator.kind, target, damage, predator.health
+            );
+            continue;
+        }
+        let Ok((_, transform, ant, _, mut health, colony_member)) = ants.get_mut(target) else {
+            continue;
+        };
+        // Already killed by an earlier bite
+        if health.current <= 0.0 {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        health.current -= damage;
+        pheromones.colony_mut(colony_member.colony_id).deposit(
+            position,
+            PheromoneChannel::Alarm,
+            BITTEN_ALARM,
+        );
+        println!(
+            "Ant {:?} bit {:?} ant {:?} for {:.1}, {:.1} health left",
+            attacker, ant.role, target, damage, health.current
+        );
+        if health.current <= 0.0 {
+            die(
+                &mut commands,
+                target,
+                ant.role,
+                colony_member.colony_id,
+                position,
+                "wounds",
+            );
+        }
+    }
+}
+
+// Ants bitten by predators lose health and raise the alarm, and may die of
+// it. Predators pick their own victims; see `crate::predator`.
+pub fn suffer_predator_bites(
+    mut commands: Commands,
+    mut bites: EventReader<PredatorBite>,
+    mut pheromones: ResMut<ColonyPheromones>,
+    mut ants: Query<(&Transform, &Ant, &mut Health, &ColonyMember)>,
+) {
+    for bite in bites.read() {
+        let Ok((transform, ant, mut health, colony_member)) = ants.get_mut(bite.victim) else {
+            continue;
+        };
+        if health.current <= 0.0 {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        health.current -= bite.damage;
+        pheromones.colony_mut(colony_member.colony_id).deposit(
+            position,
+            PheromoneChannel::Alarm,
+            BITTEN_ALARM,
+        );
+        println!(
+            "{:?} {:?} bit {:?} ant {:?} for {:.1}, {:.1} health left",
+            bite.kind, bite.predator, ant.role, bite.victim, bite.damage, health.current
+        );
+        if health.current <= 0.0 {
+            die(
+                &mut commands,
+                bite.victim,
+                ant.role,
+                colony_member.colony_id,
+                position,
+                &format!("a {:?} bite", bite.kind),
+            );
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::ant::components::AntRole;
+    use std::time::Duration;
+
+    #[test]
+    fn the_same_seed_rolls_the_same_bites() {
+        let stats = AntRole::Soldier.stats();
+        let mut first = CombatRng::seeded(42);
+        let mut second = CombatRng::seeded(42);
+        let rolls: Vec<Option<f32>> = (0..50).map(|_| bite(&stats, &mut first.0)).collect();
+        let again: Vec<Option<f32>> = (0..50).map(|_| bite(&stats, &mut second.0)).collect();
+
+        assert_eq!(rolls, again);
+        assert!(rolls.iter().any(Option::is_some) && rolls.iter().any(Option::is_none));
+    }
+
+    // Two soldiers from rival colonies biting each other for `seconds`,
+    // returning what health each has left, or None once it's dead
+    fn skirmish(seed: u64, seconds: u32) -> Vec<Option<f32>> {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<ColonyPheromones>()
+            .insert_resource(CombatRng::seeded(seed))
+            .add_systems(Update, fight);
+
+        let fighters: Vec<Entity> = [0.0, BITE_REACH / 2.0]
+            .into_iter()
+            .map(|x| {
+                let colony_id = app.world.spawn_empty().id();
+                app.world
+                    .spawn((
+                        Transform::from_xyz(x, 0.0, 0.0),
+                        Ant {
+                            role: AntRole::Soldier,
+                            behavior: Behavior::Fight,
+                            ..default()
+                        },
+                        Velocity::default(),
+                        Health::new(AntRole::Soldier),
+                        ColonyMember { colony_id },
+                    ))
+                    .id()
+            })
+            .collect();
+
+        for _ in 0..seconds {
+            app.world
+                .resource_mut::<Time>()
+                .advance_by(Duration::from_secs(1));
+            app.update();
+        }
+        fighters
+            .iter()
+            .map(|&fighter| {
+                app.world
+                    .get::<Health>(fighter)
+                    .map(|health| health.current)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn the_same_seed_plays_a_fight_out_the_same_way() {
+        let full_health = AntRole::Soldier.stats().max_health;
+        for seed in [1, 2, 3] {
+            let outcome = skirmish(seed, 5);
+            assert_eq!(outcome, skirmish(seed, 5));
+            assert!(outcome
+                .iter()
+                .any(|health| health.map_or(true, |health| health < full_health)));
+        }
+    }
+}
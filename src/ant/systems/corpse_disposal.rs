@@ -0,0 +1,142 @@
+use crate::ant::carrying::{drop_carried, pick_up};
+use crate::ant::components::{Ant, AntRole, Behavior, CarriedItem, Carrying, Corpse, WorkerState};
+use crate::ant::excavation::DIG_REACH;
+use crate::ant::systems::spoil_hauling::find_mound_tile;
+use crate::ant::systems::vitals::place_corpse_in_midden;
+use crate::colony::{Colony, ColonyMember};
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::{TileStore, TILE_SIZE};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// Idle workers notice corpses this far away
+const CORPSE_SEARCH_RADIUS: f32 = 200.0;
+
+type UndertakerQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static mut Velocity,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+);
+
+// Idle workers pick up the bodies of dead nestmates and carry them to a
+// midden chamber, or out to the midden where they are left on top of the
+// spoil mound.
+pub fn dispose_of_corpses(
+    mut commands: Commands,
+    tile_store: Res<TileStore>,
+    colony_query: Query<&Colony>,
+    mut corpses: Query<(Entity, &Transform, &mut Corpse), Without<Ant>>,
+    mut ants: Query<UndertakerQuery>,
+) {
+    // Release corpses whose ant has moved on to something else
+    for (corpse_entity, _, mut corpse) in corpses.iter_mut() {
+        if let Some(ant_entity) = corpse.claimed_by {
+            let still_coming = ants.get(ant_entity).is_ok_and(|(_, _, ant, ..)| {
+                ant.worker_state == WorkerState::MovingToCorpse(corpse_entity)
+            });
+            if !still_coming {
+                corpse.claimed_by = None;
+            }
+        }
+    }
+
+    for (entity, transform, mut ant, mut velocity, colony_member, carrying) in ants.iter_mut() {
+        if ant.role != AntRole::Worker || ant.behavior != Behavior::Work {
+            continue;
+        }
+        let position = transform.translation.truncate();
+
+        match ant.worker_state {
+            WorkerState::SearchingForDigSite => {
+                if ant.target_position.is_some() || carrying.is_some() {
+                    continue;
+                }
+
+                let nearest = corpses
+                    .iter_mut()
+                    .filter(|(_, corpse_transform, corpse)| {
+                        !corpse.in_midden
+                            && corpse.claimed_by.is_none()
+                            && corpse_transform.translation.truncate().distance(position)
+                                <= CORPSE_SEARCH_RADIUS
+                    })
+                    .min_by(|(_, a, _), (_, b, _)| {
+                        a.translation
+                            .truncate()
+                            .distance(position)
+                            .total_cmp(&b.translation.truncate().distance(position))
+                    });
+
+                if let Some((corpse_entity, corpse_transform, mut corpse)) = nearest {
+                    println!(
+                        "Ant {:?} going to collect corpse {:?}",
+                        entity, corpse_entity
+                    );
+                    corpse.claimed_by = Some(entity);
+                    ant.worker_state = WorkerState::MovingToCorpse(corpse_entity);
+                    ant.head_to(corpse_transform.translation.truncate());
+                }
+            }
+            WorkerState::MovingToCorpse(corpse_entity) => {
+                let Ok((_, corpse_transform, _)) = corpses.get(corpse_entity) else {
+                    ant.worker_state = WorkerState::SearchingForDigSite;
+                    continue;
+                };
+                let corpse_position = corpse_transform.translation.truncate();
+
+                if position.distance(corpse_position) <= DIG_REACH && carrying.is_none() {
+                    commands.entity(corpse_entity).despawn_recursive();
+                    pick_up(&mut commands, entity, CarriedItem::Corpse);
+                    println!("Ant {:?} picked up corpse {:?}", entity, corpse_entity);
+                    ant.worker_state = WorkerState::HaulingCorpse;
+                    ant.target_position = None;
+                    ant.current_path = None;
+                    ant.current_path_index = 0;
+                    velocity.linvel = Vec2::ZERO;
+                } else if ant.target_position.is_none() {
+                    println!(
+                        "Ant {:?} could not reach corpse {:?}",
+                        entity, corpse_entity
+                    );
+                    ant.worker_state = WorkerState::SearchingForDigSite;
+                }
+            }
+            WorkerState::HaulingCorpse => {
+                let Some(carrying) = carrying.filter(|c| c.item == CarriedItem::Corpse) else {
+                    ant.worker_state = WorkerState::SearchingForDigSite;
+                    continue;
+                };
+                if ant.target_position.is_some() {
+                    continue;
+                }
+                let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+                    continue;
+                };
+
+                // Bodies go to a midden chamber while there's one with room,
+                // and otherwise on top of the spoil mound
+                let (spot, approach) = match colony.chamber_with_room(ChamberPurpose::Midden) {
+                    Some(chamber) => (chamber.center, chamber.center),
+                    None => {
+                        let spot = find_mound_tile(colony.midden, &tile_store, |_| false)
+                            .unwrap_or(colony.midden);
+                        (spot, spot + Vec2::new(0.0, TILE_SIZE))
+                    }
+                };
+                if position.distance(spot) <= DIG_REACH {
+                    drop_carried(&mut commands, entity, carrying);
+                    place_corpse_in_midden(&mut commands, spot);
+                    println!("Ant {:?} left a corpse at the midden {:?}", entity, spot);
+                    ant.worker_state = WorkerState::SearchingForDigSite;
+                    velocity.linvel = Vec2::ZERO;
+                } else {
+                    ant.head_to(approach);
+                }
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,37 @@
+use super::selection::cursor_world_position;
+use super::spawn_ant;
+use crate::ant::components::AntRole;
+use crate::colony::{nearest_colony, Colony};
+use bevy::prelude::*;
+
+// The queen grows the colony; these keys add ants straight away for testing,
+// to the colony whose nest is nearest the cursor
+pub fn handle_spacebar_spawn(
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    mut commands: Commands,
+    colony_query: Query<(Entity, &Colony)>,
+) {
+    // Space spawns a worker, F a forager, N a nurse, S a soldier, C a scout
+    let role = if keyboard_input.just_pressed(KeyCode::Space) {
+        AntRole::Worker
+    } else if keyboard_input.just_pressed(KeyCode::F) {
+        AntRole::Forager
+    } else if keyboard_input.just_pressed(KeyCode::N) {
+        AntRole::Nurse
+    } else if keyboard_input.just_pressed(KeyCode::S) {
+        AntRole::Soldier
+    } else if keyboard_input.just_pressed(KeyCode::C) {
+        AntRole::Scout
+    } else {
+        return;
+    };
+
+    let cursor = cursor_world_position(&windows, &camera_q).unwrap_or(Vec2::ZERO);
+    if let Some((colony_id, colony)) = nearest_colony(colony_query.iter(), cursor) {
+        spawn_ant(&mut commands, colony_id, role, colony.position);
+    } else {
+        println!("Failed to spawn ant: no colony found");
+    }
+}
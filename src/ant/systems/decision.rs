@@ -0,0 +1,142 @@
+use crate::ant::behavior::{choose, Senses};
+use crate::ant::components::{Ant, CommandQueue, Health, Vitals};
+use crate::ant::systems::combat::ENEMY_SCENT_RADIUS;
+use crate::colony::{Colony, ColonyMember};
+use crate::predator::Predator;
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+type DeciderQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static Vitals,
+    &'static Health,
+    &'static ColonyMember,
+    &'static CommandQueue,
+);
+
+// Scents are sorted into cells this wide, so an ant only has to check the
+// cells around it for anything close enough to smell
+const SCENT_CELL_SIZE: f32 = ENEMY_SCENT_RADIUS;
+
+fn cell_of(position: Vec2) -> IVec2 {
+    (position / SCENT_CELL_SIZE).floor().as_ivec2()
+}
+
+// Where every ant and predator is, by cell. Predators are filed under no
+// colony, so they're an enemy to every ant.
+#[derive(Default)]
+struct ScentMap {
+    cells: HashMap<IVec2, Vec<(Vec2, Option<Entity>)>>,
+}
+
+impl ScentMap {
+    fn add(&mut self, position: Vec2, colony_id: Option<Entity>) {
+        self.cells
+            .entry(cell_of(position))
+            .or_default()
+            .push((position, colony_id));
+    }
+
+    // Distance to the nearest enemy of `colony_id` an ant at `position` can
+    // smell, infinite if there are none
+    fn nearest_enemy(&self, position: Vec2, colony_id: Entity) -> f32 {
+        let center = cell_of(position);
+        (-1..=1)
+            .flat_map(|dx| (-1..=1).map(move |dy| center + IVec2::new(dx, dy)))
+            .filter_map(|cell| self.cells.get(&cell))
+            .flatten()
+            .filter(|(_, other_colony)| *other_colony != Some(colony_id))
+            .map(|(other, _)| position.distance(*other))
+            .filter(|&distance| distance <= ENEMY_SCENT_RADIUS)
+            .fold(f32::INFINITY, f32::min)
+    }
+}
+
+// Every ant weighs up what to do next, given how it's doing and where it is.
+// Locomotion and the systems for each behavior act on the choice; they never
+// decide between behaviors themselves.
+pub fn decide_behavior(
+    colony_query: Query<&Colony>,
+    predators: Query<&Transform, With<Predator>>,
+    mut ants: Query<DeciderQuery>,
+) {
+    // Every ant smells of its colony, so foreign ones stand out
+    let mut scents = ScentMap::default();
+    for (_, transform, _, _, _, colony_member, _) in ants.iter() {
+        scents.add(
+            transform.translation.truncate(),
+            Some(colony_member.colony_id),
+        );
+    }
+    for transform in predators.iter() {
+        scents.add(transform.translation.truncate(), None);
+    }
+
+    for (entity, transform, mut ant, vitals, health, colony_member, queue) in ants.iter_mut() {
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+        let enemy_distance = scents.nearest_enemy(position, colony_member.colony_id);
+
+        let senses = Senses {
+            satiety: vitals.satiety,
+            range_used: position.distance(colony.position) / ant.role.stats().range,
+            stored_food: colony.food,
+            has_orders: !queue.commands.is_empty(),
+            health: health.current / ant.role.stats().max_health,
+            enemy_distance,
+        };
+        let behavior = choose(ant.role, ant.behavior, &senses);
+        if behavior != ant.behavior {
+            println!(
+                "{:?} ant {:?} switched from {:?} to {:?}",
+                ant.role, entity, ant.behavior, behavior
+            );
+            ant.behavior = behavior;
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn nearest_enemy_ignores_the_ants_own_colony() {
+        let mut world = World::new();
+        let home = world.spawn_empty().id();
+        let rival = world.spawn_empty().id();
+        let mut scents = ScentMap::default();
+        scents.add(Vec2::new(2.0, 0.0), Some(home));
+        scents.add(Vec2::new(0.0, 10.0), Some(rival));
+        scents.add(Vec2::new(-20.0, 0.0), None);
+
+        assert_eq!(scents.nearest_enemy(Vec2::ZERO, home), 10.0);
+        assert_eq!(scents.nearest_enemy(Vec2::ZERO, rival), 2.0);
+    }
+
+    #[test]
+    fn nearest_enemy_finds_scents_across_cell_borders() {
+        let mut world = World::new();
+        let home = world.spawn_empty().id();
+        let mut scents = ScentMap::default();
+        scents.add(Vec2::new(SCENT_CELL_SIZE + 1.0, 0.0), None);
+
+        let position = Vec2::new(SCENT_CELL_SIZE - 1.0, 0.0);
+        assert_eq!(scents.nearest_enemy(position, home), 2.0);
+    }
+
+    #[test]
+    fn enemies_out_of_scent_range_are_not_noticed() {
+        let mut world = World::new();
+        let home = world.spawn_empty().id();
+        let mut scents = ScentMap::default();
+        scents.add(Vec2::new(ENEMY_SCENT_RADIUS * 1.5, 0.0), None);
+        scents.add(Vec2::new(ENEMY_SCENT_RADIUS * 5.0, 0.0), None);
+
+        assert_eq!(scents.nearest_enemy(Vec2::ZERO, home), f32::INFINITY);
+    }
+}
@@ -0,0 +1,188 @@
+use crate::ant::components::{
+    Ant, AntRole, Behavior, WorkerState, BRANCH_CHANCE, DIG_CHANCE, MAX_SEARCH_ATTEMPTS,
+    PREFERRED_DIG_ANGLE, SEARCH_RADIUS, WORKER_WORK_RADIUS,
+};
+use crate::ant::excavation::{ExcavationPlans, TunnelEnd};
+use crate::ant::pathfinding::{find_nearest_accessible_point, GridPos};
+use crate::colony::{Colony, ColonyMember};
+use crate::terrain::TileStore;
+use bevy::prelude::*;
+use rand::Rng;
+
+// Autonomously dug tunnels are a single tile wide
+const AUTONOMOUS_TUNNEL_WIDTH: i32 = 1;
+// A colony starts no new tunnels while this many of its own are still being dug
+const MAX_ACTIVE_PLANS: usize = 4;
+
+// A solid tile with open ground next to it, where a new tunnel could start
+struct DigFace {
+    start: Vec2,
+    normal: Vec2,
+}
+
+// Look for a diggable wall within SEARCH_RADIUS of `origin`
+fn find_dig_face(
+    origin: Vec2,
+    colony_position: Vec2,
+    tile_store: &TileStore,
+    rng: &mut impl Rng,
+) -> Option<DigFace> {
+    for _ in 0..MAX_SEARCH_ATTEMPTS {
+        let angle = rng.gen_range(0.0..std::f32::consts::TAU);
+        let distance = rng.gen_range(0.0..SEARCH_RADIUS);
+        let sample = GridPos::from_vec2(origin + Vec2::from_angle(angle) * distance);
+
+        if !tile_store.is_solid(&sample.to_vec2())
+            || sample.to_vec2().distance(colony_position) > WORKER_WORK_RADIUS
+        {
+            continue;
+        }
+
+        for (dx, dy) in [(1, 0), (-1, 0), (0, 1), (0, -1)] {
+            let open = GridPos {
+                x: sample.x + dx,
+                y: sample.y + dy,
+            }
+            .to_vec2();
+            if tile_store
+                .get_tile(&open)
+                .is_some_and(|tile| !tile.tile_type.is_solid())
+            {
+                return Some(DigFace {
+                    start: open,
+                    normal: (sample.to_vec2() - open).normalize(),
+                });
+            }
+        }
+    }
+    None
+}
+
+// Turn a wall normal into a tunnel heading that leaves the wall at the
+// preferred dig angle, descending when the wall gives no preference
+fn heading_from_normal(normal: Vec2, rng: &mut impl Rng) -> Vec2 {
+    let x = if normal.x.abs() > f32::EPSILON {
+        normal.x.signum()
+    } else if rng.gen_bool(0.5) {
+        1.0
+    } else {
+        -1.0
+    };
+    let y = if normal.y.abs() > f32::EPSILON {
+        normal.y.signum()
+    } else {
+        -1.0
+    };
+    Vec2::new(x * PREFERRED_DIG_ANGLE.cos(), y * PREFERRED_DIG_ANGLE.sin())
+}
+
+// Idle workers pick their own dig sites: they extend the tunnels they've
+// already dug and occasionally branch off into new ones, so the colony grows
+// without any player input.
+pub fn search_for_dig_sites(
+    time: Res<Time>,
+    mut plans: ResMut<ExcavationPlans>,
+    tile_store: Res<TileStore>,
+    colony_query: Query<&Colony>,
+    mut ants: Query<(&Transform, &mut Ant, &ColonyMember)>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for (transform, mut ant, colony_member) in ants.iter_mut() {
+        if ant.role != AntRole::Worker
+            || ant.behavior != Behavior::Work
+            || ant.target_position.is_some()
+        {
+            continue;
+        }
+
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+        let current_pos = transform.translation.truncate();
+
+        if ant.worker_state != WorkerState::SearchingForDigSite {
+            continue;
+        }
+
+        ant.search_timer.tick(time.delta());
+        if !ant.search_timer.just_finished() {
+            continue;
+        }
+
+        // Some of the time, just wander around the nest instead of digging
+        if !rng.gen_bool(DIG_CHANCE as f64) {
+            let wander_target = current_pos
+                + Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU))
+                    * rng.gen_range(0.0..SEARCH_RADIUS);
+            let solid_tiles = tile_store.get_solid_tiles();
+            if let Some(point) =
+                find_nearest_accessible_point(current_pos, wander_target, &solid_tiles)
+            {
+                ant.target_position = Some(point);
+            }
+            continue;
+        }
+
+        let colony_id = colony_member.colony_id;
+        if plans.plans_of(colony_id).count() >= MAX_ACTIVE_PLANS {
+            continue;
+        }
+
+        // Extend the colony's closest existing tunnel, or branch off a new one
+        let branch = !plans
+            .tunnel_ends
+            .iter()
+            .any(|end| end.colony_id == colony_id)
+            || rng.gen_bool(BRANCH_CHANCE as f64);
+        let (start, heading) = if branch {
+            let Some(face) = find_dig_face(current_pos, colony.position, &tile_store, &mut rng)
+            else {
+                continue;
+            };
+            println!("Ant branching a new tunnel from {:?}", face.start);
+            (face.start, heading_from_normal(face.normal, &mut rng))
+        } else {
+            let Some(index) = plans
+                .tunnel_ends
+                .iter()
+                .enumerate()
+                .filter(|(_, end)| end.colony_id == colony_id)
+                .min_by(|(_, a), (_, b)| {
+                    a.position
+                        .distance(current_pos)
+                        .total_cmp(&b.position.distance(current_pos))
+                })
+                .map(|(index, _)| index)
+            else {
+                continue;
+            };
+            let TunnelEnd {
+                position,
+                direction,
+                ..
+            } = plans.tunnel_ends.swap_remove(index);
+            println!("Ant extending tunnel from {:?}", position);
+            (
+                position,
+                Vec2::from_angle(rng.gen_range(-0.3..0.3)).rotate(direction),
+            )
+        };
+
+        let length = rng.gen_range(0.25..0.6) * SEARCH_RADIUS;
+        let target = start + heading * length;
+        if target.distance(colony.position) > WORKER_WORK_RADIUS {
+            // Tunnels stop growing at the edge of the work area
+            continue;
+        }
+
+        plans.add_plan(
+            colony_id,
+            start,
+            target,
+            AUTONOMOUS_TUNNEL_WIDTH,
+            PREFERRED_DIG_ANGLE,
+            &tile_store,
+        );
+    }
+}
@@ -0,0 +1,51 @@
+use super::vitals::SATIETY_PER_FOOD;
+use crate::ant::components::{Ant, AntRole, Behavior, Vitals, WorkerState};
+use crate::colony::{Colony, ColonyMember};
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+
+// Ants this close to the colony can eat from its store
+const NEST_EATING_DISTANCE: f32 = 5.0 * TILE_SIZE;
+
+// Hungry ants head home and eat their fill from the colony store. The queen
+// never leaves her chamber. Nurses bring her meals, and if she still goes
+// hungry she eats from the store where she is.
+pub fn eat_at_nest(
+    mut colony_query: Query<&mut Colony>,
+    mut ants: Query<(Entity, &Transform, &mut Ant, &mut Vitals, &ColonyMember)>,
+) {
+    for (entity, transform, mut ant, mut vitals, colony_member) in ants.iter_mut() {
+        if ant.behavior != Behavior::Eat {
+            continue;
+        }
+        let Ok(mut colony) = colony_query.get_mut(colony_member.colony_id) else {
+            continue;
+        };
+
+        let position = transform.translation.truncate();
+        if ant.role == AntRole::Queen || position.distance(colony.position) <= NEST_EATING_DISTANCE
+        {
+            if colony.food > 0.0 {
+                let eaten = ((1.0 - vitals.satiety) / SATIETY_PER_FOOD).min(colony.food);
+                colony.food -= eaten;
+                vitals.satiety += eaten * SATIETY_PER_FOOD;
+                println!(
+                    "Ant {:?} ate {:.2} food at the nest, {:.1} left",
+                    entity, eaten, colony.food
+                );
+            }
+        } else if ant.target_position != Some(colony.position) {
+            println!("Ant {:?} is hungry, heading home", entity);
+            // Haulers keep their load and carry on once they've eaten
+            if ant.role == AntRole::Worker
+                && !matches!(
+                    ant.worker_state,
+                    WorkerState::HaulingSpoil | WorkerState::HaulingCorpse
+                )
+            {
+                ant.worker_state = WorkerState::SearchingForDigSite;
+            }
+            ant.head_to(colony.position);
+        }
+    }
+}
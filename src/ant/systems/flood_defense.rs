@@ -0,0 +1,165 @@
+use crate::ant::components::{Ant, AntRole, Behavior, Carrying, WorkerState};
+use crate::ant::excavation::ExcavationPlans;
+use crate::ant::pathfinding::GridPos;
+use crate::colony::{nearest_colony, Colony, ColonyMember};
+use crate::terrain::weather::{CaveIn, Weather};
+use crate::terrain::{
+    update_tile, DirtTile, TileStore, TileUpdateEvent, ENTRANCE_WIDTH, SURFACE_Y, TILE_SIZE,
+};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// A plugger works from this close to the entrance, standing inside the shaft
+const PLUG_REACH: f32 = 2.0 * TILE_SIZE;
+// Seconds the rain has to have stopped before a plugged entrance is dug open
+const REOPEN_DELAY: f32 = 30.0;
+
+type PluggerQuery = (
+    Entity,
+    &'static mut Transform,
+    &'static mut Ant,
+    &'static mut Velocity,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+);
+
+// The tiles across the top of the colony's entrance shaft, level with the
+// surface
+fn plug_tiles(colony: &Colony) -> Vec<Vec2> {
+    let column = (colony.position.x / TILE_SIZE).round() as i32;
+    (column..column + ENTRANCE_WIDTH)
+        .map(|x| Vec2::new(x as f32 * TILE_SIZE, SURFACE_Y))
+        .collect()
+}
+
+// When it rains, an idle worker climbs up the entrance shaft and fills its
+// top with soil to keep the water out, with anyone standing there pushed
+// inside first. Once the weather has been dry a while the plug is queued for
+// digging out again.
+pub fn plug_entrances(
+    weather: Res<Weather>,
+    mut plans: ResMut<ExcavationPlans>,
+    mut tile_store: ResMut<TileStore>,
+    mut tile_update_events: EventWriter<TileUpdateEvent>,
+    mut colonies: Query<(Entity, &mut Colony)>,
+    mut ants: Query<PluggerQuery>,
+) {
+    let ant_positions: Vec<(Entity, Vec2)> = ants
+        .iter()
+        .map(|(entity, transform, ..)| (entity, transform.translation.truncate()))
+        .collect();
+
+    for (colony_id, mut colony) in colonies.iter_mut() {
+        let tiles = plug_tiles(&colony);
+
+        if !weather.is_raining() {
+            if colony.entrance_plugged && weather.dry_for >= REOPEN_DELAY {
+                for &tile in &tiles {
+                    plans.unseal(colony_id, GridPos::from_vec2(tile));
+                }
+                colony.entrance_plugged = false;
+                println!("Colony {:?} is digging its entrance open again", colony_id);
+            }
+            continue;
+        }
+        if colony.entrance_plugged {
+            continue;
+        }
+
+        let stand = colony.entrance - Vec2::new(0.0, TILE_SIZE);
+        let members = || {
+            ants.iter().filter(move |(_, _, ant, _, member, _)| {
+                member.colony_id == colony_id && ant.role == AntRole::Worker
+            })
+        };
+        let plugger = members()
+            .find(|(_, _, ant, ..)| ant.worker_state == WorkerState::PluggingEntrance)
+            .map(|(entity, ..)| entity);
+        let Some(plugger) = plugger.or_else(|| {
+            members()
+                .filter(|(_, _, ant, _, _, carrying)| {
+                    ant.behavior == Behavior::Work
+                        && ant.worker_state == WorkerState::SearchingForDigSite
+                        && carrying.is_none()
+                })
+                .min_by(|a, b| {
+                    let distance =
+                        |transform: &Transform| transform.translation.truncate().distance(stand);
+                    distance(a.1).total_cmp(&distance(b.1))
+                })
+                .map(|(entity, ..)| entity)
+        }) else {
+            continue;
+        };
+        let Ok((_, transform, mut ant, mut velocity, _, _)) = ants.get_mut(plugger) else {
+            continue;
+        };
+
+        if ant.worker_state != WorkerState::PluggingEntrance {
+            println!(
+                "Worker {:?} is going to plug the entrance of colony {:?} against the rain",
+                plugger, colony_id
+            );
+            ant.worker_state = WorkerState::PluggingEntrance;
+            ant.head_to(stand);
+            continue;
+        }
+        if ant.behavior != Behavior::Work || ant.target_position.is_some() {
+            continue;
+        }
+        if transform.translation.truncate().distance(stand) > PLUG_REACH {
+            // Couldn't get there; someone else will try
+            ant.worker_state = WorkerState::SearchingForDigSite;
+            continue;
+        }
+
+        velocity.linvel = Vec2::ZERO;
+        ant.worker_state = WorkerState::SearchingForDigSite;
+        // Anyone standing in the way is shoved down into the shaft first
+        let in_the_way: Vec<Entity> = ant_positions
+            .iter()
+            .filter(|(_, position)| {
+                tiles
+                    .iter()
+                    .any(|tile| position.distance(*tile) < TILE_SIZE)
+            })
+            .map(|&(entity, _)| entity)
+            .collect();
+        for entity in in_the_way {
+            if let Ok((_, mut transform, ..)) = ants.get_mut(entity) {
+                transform.translation.x = stand.x;
+                transform.translation.y = stand.y;
+            }
+        }
+        for &tile in &tiles {
+            if !tile_store.is_solid(&tile) {
+                update_tile(
+                    &mut tile_store,
+                    &mut tile_update_events,
+                    tile,
+                    Box::new(DirtTile),
+                );
+            }
+            plans.seal(GridPos::from_vec2(tile), &tile_store);
+        }
+        colony.entrance_plugged = true;
+        println!(
+            "Worker {:?} plugged the entrance of colony {:?}",
+            plugger, colony_id
+        );
+    }
+}
+
+// Soil that caved in is dug back out of the tunnel it fell into, by the
+// nearest colony
+pub fn clear_cave_ins(
+    mut cave_ins: EventReader<CaveIn>,
+    colony_query: Query<(Entity, &Colony)>,
+    mut plans: ResMut<ExcavationPlans>,
+) {
+    for cave_in in cave_ins.read() {
+        if let Some((colony_id, _)) = nearest_colony(colony_query.iter(), cave_in.rubble) {
+            plans.queue_dig(colony_id, GridPos::from_vec2(cave_in.rubble));
+        }
+    }
+}
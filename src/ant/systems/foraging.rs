@@ -0,0 +1,280 @@
+use crate::ant::carrying::{drop_carried, pick_up};
+use crate::ant::components::{Ant, AntRole, Behavior, CarriedItem, Carrying, ForagerState};
+use crate::ant::pathfinding::GridPos;
+use crate::colony::{Colony, ColonyMember};
+use crate::food::{FoodKind, FoodSource};
+use crate::terrain::pheromone::{ColonyPheromones, PheromoneChannel};
+use crate::terrain::{TileStore, SURFACE_Y, TILE_SIZE};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use std::f32::consts::{FRAC_PI_2, FRAC_PI_4, PI};
+
+// How far ahead and to the side the antennae reach
+const SENSE_DISTANCE: f32 = 1.5 * TILE_SIZE;
+const SENSE_SPREAD: f32 = 0.6;
+// Scent weaker than this is ignored
+const SCENT_THRESHOLD: f32 = 0.05;
+// Radians per second an ant turns toward the stronger antenna
+const SCENT_TURN_RATE: f32 = 4.0;
+// Largest random heading change per second while wandering
+const WANDER_JITTER: f32 = 3.0;
+// Food is noticed from this far away, and picked up within reach
+const FOOD_SENSE_RADIUS: f32 = 4.0 * TILE_SIZE;
+const FOOD_PICKUP_DISTANCE: f32 = TILE_SIZE;
+// Foragers find the nest by itself once inside the central cavity
+const NEST_SENSE_RADIUS: f32 = 5.0 * TILE_SIZE;
+const NEST_DROP_DISTANCE: f32 = 2.0 * TILE_SIZE;
+// Pheromone laid per second of walking, fading the longer the ant has been
+// away from where its trail leads
+pub const TRAIL_DEPOSIT_RATE: f32 = 4.0;
+pub const TRAIL_FADE_TIME: f32 = 40.0;
+// How strongly a lost forager carrying food turns toward the nest, relative
+// to following a scent. Real ants keep track of the way home as they walk.
+const HOMING_FACTOR: f32 = 0.5;
+// Foragers walk a little slower than workers heading somewhere on purpose
+const FORAGE_SPEED_FACTOR: f32 = 0.7;
+// Above ground, ants keep within this many tiles of the ground beneath them
+const GROUND_CLEARANCE: i32 = 2;
+// Distance ahead checked for walls before stepping
+const WALL_LOOKAHEAD: f32 = 0.75 * TILE_SIZE;
+
+// Open ground a forager can walk on. Underground that's any open tile of the
+// map; on the surface it has to be close to the ground instead of up in the air.
+fn is_walkable(position: Vec2, tile_store: &TileStore) -> bool {
+    if position.y <= SURFACE_Y + TILE_SIZE / 2.0 {
+        return tile_store
+            .get_tile(&GridPos::from_vec2(position).to_vec2())
+            .is_some_and(|tile| !tile.tile_type.is_solid());
+    }
+    if tile_store.is_solid(&GridPos::from_vec2(position).to_vec2()) {
+        return false;
+    }
+    let cell = GridPos::from_vec2(position);
+    (1..=GROUND_CLEARANCE).any(|depth| {
+        tile_store.is_solid(
+            &GridPos {
+                x: cell.x,
+                y: cell.y - depth,
+            }
+            .to_vec2(),
+        )
+    })
+}
+
+// Turn toward whichever antenna smells the channel most strongly. Without a
+// scent to follow the ant wanders at random, drifting toward `fallback` if it
+// has a rough idea of where it's going.
+fn follow_scent(
+    heading: Vec2,
+    senses: [f32; 3],
+    fallback: Option<Vec2>,
+    delta_seconds: f32,
+    rng: &mut impl Rng,
+) -> Vec2 {
+    let [left, ahead, right] = senses;
+    let jitter = rng.gen_range(-1.0..1.0) * WANDER_JITTER * delta_seconds;
+
+    let turn = if left.max(ahead).max(right) < SCENT_THRESHOLD {
+        let drift = fallback.map_or(0.0, |direction| {
+            heading.angle_between(direction).clamp(-1.0, 1.0)
+                * SCENT_TURN_RATE
+                * HOMING_FACTOR
+                * delta_seconds
+        });
+        jitter + drift
+    } else if ahead >= left && ahead >= right {
+        jitter * 0.25
+    } else if left > right {
+        SCENT_TURN_RATE * delta_seconds
+    } else {
+        -SCENT_TURN_RATE * delta_seconds
+    };
+
+    Vec2::from_angle(turn).rotate(heading)
+}
+
+// Turn away from walls, trying small turns before large ones
+fn avoid_walls(position: Vec2, heading: Vec2, tile_store: &TileStore, rng: &mut impl Rng) -> Vec2 {
+    if is_walkable(position + heading * WALL_LOOKAHEAD, tile_store) {
+        return heading;
+    }
+
+    let side = if rng.gen_bool(0.5) { 1.0 } else { -1.0 };
+    for angle in [FRAC_PI_4, FRAC_PI_2, 3.0 * FRAC_PI_4] {
+        for sign in [side, -side] {
+            let candidate = Vec2::from_angle(angle * sign).rotate(heading);
+            if is_walkable(position + candidate * WALL_LOOKAHEAD, tile_store) {
+                return candidate;
+            }
+        }
+    }
+    Vec2::from_angle(PI).rotate(heading)
+}
+
+type ForagerQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static mut Velocity,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+);
+
+// Foragers wander out of the nest laying a home trail, pick up food when they
+// find it, and carry it back following that home trail while laying a food
+// trail. Outbound foragers follow food trails, so good routes get reinforced
+// by every ant that uses them.
+#[allow(clippy::too_many_arguments)]
+pub fn forage(
+    mut commands: Commands,
+    time: Res<Time>,
+    tile_store: Res<TileStore>,
+    mut pheromones: ResMut<ColonyPheromones>,
+    mut colony_query: Query<&mut Colony>,
+    mut food_query: Query<(Entity, &Transform, &mut FoodSource), Without<Ant>>,
+    mut ants: Query<ForagerQuery>,
+) {
+    let mut rng = rand::thread_rng();
+    let delta_seconds = time.delta_seconds();
+
+    for (entity, transform, mut ant, mut velocity, colony_member, carrying) in ants.iter_mut() {
+        if ant.role != AntRole::Forager
+            || ant.behavior != Behavior::Work
+            || ant.target_position.is_some()
+        {
+            continue;
+        }
+        let Ok(mut colony) = colony_query.get_mut(colony_member.colony_id) else {
+            continue;
+        };
+        let trails = pheromones.colony_mut(colony_member.colony_id);
+
+        let position = transform.translation.truncate();
+        let mut heading = ant
+            .direction
+            .try_normalize()
+            .unwrap_or_else(|| Vec2::from_angle(rng.gen_range(0.0..std::f32::consts::TAU)));
+
+        match ant.forager_state {
+            // Tending is seen to alongside the herds themselves
+            ForagerState::TendingAphids { .. } => continue,
+            ForagerState::Exploring { since_home } => {
+                // Passing through the nest starts a fresh home trail
+                let since_home = if position.distance(colony.position) <= NEST_SENSE_RADIUS {
+                    0.0
+                } else {
+                    since_home + delta_seconds
+                };
+                trails.deposit(
+                    position,
+                    PheromoneChannel::Home,
+                    TRAIL_DEPOSIT_RATE * delta_seconds * (-since_home / TRAIL_FADE_TIME).exp(),
+                );
+
+                let nearest_food = food_query
+                    .iter_mut()
+                    .filter(|(_, food_transform, food)| {
+                        food.amount > 0
+                            // Leaves are only worth cutting with a garden to put them in
+                            && (food.kind != FoodKind::Leaves || colony.has_garden_room())
+                            && food_transform.translation.truncate().distance(position)
+                                <= FOOD_SENSE_RADIUS
+                    })
+                    .min_by(|(_, a, _), (_, b, _)| {
+                        a.translation
+                            .truncate()
+                            .distance(position)
+                            .total_cmp(&b.translation.truncate().distance(position))
+                    });
+
+                if let Some((food_entity, food_transform, mut food)) = nearest_food {
+                    let food_position = food_transform.translation.truncate();
+                    if food_position.distance(position) <= FOOD_PICKUP_DISTANCE
+                        && carrying.is_none()
+                    {
+                        food.amount -= 1;
+                        food.mark(colony_member.colony_id);
+                        pick_up(&mut commands, entity, CarriedItem::Food(food.kind));
+                        println!(
+                            "Forager {:?} picked up {:?} at {:?} ({} left)",
+                            entity, food.kind, food_position, food.amount
+                        );
+                        if food.amount == 0 && !food.renewable {
+                            println!("Food source at {:?} is used up", food_position);
+                            commands.entity(food_entity).despawn_recursive();
+                        }
+                        ant.forager_state = ForagerState::ReturningWithFood { since_food: 0.0 };
+                        heading = -heading;
+                    } else {
+                        ant.forager_state = ForagerState::Exploring { since_home };
+                        heading = (food_position - position).normalize_or_zero();
+                    }
+                } else {
+                    ant.forager_state = ForagerState::Exploring { since_home };
+                    let senses = trails.sense(
+                        position,
+                        heading,
+                        SENSE_DISTANCE,
+                        SENSE_SPREAD,
+                        PheromoneChannel::Food,
+                    );
+                    heading = follow_scent(heading, senses, None, delta_seconds, &mut rng);
+                }
+            }
+            ForagerState::ReturningWithFood { since_food } => {
+                let since_food = since_food + delta_seconds;
+                trails.deposit(
+                    position,
+                    PheromoneChannel::Food,
+                    TRAIL_DEPOSIT_RATE * delta_seconds * (-since_food / TRAIL_FADE_TIME).exp(),
+                );
+
+                let to_nest = colony.position - position;
+                if to_nest.length() <= NEST_DROP_DISTANCE {
+                    if let Some(carrying) = carrying {
+                        if let CarriedItem::Food(kind) = carrying.item {
+                            let spilled = kind.nutrition() - colony.bring_home(kind);
+                            if spilled > 0.0 {
+                                println!(
+                                    "Forager {:?} found no room for its {:?}, {:.1} went to waste",
+                                    entity, kind, spilled
+                                );
+                            }
+                        }
+                        drop_carried(&mut commands, entity, carrying);
+                    }
+                    println!(
+                        "Forager {:?} brought food home, colony store is now {:.1} with {:.1} in the fungus gardens",
+                        entity, colony.food, colony.leaves
+                    );
+                    ant.forager_state = ForagerState::Exploring { since_home: 0.0 };
+                    heading = -heading;
+                } else if to_nest.length() <= NEST_SENSE_RADIUS {
+                    ant.forager_state = ForagerState::ReturningWithFood { since_food };
+                    heading = to_nest.normalize_or_zero();
+                } else {
+                    ant.forager_state = ForagerState::ReturningWithFood { since_food };
+                    let senses = trails.sense(
+                        position,
+                        heading,
+                        SENSE_DISTANCE,
+                        SENSE_SPREAD,
+                        PheromoneChannel::Home,
+                    );
+                    heading = follow_scent(
+                        heading,
+                        senses,
+                        to_nest.try_normalize(),
+                        delta_seconds,
+                        &mut rng,
+                    );
+                }
+            }
+        }
+
+        heading = avoid_walls(position, heading, &tile_store, &mut rng);
+        ant.direction = heading;
+        velocity.linvel = heading * ant.speed * FORAGE_SPEED_FACTOR;
+    }
+}
@@ -0,0 +1,28 @@
+use crate::ant::components::{Ant, Behavior};
+use crate::colony::{Colony, ColonyMember};
+use bevy::prelude::*;
+
+// Ants that have strayed further from the colony than their role allows
+// head back home
+pub fn return_to_range(
+    colony_query: Query<&Colony>,
+    mut ants: Query<(Entity, &Transform, &mut Ant, &ColonyMember)>,
+) {
+    for (entity, transform, mut ant, colony_member) in ants.iter_mut() {
+        if ant.behavior != Behavior::ReturnToRange {
+            continue;
+        }
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+
+        if ant.target_position != Some(colony.position) {
+            println!(
+                "Ant {:?} is too far from the colony at {:?}, returning",
+                entity,
+                transform.translation.truncate()
+            );
+            ant.head_to(colony.position);
+        }
+    }
+}
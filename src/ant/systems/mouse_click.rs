@@ -1,41 +1,188 @@
-use crate::ant::components::{Ant, WorkerState};
+use super::orders::{give_order, CommandFinished};
+use super::selection::{cursor_world_position, shift_held};
+use crate::ant::components::{
+    Ant, AntCommand, Brood, CommandQueue, Corpse, Selected, PREFERRED_DIG_ANGLE,
+};
+use crate::ant::excavation::{ExcavationPlans, DEFAULT_TUNNEL_WIDTH};
+use crate::ant::pathfinding::find_nearest_accessible_point;
+use crate::colony::{nearest_colony, Colony, ColonyMember};
+use crate::food::FoodSource;
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::{TileStore, TILE_SIZE};
 use bevy::prelude::*;
-use bevy_rapier2d::prelude::*;
 
+// Clicking this close to food, a corpse or brood picks it out
+const ITEM_CLICK_RADIUS: f32 = 1.5 * TILE_SIZE;
+// A right-button drag shorter than this is taken as a click
+const DRAG_THRESHOLD: f32 = 2.0 * TILE_SIZE;
+// Size of the area guarded when ordered to stand guard
+const GUARD_RADIUS: f32 = 4.0 * TILE_SIZE;
+
+type ItemQuery = (
+    Entity,
+    &'static Transform,
+    Has<FoodSource>,
+    Has<Corpse>,
+    Has<Brood>,
+);
+
+type SelectedAntQuery = (Entity, &'static mut CommandQueue, &'static ColonyMember);
+
+// Give every selected ant the same order, worked out for the colony each one
+// belongs to
+fn order_selected(
+    ants: &mut Query<SelectedAntQuery, With<Selected>>,
+    colony_query: &Query<(Entity, &Colony)>,
+    command_for: impl Fn(&Colony) -> AntCommand,
+    append: bool,
+    finished: &mut EventWriter<CommandFinished>,
+) {
+    let mut issued = Vec::new();
+    for (entity, mut queue, colony_member) in ants.iter_mut() {
+        let Ok((_, colony)) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+        let command = command_for(colony);
+        give_order(entity, &mut queue, command, append, finished);
+        issued.push(command);
+    }
+    if let Some(command) = issued.first() {
+        println!(
+            "{} order for {} ant(s): {:?}",
+            if append { "Queueing" } else { "Issuing" },
+            issued.len(),
+            command
+        );
+    }
+}
+
+// Right click orders the selected ants to carry whatever was clicked home, dig
+// out the clicked tile, stand guard there while G is held, or otherwise move
+// there. Dragging with the right button digs a tunnel along the line. Holding
+// shift adds the order after the ones they have. Carried items go home to
+// each ant's own colony. With no ants selected, right clicking solid ground
+// plans a tunnel from the nearest colony instead.
+#[allow(clippy::too_many_arguments)]
 pub fn handle_mouse_click(
     mouse_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
     windows: Query<&Window>,
     camera_q: Query<(&Camera, &GlobalTransform)>,
-    mut query: Query<(&Transform, &mut Ant, &mut Velocity)>,
+    mut ants: Query<SelectedAntQuery, With<Selected>>,
+    colony_query: Query<(Entity, &Colony)>,
+    items: Query<ItemQuery, Without<Ant>>,
+    tile_store: Res<TileStore>,
+    mut plans: ResMut<ExcavationPlans>,
+    mut finished: EventWriter<CommandFinished>,
+    mut drag_start: Local<Option<Vec2>>,
 ) {
-    if mouse_input.just_pressed(MouseButton::Left) {
-        if let Ok(window) = windows.get_single() {
-            if let Some(cursor_pos) = window.cursor_position() {
-                if let Ok((camera, camera_transform)) = camera_q.get_single() {
-                    if let Some(world_pos) =
-                        camera.viewport_to_world_2d(camera_transform, cursor_pos)
-                    {
-                        println!("Issuing new move command to position: {:?}", world_pos);
-                        for (transform, mut ant, mut velocity) in query.iter_mut() {
-                            // Immediately stop current movement
-                            velocity.linvel = Vec2::ZERO;
-
-                            // Clear existing path
-                            ant.current_path = None;
-                            ant.current_path_index = 0;
-
-                            // Set new target
-                            ant.target_position = Some(world_pos);
-                            ant.worker_state = WorkerState::SearchingForDigSite;
-
-                            println!(
-                                "Interrupted ant at {:?}, setting new target",
-                                transform.translation.truncate()
-                            );
-                        }
-                    }
-                }
+    let append = shift_held(&keyboard_input);
+
+    if mouse_input.just_pressed(MouseButton::Right) {
+        *drag_start = cursor_world_position(&windows, &camera_q);
+    }
+    if !mouse_input.just_released(MouseButton::Right) {
+        return;
+    }
+    let (Some(start), Some(world_pos)) = (
+        drag_start.take(),
+        cursor_world_position(&windows, &camera_q),
+    ) else {
+        return;
+    };
+
+    if ants.is_empty() {
+        // Clicking into solid ground plans a tunnel there from the closest
+        // point the colony can already reach
+        if tile_store.is_solid(&world_pos) {
+            let Some((colony_id, colony)) = nearest_colony(colony_query.iter(), world_pos) else {
+                return;
+            };
+            let solid_tiles = tile_store.get_solid_tiles();
+            if let Some(start) =
+                find_nearest_accessible_point(colony.position, world_pos, &solid_tiles)
+            {
+                println!("Issuing new dig command to position: {:?}", world_pos);
+                plans.add_plan(
+                    colony_id,
+                    start,
+                    world_pos,
+                    DEFAULT_TUNNEL_WIDTH,
+                    PREFERRED_DIG_ANGLE,
+                    &tile_store,
+                );
             }
         }
+        return;
+    }
+
+    if start.distance(world_pos) >= DRAG_THRESHOLD {
+        let command = AntCommand::DigTunnel {
+            from: start,
+            to: world_pos,
+        };
+        order_selected(&mut ants, &colony_query, |_| command, append, &mut finished);
+        return;
+    }
+
+    let clicked_item = items
+        .iter()
+        .map(|(entity, transform, food, corpse, brood)| {
+            (
+                entity,
+                transform.translation.truncate().distance(world_pos),
+                food,
+                corpse,
+                brood,
+            )
+        })
+        .filter(|&(_, distance, food, corpse, brood)| {
+            distance <= ITEM_CLICK_RADIUS && (food || corpse || brood)
+        })
+        .min_by(|a, b| a.1.total_cmp(&b.1));
+
+    let guard = keyboard_input.pressed(KeyCode::G);
+    let solid = tile_store.is_solid(&world_pos);
+    let command_for = |colony: &Colony| match clicked_item {
+        _ if guard => AntCommand::Guard {
+            center: world_pos,
+            radius: GUARD_RADIUS,
+        },
+        // Food goes to the store, bodies to a midden chamber with room or the
+        // midden outside, and brood to the royal chamber
+        Some((item, _, food, corpse, _)) => AntCommand::Carry {
+            item,
+            to: if food {
+                colony.position
+            } else if corpse {
+                colony
+                    .chamber_with_room(ChamberPurpose::Midden)
+                    .map_or(colony.midden, |chamber| chamber.center)
+            } else {
+                colony.royal_chamber
+            },
+        },
+        _ if solid => AntCommand::DigAt(world_pos),
+        _ => AntCommand::MoveTo(world_pos),
+    };
+    order_selected(&mut ants, &colony_query, command_for, append, &mut finished);
+}
+
+// H calls the selected ants home
+pub fn handle_order_keys(
+    keyboard_input: Res<Input<KeyCode>>,
+    mut ants: Query<SelectedAntQuery, With<Selected>>,
+    colony_query: Query<(Entity, &Colony)>,
+    mut finished: EventWriter<CommandFinished>,
+) {
+    if keyboard_input.just_pressed(KeyCode::H) {
+        let append = shift_held(&keyboard_input);
+        order_selected(
+            &mut ants,
+            &colony_query,
+            |_| AntCommand::ReturnHome,
+            append,
+            &mut finished,
+        );
     }
 }
@@ -0,0 +1,342 @@
+use super::brood::{nursery_speedup, site_comfort};
+use super::vitals::SATIETY_PER_FOOD;
+use crate::ant::carrying::{drop_carried, pick_up};
+use crate::ant::components::{
+    Ant, AntRole, Behavior, Brood, BroodStage, CarriedItem, Carrying, NurseState, Queen, Vitals,
+};
+use crate::colony::{Chamber, Colony, ColonyMember, ROYAL_CHAMBER_RADIUS};
+use crate::terrain::climate::Climate;
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::weather::Weather;
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use std::collections::HashSet;
+
+// How close a nurse has to be to brood to look after it
+const NURSE_REACH: f32 = 1.5 * TILE_SIZE;
+// The queen is fed once she's this hungry, this much at a time
+const QUEEN_FEED_THRESHOLD: f32 = 0.6;
+const QUEEN_MEAL: f32 = 1.0;
+// Larvae are fed once they're this hungry
+const LARVA_FEED_THRESHOLD: f32 = 0.5;
+// Food taken from the colony store for each meal a larva is given
+const LARVA_MEAL: f32 = 1.0;
+// Brood is groomed once its care has worn down this far
+const TEND_THRESHOLD: f32 = 0.3;
+// Brood is only moved for a chamber that suits it this much better
+const RELOCATE_MARGIN: f32 = 0.1;
+
+type NurseQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static mut Velocity,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+);
+
+type QueenQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Vitals,
+    &'static ColonyMember,
+);
+
+// Work a piece of brood needs from a nurse, most urgent first
+fn brood_need(
+    brood_entity: Entity,
+    brood: &Brood,
+    position: Vec2,
+    colony: &Colony,
+    climate: &Climate,
+    weather: &Weather,
+) -> Option<(u8, NurseState)> {
+    if brood.stage == BroodStage::Larva
+        && brood.satiety < LARVA_FEED_THRESHOLD
+        && colony.food >= LARVA_MEAL
+    {
+        return Some((0, NurseState::Feeding(brood_entity)));
+    }
+
+    let sites = colony.brood_sites();
+    // Flooded sites are no good for brood at all
+    let comfort = |site: Vec2| {
+        if weather.is_flooded(site) {
+            return 0.0;
+        }
+        site_comfort(brood.stage, site, climate) * nursery_speedup(colony, site, climate)
+    };
+    let current = sites
+        .iter()
+        .copied()
+        .min_by(|a, b| a.distance(position).total_cmp(&b.distance(position)))?;
+    // Brood isn't moved into a nursery that's already full
+    let has_room = |site: &Vec2| {
+        colony
+            .chamber_at(ChamberPurpose::Nursery, *site)
+            .map_or(true, Chamber::has_room)
+    };
+    let best = sites
+        .iter()
+        .copied()
+        .filter(|site| *site == current || has_room(site))
+        .max_by(|a, b| comfort(*a).total_cmp(&comfort(*b)))?;
+    if best != current && comfort(best) > comfort(current) + RELOCATE_MARGIN {
+        return Some((
+            1,
+            NurseState::FetchingBrood {
+                brood: brood_entity,
+                to: best,
+            },
+        ));
+    }
+
+    if brood.care < TEND_THRESHOLD {
+        return Some((2, NurseState::Tending(brood_entity)));
+    }
+    None
+}
+
+// Nurses feed the queen and hungry larvae from the colony store, groom brood
+// that's been left alone, and carry brood to whichever of the colony's
+// chambers has the temperature and moisture that suit its stage, out of any
+// that are flooding. The queen comes first.
+#[allow(clippy::too_many_arguments)]
+pub fn tend_brood(
+    mut commands: Commands,
+    climate: Res<Climate>,
+    weather: Res<Weather>,
+    mut colony_query: Query<&mut Colony>,
+    mut brood_query: Query<(Entity, &mut Transform, &mut Brood, &mut Visibility), Without<Ant>>,
+    mut queens: Query<QueenQuery, (With<Queen>, Without<Brood>)>,
+    mut nurses: Query<NurseQuery>,
+) {
+    let mut rng = rand::thread_rng();
+
+    // Let go of brood whose nurse has died or moved on to something else.
+    // Brood an ant was ordered to carry stays with that ant.
+    let busy_nurses: HashSet<Entity> = nurses
+        .iter()
+        .filter(|(_, _, ant, _, _, carrying)| {
+            (ant.role == AntRole::Nurse && ant.nurse_state != NurseState::Idle)
+                || carrying.is_some_and(|carrying| matches!(carrying.item, CarriedItem::Brood(_)))
+        })
+        .map(|(entity, ..)| entity)
+        .collect();
+    for (_, _, mut brood, mut visibility) in brood_query.iter_mut() {
+        if brood
+            .claimed_by
+            .is_some_and(|nurse| !busy_nurses.contains(&nurse))
+        {
+            brood.claimed_by = None;
+            if brood.carried {
+                brood.carried = false;
+                *visibility = Visibility::Inherited;
+            }
+        }
+    }
+
+    let mut queens_being_fed: HashSet<Entity> = nurses
+        .iter()
+        .filter_map(|(_, _, ant, ..)| match ant.nurse_state {
+            NurseState::FeedingQueen(queen) => Some(queen),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, transform, mut ant, mut velocity, colony_member, carrying) in nurses.iter_mut() {
+        if ant.role != AntRole::Nurse
+            || ant.behavior != Behavior::Work
+            || ant.target_position.is_some()
+        {
+            continue;
+        }
+        let Ok(mut colony) = colony_query.get_mut(colony_member.colony_id) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+
+        match ant.nurse_state {
+            NurseState::Idle => {
+                let hungry_queen = queens.iter().find(|(queen, _, vitals, queen_colony)| {
+                    queen_colony.colony_id == colony_member.colony_id
+                        && vitals.satiety < QUEEN_FEED_THRESHOLD
+                        && !queens_being_fed.contains(queen)
+                });
+                if let Some((queen, queen_transform, ..)) = hungry_queen {
+                    if colony.food >= QUEEN_MEAL {
+                        println!("Nurse {:?} is going to feed the queen", entity);
+                        queens_being_fed.insert(queen);
+                        ant.nurse_state = NurseState::FeedingQueen(queen);
+                        ant.head_to(queen_transform.translation.truncate());
+                        continue;
+                    }
+                }
+
+                let job = brood_query
+                    .iter()
+                    .filter(|(_, _, brood, _)| {
+                        brood.colony_id == colony_member.colony_id
+                            && brood.claimed_by.is_none()
+                            && !brood.carried
+                    })
+                    .filter_map(|(brood_entity, brood_transform, brood, _)| {
+                        let brood_position = brood_transform.translation.truncate();
+                        brood_need(
+                            brood_entity,
+                            brood,
+                            brood_position,
+                            &colony,
+                            &climate,
+                            &weather,
+                        )
+                        .map(|(priority, state)| {
+                            (
+                                priority,
+                                brood_position.distance(position),
+                                state,
+                                brood_entity,
+                                brood_position,
+                            )
+                        })
+                    })
+                    .min_by(|a, b| a.0.cmp(&b.0).then(a.1.total_cmp(&b.1)));
+
+                if let Some((_, _, state, brood_entity, brood_position)) = job {
+                    if let Ok((_, _, mut brood, _)) = brood_query.get_mut(brood_entity) {
+                        brood.claimed_by = Some(entity);
+                    }
+                    println!("Nurse {:?} is now {:?}", entity, state);
+                    ant.nurse_state = state;
+                    ant.head_to(brood_position);
+                } else if position.distance(colony.royal_chamber) > ROYAL_CHAMBER_RADIUS {
+                    // Wait by the eggs for something to do
+                    ant.head_to(colony.royal_chamber);
+                } else {
+                    velocity.linvel = Vec2::ZERO;
+                }
+            }
+            NurseState::FeedingQueen(queen) => {
+                let Ok((_, queen_transform, mut vitals, _)) = queens.get_mut(queen) else {
+                    ant.nurse_state = NurseState::Idle;
+                    continue;
+                };
+                let queen_position = queen_transform.translation.truncate();
+                if queen_position.distance(position) > NURSE_REACH {
+                    ant.head_to(queen_position);
+                    continue;
+                }
+
+                velocity.linvel = Vec2::ZERO;
+                if colony.food >= QUEEN_MEAL {
+                    colony.food -= QUEEN_MEAL;
+                    vitals.satiety = (vitals.satiety + QUEEN_MEAL * SATIETY_PER_FOOD).min(1.0);
+                    println!(
+                        "Nurse {:?} fed the queen, colony store is now {:.1}",
+                        entity, colony.food
+                    );
+                }
+                queens_being_fed.remove(&queen);
+                ant.nurse_state = NurseState::Idle;
+            }
+            NurseState::Feeding(brood_entity)
+            | NurseState::Tending(brood_entity)
+            | NurseState::FetchingBrood {
+                brood: brood_entity,
+                ..
+            } => {
+                let Ok((_, brood_transform, mut brood, mut visibility)) =
+                    brood_query.get_mut(brood_entity)
+                else {
+                    ant.nurse_state = NurseState::Idle;
+                    continue;
+                };
+                let brood_position = brood_transform.translation.truncate();
+                if brood_position.distance(position) > NURSE_REACH {
+                    ant.head_to(brood_position);
+                    continue;
+                }
+
+                velocity.linvel = Vec2::ZERO;
+                match ant.nurse_state {
+                    NurseState::Feeding(_) => {
+                        if colony.food >= LARVA_MEAL {
+                            colony.food -= LARVA_MEAL;
+                            brood.satiety = 1.0;
+                            brood.nutrition += LARVA_MEAL;
+                            brood.care = 1.0;
+                            println!(
+                                "Nurse {:?} fed a larva, colony store is now {:.1}",
+                                entity, colony.food
+                            );
+                        }
+                        brood.claimed_by = None;
+                        ant.nurse_state = NurseState::Idle;
+                    }
+                    NurseState::Tending(_) => {
+                        brood.care = 1.0;
+                        brood.claimed_by = None;
+                        ant.nurse_state = NurseState::Idle;
+                    }
+                    NurseState::FetchingBrood { to, .. } => {
+                        if carrying.is_some() {
+                            brood.claimed_by = None;
+                            ant.nurse_state = NurseState::Idle;
+                            continue;
+                        }
+                        pick_up(&mut commands, entity, CarriedItem::Brood(brood_entity));
+                        brood.carried = true;
+                        *visibility = Visibility::Hidden;
+                        ant.nurse_state = NurseState::CarryingBrood {
+                            brood: brood_entity,
+                            to,
+                        };
+                        ant.head_to(to);
+                    }
+                    _ => {}
+                }
+            }
+            NurseState::CarryingBrood {
+                brood: brood_entity,
+                to,
+            } => {
+                let Ok((_, mut brood_transform, mut brood, mut visibility)) =
+                    brood_query.get_mut(brood_entity)
+                else {
+                    if let Some(carrying) = carrying {
+                        drop_carried(&mut commands, entity, carrying);
+                    }
+                    ant.nurse_state = NurseState::Idle;
+                    continue;
+                };
+                if position.distance(to) > NURSE_REACH {
+                    ant.head_to(to);
+                    continue;
+                }
+
+                // Lay the brood down with the rest, a little apart from each other
+                let offset = Vec2::new(
+                    rng.gen_range(-TILE_SIZE..TILE_SIZE),
+                    rng.gen_range(-TILE_SIZE / 2.0..TILE_SIZE / 2.0),
+                );
+                let drop_position = position + offset;
+                brood_transform.translation.x = drop_position.x;
+                brood_transform.translation.y = drop_position.y;
+                brood.carried = false;
+                brood.claimed_by = None;
+                *visibility = Visibility::Inherited;
+                if let Some(carrying) = carrying {
+                    drop_carried(&mut commands, entity, carrying);
+                }
+                println!(
+                    "Nurse {:?} moved a {:?} to {:?}",
+                    entity, brood.stage, drop_position
+                );
+                velocity.linvel = Vec2::ZERO;
+                ant.nurse_state = NurseState::Idle;
+            }
+        }
+    }
+}
@@ -0,0 +1,661 @@
+use super::vitals::spawn_corpse;
+use crate::ant::carrying::{drop_carried, pick_up};
+use crate::ant::components::{
+    Ant, AntCommand, Behavior, Brood, CarriedItem, Carrying, CommandProgress, CommandQueue, Corpse,
+    WorkerState, PREFERRED_DIG_ANGLE,
+};
+use crate::ant::excavation::{ExcavationPlans, DEFAULT_TUNNEL_WIDTH};
+use crate::ant::pathfinding::find_nearest_accessible_point;
+use crate::colony::{Colony, ColonyMember};
+use crate::food::{spawn_food_source, FoodSource};
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::pheromone::{ColonyPheromones, PheromoneChannel, PheromoneGrid};
+use crate::terrain::{TileStore, TILE_SIZE};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::f32::consts::FRAC_PI_2;
+
+// An ant has got where it was sent once it stops this close to it
+const ARRIVAL_DISTANCE: f32 = 2.0 * TILE_SIZE;
+// How close an ant has to be to something to pick it up
+const PICK_UP_REACH: f32 = 1.5 * TILE_SIZE;
+// Food put down this close to the nest goes into the colony store
+const NEST_DROP_DISTANCE: f32 = 5.0 * TILE_SIZE;
+// Corpses put down this close to the midden are left there for good
+const MIDDEN_DROP_DISTANCE: f32 = 4.0 * TILE_SIZE;
+// Seconds an ant stands guard before moving on to its next order
+const GUARD_ORDER_TIME: f32 = 60.0;
+// Guards answer alarm scent inside their area once it's at least this strong
+const ALARM_THRESHOLD: f32 = 0.5;
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum CommandOutcome {
+    Completed,
+    Failed(&'static str),
+    // Dropped for new orders before it was done
+    Cancelled,
+}
+
+// Sent whenever an ant is done with one of its orders, one way or another
+#[derive(Event, Debug, Clone, Copy)]
+pub struct CommandFinished {
+    pub ant: Entity,
+    pub command: AntCommand,
+    pub outcome: CommandOutcome,
+}
+
+// Give an ant an order, either after the ones it already has or instead of them
+pub fn give_order(
+    entity: Entity,
+    queue: &mut CommandQueue,
+    command: AntCommand,
+    append: bool,
+    finished: &mut EventWriter<CommandFinished>,
+) {
+    if append {
+        queue.push(command);
+        return;
+    }
+    for dropped in queue.replace(command) {
+        finished.send(CommandFinished {
+            ant: entity,
+            command: dropped,
+            outcome: CommandOutcome::Cancelled,
+        });
+    }
+}
+
+type OrderedAntQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static mut Velocity,
+    &'static mut CommandQueue,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+);
+
+type FoodQuery = (&'static Transform, &'static mut FoodSource);
+
+type NotAntOrBrood = (Without<Ant>, Without<Brood>);
+
+type BroodQuery = (
+    &'static mut Transform,
+    &'static mut Brood,
+    &'static mut Visibility,
+);
+
+// Ants work through their orders front to back, and go back to their own
+// work once the queue is empty. Orders interrupted by hunger or straying are
+// started over when the ant comes back to them, except digging, which carries
+// on with the same tunnel.
+#[allow(clippy::too_many_arguments)]
+pub fn carry_out_orders(
+    mut commands: Commands,
+    time: Res<Time>,
+    tile_store: Res<TileStore>,
+    pheromones: Res<ColonyPheromones>,
+    mut plans: ResMut<ExcavationPlans>,
+    mut colony_query: Query<&mut Colony>,
+    mut food_sources: Query<FoodQuery, NotAntOrBrood>,
+    corpses: Query<&Transform, (With<Corpse>, NotAntOrBrood)>,
+    mut brood_query: Query<BroodQuery, Without<Ant>>,
+    mut ants: Query<OrderedAntQuery>,
+    mut finished: EventWriter<CommandFinished>,
+) {
+    for (entity, transform, mut ant, mut velocity, mut queue, colony_member, carrying) in
+        ants.iter_mut()
+    {
+        let Ok(mut colony) = colony_query.get_mut(colony_member.colony_id) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+
+        // Put down anything picked up for an order that has since been dropped
+        if let Some(item) = queue.holding {
+            if !matches!(queue.current(), Some(AntCommand::Carry { .. })) {
+                // It may have eaten it already
+                if let Some(carrying) = carrying {
+                    put_down(
+                        &mut commands,
+                        item,
+                        position,
+                        colony_member.colony_id,
+                        &mut colony,
+                        &mut brood_query,
+                    );
+                    drop_carried(&mut commands, entity, carrying);
+                }
+                queue.holding = None;
+            }
+        }
+
+        let Some(command) = queue.current() else {
+            continue;
+        };
+        if ant.behavior != Behavior::FollowOrders {
+            if matches!(
+                queue.progress,
+                CommandProgress::Underway | CommandProgress::Guarding { .. }
+            ) {
+                queue.progress = CommandProgress::NotStarted;
+            }
+            continue;
+        }
+
+        // Ants sent off mid-dig leave the tile for someone else
+        if !matches!(command, AntCommand::DigAt(_) | AntCommand::DigTunnel { .. })
+            && matches!(
+                ant.worker_state,
+                WorkerState::MovingToDigSite(_) | WorkerState::Digging(_)
+            )
+        {
+            ant.worker_state = WorkerState::SearchingForDigSite;
+        }
+
+        let outcome = match command {
+            AntCommand::MoveTo(destination) => travel(
+                &mut ant,
+                &mut queue,
+                position,
+                destination,
+                "couldn't get there",
+            ),
+            AntCommand::ReturnHome => travel(
+                &mut ant,
+                &mut queue,
+                position,
+                colony.position,
+                "couldn't find the way home",
+            ),
+            AntCommand::DigAt(site) => dig(
+                entity,
+                colony_member.colony_id,
+                &mut ant,
+                &mut velocity,
+                &mut queue,
+                &mut plans,
+                &tile_store,
+                position,
+                || {
+                    let solid_tiles = tile_store.get_solid_tiles();
+                    let start = find_nearest_accessible_point(position, site, &solid_tiles)?;
+                    Some((start, site, 1, PREFERRED_DIG_ANGLE))
+                },
+                site,
+            ),
+            AntCommand::DigTunnel { from, to } => dig(
+                entity,
+                colony_member.colony_id,
+                &mut ant,
+                &mut velocity,
+                &mut queue,
+                &mut plans,
+                &tile_store,
+                position,
+                || {
+                    let start = if tile_store.is_solid(&from) {
+                        let solid_tiles = tile_store.get_solid_tiles();
+                        find_nearest_accessible_point(position, from, &solid_tiles)?
+                    } else {
+                        from
+                    };
+                    // Straight along the line, however steep
+                    Some((start, to, DEFAULT_TUNNEL_WIDTH, FRAC_PI_2))
+                },
+                to,
+            ),
+            AntCommand::Carry { item, to } => {
+                if queue.holding.is_some() {
+                    deliver(
+                        &mut commands,
+                        entity,
+                        &mut ant,
+                        &mut queue,
+                        colony_member.colony_id,
+                        &mut colony,
+                        &mut brood_query,
+                        position,
+                        carrying,
+                        to,
+                    )
+                } else {
+                    fetch(
+                        &mut commands,
+                        entity,
+                        &mut ant,
+                        &mut queue,
+                        colony_member.colony_id,
+                        &mut food_sources,
+                        &corpses,
+                        &mut brood_query,
+                        position,
+                        carrying,
+                        item,
+                        to,
+                    )
+                }
+            }
+            AntCommand::Guard { center, radius } => guard(
+                &mut ant,
+                &mut velocity,
+                &mut queue,
+                pheromones.colony(colony_member.colony_id),
+                position,
+                center,
+                radius,
+                time.delta_seconds(),
+            ),
+        };
+
+        if let Some(outcome) = outcome {
+            queue.finish();
+            finished.send(CommandFinished {
+                ant: entity,
+                command,
+                outcome,
+            });
+        }
+    }
+}
+
+// Walk to a spot, done once the ant stops there
+fn travel(
+    ant: &mut Ant,
+    queue: &mut CommandQueue,
+    position: Vec2,
+    destination: Vec2,
+    failure: &'static str,
+) -> Option<CommandOutcome> {
+    match queue.progress {
+        CommandProgress::NotStarted => {
+            ant.head_to(destination);
+            queue.progress = CommandProgress::Underway;
+            None
+        }
+        _ if ant.target_position.is_some() => None,
+        _ if position.distance(destination) <= ARRIVAL_DISTANCE => Some(CommandOutcome::Completed),
+        _ => Some(CommandOutcome::Failed(failure)),
+    }
+}
+
+// Plan a tunnel the first time round, then keep taking its jobs until every
+// tile has been dug. Other workers may lend a hand with it. `plan` works out
+// the tunnel's (start, target, width, max slope), or None if there's no way
+// to dig it.
+#[allow(clippy::too_many_arguments)]
+fn dig(
+    entity: Entity,
+    colony_id: Entity,
+    ant: &mut Ant,
+    velocity: &mut Velocity,
+    queue: &mut CommandQueue,
+    plans: &mut ExcavationPlans,
+    tile_store: &TileStore,
+    position: Vec2,
+    plan: impl FnOnce() -> Option<(Vec2, Vec2, i32, f32)>,
+    target: Vec2,
+) -> Option<CommandOutcome> {
+    match queue.progress {
+        CommandProgress::Digging { plan } if plans.has_plan(plan) => {
+            if !matches!(
+                ant.worker_state,
+                WorkerState::MovingToDigSite(_) | WorkerState::Digging(_)
+            ) && !plans.claim_nearest_job(
+                entity,
+                colony_id,
+                ant,
+                position,
+                Some(plan),
+                tile_store,
+            ) {
+                // Wait for whoever is digging the tiles in front
+                velocity.linvel = Vec2::ZERO;
+            }
+            None
+        }
+        CommandProgress::Digging { .. } => {
+            if matches!(
+                ant.worker_state,
+                WorkerState::MovingToDigSite(_) | WorkerState::Digging(_)
+            ) {
+                ant.worker_state = WorkerState::SearchingForDigSite;
+            }
+            if tile_store.is_solid(&target) {
+                Some(CommandOutcome::Failed("couldn't dig all the way"))
+            } else {
+                Some(CommandOutcome::Completed)
+            }
+        }
+        _ => {
+            if !tile_store.is_solid(&target) {
+                return Some(CommandOutcome::Failed("nothing to dig there"));
+            }
+            let Some((start, target, width, max_slope)) = plan() else {
+                return Some(CommandOutcome::Failed("no way to dig there"));
+            };
+            match plans.add_plan(colony_id, start, target, width, max_slope, tile_store) {
+                Some(plan) => {
+                    queue.progress = CommandProgress::Digging { plan };
+                    None
+                }
+                None => Some(CommandOutcome::Failed("nothing to dig there")),
+            }
+        }
+    }
+}
+
+// Go to the item and pick it up
+#[allow(clippy::too_many_arguments)]
+fn fetch(
+    commands: &mut Commands,
+    entity: Entity,
+    ant: &mut Ant,
+    queue: &mut CommandQueue,
+    colony_id: Entity,
+    food_sources: &mut Query<FoodQuery, NotAntOrBrood>,
+    corpses: &Query<&Transform, (With<Corpse>, NotAntOrBrood)>,
+    brood_query: &mut Query<BroodQuery, Without<Ant>>,
+    position: Vec2,
+    carrying: Option<&Carrying>,
+    item: Entity,
+    to: Vec2,
+) -> Option<CommandOutcome> {
+    match carrying.map(|carrying| carrying.item) {
+        // Spoil from digging is let fall to pick up something worth carrying
+        Some(CarriedItem::Dirt) => {
+            if let Some(carrying) = carrying {
+                drop_carried(commands, entity, carrying);
+            }
+            if ant.worker_state == WorkerState::HaulingSpoil {
+                ant.worker_state = WorkerState::SearchingForDigSite;
+            }
+            return None;
+        }
+        Some(_) => return Some(CommandOutcome::Failed("already carrying something")),
+        None => {}
+    }
+
+    let item_position = if let Ok((transform, _)) = food_sources.get(item) {
+        transform.translation.truncate()
+    } else if let Ok(transform) = corpses.get(item) {
+        transform.translation.truncate()
+    } else if let Ok((transform, brood, _)) = brood_query.get(item) {
+        if brood.carried || brood.claimed_by.is_some() {
+            return Some(CommandOutcome::Failed("someone else is looking after it"));
+        }
+        transform.translation.truncate()
+    } else {
+        return Some(CommandOutcome::Failed("it's gone"));
+    };
+
+    if position.distance(item_position) > PICK_UP_REACH {
+        return match queue.progress {
+            CommandProgress::NotStarted => {
+                ant.head_to(item_position);
+                queue.progress = CommandProgress::Underway;
+                None
+            }
+            _ if ant.target_position.is_some() => None,
+            _ => Some(CommandOutcome::Failed("couldn't reach it")),
+        };
+    }
+
+    let picked = if let Ok((_, mut food)) = food_sources.get_mut(item) {
+        food.amount -= 1;
+        food.mark(colony_id);
+        if food.amount == 0 && !food.renewable {
+            println!("Food source at {:?} is used up", item_position);
+            commands.entity(item).despawn_recursive();
+        }
+        CarriedItem::Food(food.kind)
+    } else if corpses.contains(item) {
+        commands.entity(item).despawn_recursive();
+        CarriedItem::Corpse
+    } else if let Ok((_, mut brood, mut visibility)) = brood_query.get_mut(item) {
+        brood.claimed_by = Some(entity);
+        brood.carried = true;
+        *visibility = Visibility::Hidden;
+        CarriedItem::Brood(item)
+    } else {
+        return Some(CommandOutcome::Failed("it's gone"));
+    };
+
+    println!(
+        "Ant {:?} picked up {:?} at {:?} to carry to {:?}",
+        entity, picked, item_position, to
+    );
+    pick_up(commands, entity, picked);
+    queue.holding = Some(picked);
+    ant.head_to(to);
+    queue.progress = CommandProgress::Underway;
+    None
+}
+
+// Take what the ant is holding to where it was asked to, or put it down
+// wherever the ant gets stuck
+#[allow(clippy::too_many_arguments)]
+fn deliver(
+    commands: &mut Commands,
+    entity: Entity,
+    ant: &mut Ant,
+    queue: &mut CommandQueue,
+    colony_id: Entity,
+    colony: &mut Colony,
+    brood_query: &mut Query<BroodQuery, Without<Ant>>,
+    position: Vec2,
+    carrying: Option<&Carrying>,
+    to: Vec2,
+) -> Option<CommandOutcome> {
+    let Some(carrying) = carrying else {
+        queue.holding = None;
+        return Some(CommandOutcome::Failed("lost what it was carrying"));
+    };
+    match queue.progress {
+        CommandProgress::NotStarted => {
+            ant.head_to(to);
+            queue.progress = CommandProgress::Underway;
+            return None;
+        }
+        _ if ant.target_position.is_some() => return None,
+        _ => {}
+    }
+
+    let item = queue.holding.take()?;
+    put_down(commands, item, position, colony_id, colony, brood_query);
+    drop_carried(commands, entity, carrying);
+    if position.distance(to) <= ARRIVAL_DISTANCE {
+        Some(CommandOutcome::Completed)
+    } else {
+        Some(CommandOutcome::Failed(
+            "couldn't get there, left it on the way",
+        ))
+    }
+}
+
+// Leave a carried item where it belongs if the ant is close enough, or on
+// the ground where it stands
+fn put_down(
+    commands: &mut Commands,
+    item: CarriedItem,
+    position: Vec2,
+    colony_id: Entity,
+    colony: &mut Colony,
+    brood_query: &mut Query<BroodQuery, Without<Ant>>,
+) {
+    match item {
+        CarriedItem::Food(kind) => {
+            // Food only keeps in the storage chambers and leaves in the
+            // fungus gardens, so once they're full it's left on the ground
+            if position.distance(colony.position) <= NEST_DROP_DISTANCE
+                && colony.bring_home(kind) > 0.0
+            {
+                println!(
+                    "Colony store is now {:.1} with {:.1} in the fungus gardens",
+                    colony.food, colony.leaves
+                );
+            } else {
+                let source = spawn_food_source(commands, kind, position);
+                commands.entity(source).insert(FoodSource {
+                    kind,
+                    amount: 1,
+                    marked_by: vec![colony_id],
+                    renewable: false,
+                });
+            }
+        }
+        CarriedItem::Corpse => {
+            spawn_corpse(
+                commands,
+                position,
+                position.distance(colony.midden) <= MIDDEN_DROP_DISTANCE
+                    || colony
+                        .chamber_at(ChamberPurpose::Midden, position)
+                        .is_some(),
+            );
+        }
+        CarriedItem::Brood(brood_entity) => {
+            if let Ok((mut transform, mut brood, mut visibility)) =
+                brood_query.get_mut(brood_entity)
+            {
+                transform.translation.x = position.x;
+                transform.translation.y = position.y;
+                brood.carried = false;
+                brood.claimed_by = None;
+                *visibility = Visibility::Inherited;
+            }
+        }
+        CarriedItem::Dirt => {}
+    }
+}
+
+// Go to the area and stay there, heading for any alarm raised inside it
+#[allow(clippy::too_many_arguments)]
+fn guard(
+    ant: &mut Ant,
+    velocity: &mut Velocity,
+    queue: &mut CommandQueue,
+    pheromones: Option<&PheromoneGrid>,
+    position: Vec2,
+    center: Vec2,
+    radius: f32,
+    delta_seconds: f32,
+) -> Option<CommandOutcome> {
+    match queue.progress {
+        CommandProgress::NotStarted => {
+            ant.head_to(center);
+            queue.progress = CommandProgress::Underway;
+            None
+        }
+        CommandProgress::Guarding { remaining } => {
+            let remaining = remaining - delta_seconds;
+            if remaining <= 0.0 {
+                return Some(CommandOutcome::Completed);
+            }
+            queue.progress = CommandProgress::Guarding { remaining };
+
+            let sense_radius = (radius / TILE_SIZE).ceil() as i32;
+            let alarm = pheromones
+                .and_then(|grid| grid.strongest_near(center, sense_radius, PheromoneChannel::Alarm))
+                .filter(|&(source, strength)| {
+                    strength >= ALARM_THRESHOLD && source.distance(center) <= radius
+                });
+            match alarm {
+                Some((source, _)) if ant.target_position != Some(source) => {
+                    ant.head_to(source);
+                }
+                None if ant.target_position.is_none() && position.distance(center) > radius => {
+                    ant.head_to(center);
+                }
+                _ => {
+                    if ant.target_position.is_none() {
+                        velocity.linvel = Vec2::ZERO;
+                    }
+                }
+            }
+            None
+        }
+        _ if ant.target_position.is_some() => None,
+        _ if position.distance(center) <= radius.max(ARRIVAL_DISTANCE) => {
+            queue.progress = CommandProgress::Guarding {
+                remaining: GUARD_ORDER_TIME,
+            };
+            None
+        }
+        _ => Some(CommandOutcome::Failed("couldn't reach the area")),
+    }
+}
+
+pub fn report_finished_commands(mut finished: EventReader<CommandFinished>) {
+    for event in finished.read() {
+        match event.outcome {
+            CommandOutcome::Completed => {
+                println!("Ant {:?} finished {:?}", event.ant, event.command)
+            }
+            CommandOutcome::Failed(reason) => println!(
+                "Ant {:?} gave up on {:?}: {}",
+                event.ant, event.command, reason
+            ),
+            CommandOutcome::Cancelled => {
+                println!("Ant {:?} dropped {:?}", event.ant, event.command)
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::event::ManualEventReader;
+
+    const HERE: AntCommand = AntCommand::MoveTo(Vec2::ZERO);
+    const THERE: AntCommand = AntCommand::MoveTo(Vec2::ONE);
+
+    // Gives an ant that already has two orders a third, and returns its queue
+    // along with every order reported finished
+    fn order_again(append: bool) -> (Vec<AntCommand>, Vec<CommandFinished>) {
+        let mut app = App::new();
+        app.add_event::<CommandFinished>().add_systems(
+            Update,
+            move |mut queues: Query<(Entity, &mut CommandQueue)>,
+                  mut finished: EventWriter<CommandFinished>| {
+                for (entity, mut queue) in queues.iter_mut() {
+                    give_order(entity, &mut queue, THERE, append, &mut finished);
+                }
+            },
+        );
+        let mut queue = CommandQueue::default();
+        queue.push(HERE);
+        queue.push(AntCommand::ReturnHome);
+        let ant = app.world.spawn(queue).id();
+
+        app.update();
+
+        let events = app.world.resource::<Events<CommandFinished>>();
+        let finished = ManualEventReader::<CommandFinished>::default()
+            .read(events)
+            .copied()
+            .collect();
+        let commands = app.world.get::<CommandQueue>(ant).unwrap().commands.iter();
+        (commands.copied().collect(), finished)
+    }
+
+    #[test]
+    fn an_appended_order_keeps_the_ones_already_given() {
+        let (commands, finished) = order_again(true);
+        assert_eq!(commands, vec![HERE, AntCommand::ReturnHome, THERE]);
+        assert!(finished.is_empty());
+    }
+
+    #[test]
+    fn a_plain_order_cancels_every_one_it_replaces() {
+        let (commands, finished) = order_again(false);
+        assert_eq!(commands, vec![THERE]);
+        let cancelled: Vec<AntCommand> = finished
+            .iter()
+            .inspect(|event| assert_eq!(event.outcome, CommandOutcome::Cancelled))
+            .map(|event| event.command)
+            .collect();
+        assert_eq!(cancelled, vec![HERE, AntCommand::ReturnHome]);
+    }
+}
@@ -0,0 +1,105 @@
+use super::selection::cursor_world_position;
+use crate::terrain::designation::{ChamberPurpose, Designation, Designations};
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+
+const PAINT_BOX_COLOR: Color = Color::rgba(1.0, 1.0, 1.0, 0.6);
+
+// What dragging with the left button paints, when not selecting ants
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PaintTool {
+    Paint(Designation),
+    Erase,
+}
+
+#[derive(Resource, Default)]
+pub struct DesignationTool {
+    pub active: Option<PaintTool>,
+    // Where the current drag started, in world coordinates
+    drag_start: Option<Vec2>,
+}
+
+// The chamber purpose after `current`, starting over after the last one
+fn next_purpose(current: Option<PaintTool>) -> ChamberPurpose {
+    let index = match current {
+        Some(PaintTool::Paint(Designation::Chamber(purpose))) => ChamberPurpose::ALL
+            .iter()
+            .position(|&other| other == purpose)
+            .map_or(0, |index| (index + 1) % ChamberPurpose::ALL.len()),
+        _ => 0,
+    };
+    ChamberPurpose::ALL[index]
+}
+
+// Z paints dig zones, X no-dig zones, B chamber footprints (pressed again to
+// go through the chamber purposes) and E erases. Escape goes back to
+// selecting ants.
+pub fn choose_paint_tool(keyboard_input: Res<Input<KeyCode>>, mut tool: ResMut<DesignationTool>) {
+    let chosen = if keyboard_input.just_pressed(KeyCode::Z) {
+        Some(PaintTool::Paint(Designation::Dig))
+    } else if keyboard_input.just_pressed(KeyCode::X) {
+        Some(PaintTool::Paint(Designation::NoDig))
+    } else if keyboard_input.just_pressed(KeyCode::B) {
+        Some(PaintTool::Paint(Designation::Chamber(next_purpose(
+            tool.active,
+        ))))
+    } else if keyboard_input.just_pressed(KeyCode::E) {
+        Some(PaintTool::Erase)
+    } else if keyboard_input.just_pressed(KeyCode::Escape) {
+        None
+    } else {
+        return;
+    };
+
+    if chosen != tool.active {
+        match chosen {
+            Some(chosen) => println!("Painting tool: {:?}", chosen),
+            None => println!("Stopped painting"),
+        }
+        tool.active = chosen;
+        tool.drag_start = None;
+    }
+}
+
+// Drag with the left button to paint every tile in the box with the active
+// tool; a click paints a single tile
+pub fn paint_designations(
+    mut gizmos: Gizmos,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    mut tool: ResMut<DesignationTool>,
+    mut designations: ResMut<Designations>,
+) {
+    let Some(active) = tool.active else {
+        return;
+    };
+    let cursor = cursor_world_position(&windows, &camera_q);
+
+    if mouse_input.just_pressed(MouseButton::Left) {
+        tool.drag_start = cursor;
+    }
+    let (Some(start), Some(end)) = (tool.drag_start, cursor) else {
+        return;
+    };
+
+    if mouse_input.just_released(MouseButton::Left) {
+        tool.drag_start = None;
+        let designation = match active {
+            PaintTool::Paint(designation) => Some(designation),
+            PaintTool::Erase => None,
+        };
+        designations.paint(start, end, designation);
+        println!("Painted {:?} from {:?} to {:?}", active, start, end);
+    } else if mouse_input.pressed(MouseButton::Left) {
+        // Outline the tiles that will be painted
+        let snap = |position: Vec2| (position / TILE_SIZE).round() * TILE_SIZE;
+        let area = Rect::from_corners(snap(start), snap(end));
+        gizmos.rect_2d(
+            area.center(),
+            0.0,
+            area.size() + Vec2::splat(TILE_SIZE),
+            PAINT_BOX_COLOR,
+        );
+    }
+}
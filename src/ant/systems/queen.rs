@@ -0,0 +1,99 @@
+use crate::ant::components::{Ant, Brood, BroodStage, Queen};
+use crate::colony::{Colony, ColonyMember, ROYAL_CHAMBER_RADIUS};
+use crate::terrain::climate::Climate;
+use crate::terrain::clock::SimClock;
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+use rand::Rng;
+
+// Eggs laid per second by a well fed queen
+const MAX_EGG_RATE: f32 = 1.0 / 20.0;
+// The queen lays at full rate once the store holds this much food, and more
+// slowly the emptier it gets
+const WELL_FED_FOOD: f32 = 100.0;
+// Food taken from the store for every egg laid
+const EGG_FOOD_COST: f32 = 2.0;
+
+// The queen never leaves the royal chamber of her own accord, and heads back
+// to it if she's been moved out
+pub fn keep_queen_in_chamber(
+    colony_query: Query<&Colony>,
+    mut queens: Query<(&Transform, &mut Ant, &ColonyMember), With<Queen>>,
+) {
+    for (transform, mut ant, colony_member) in queens.iter_mut() {
+        if ant.target_position.is_some() {
+            continue;
+        }
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+
+        let position = transform.translation.truncate();
+        if position.distance(colony.royal_chamber) > ROYAL_CHAMBER_RADIUS {
+            println!("Queen is returning to the royal chamber");
+            ant.head_to(colony.royal_chamber);
+        }
+    }
+}
+
+// Queens lay eggs as fast as the colony's food store allows, and stop while
+// the royal chamber is full of brood. Like the rest of the colony they slow
+// down in the cold, so few eggs are laid in winter. Each egg takes its share
+// of food from the store.
+pub fn lay_eggs(
+    mut commands: Commands,
+    time: Res<Time>,
+    clock: Res<SimClock>,
+    climate: Res<Climate>,
+    mut colony_query: Query<&mut Colony>,
+    mut queens: Query<(&Transform, &mut Queen, &ColonyMember)>,
+) {
+    let mut rng = rand::thread_rng();
+
+    for (transform, mut queen, colony_member) in queens.iter_mut() {
+        let Ok(mut colony) = colony_query.get_mut(colony_member.colony_id) else {
+            continue;
+        };
+
+        let crowded = colony
+            .chamber_at(ChamberPurpose::Royal, colony.royal_chamber)
+            .is_some_and(|chamber| !chamber.has_room());
+        if crowded {
+            continue;
+        }
+
+        let rate = MAX_EGG_RATE
+            * (colony.food / WELL_FED_FOOD).clamp(0.0, 1.0)
+            * clock.activity(&climate, transform.translation.truncate());
+        queen.egg_progress = (queen.egg_progress + rate * time.delta_seconds()).min(1.0);
+        if queen.egg_progress < 1.0 || colony.food < EGG_FOOD_COST {
+            continue;
+        }
+
+        queen.egg_progress = 0.0;
+        colony.food -= EGG_FOOD_COST;
+
+        let offset = Vec2::new(
+            rng.gen_range(-TILE_SIZE..TILE_SIZE),
+            rng.gen_range(-TILE_SIZE..0.0),
+        );
+        let position = transform.translation.truncate() + offset;
+        commands.spawn((
+            Brood::new(colony_member.colony_id),
+            SpriteBundle {
+                sprite: Sprite {
+                    color: BroodStage::Egg.color(),
+                    custom_size: Some(BroodStage::Egg.size()),
+                    ..default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, 7.0),
+                ..default()
+            },
+        ));
+        println!(
+            "Queen laid an egg at {:?}, colony store is now {:.1}",
+            position, colony.food
+        );
+    }
+}
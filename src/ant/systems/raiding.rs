@@ -0,0 +1,218 @@
+use crate::ant::carrying::{drop_carried, pick_up};
+use crate::ant::components::{Ant, AntRole, Behavior, CarriedItem, Carrying, SoldierState};
+use crate::colony::{Colony, ColonyMember};
+use crate::food::FoodKind;
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+
+// Seconds between each colony weighing up whether to raid
+const RAID_PLAN_INTERVAL: f32 = 15.0;
+// Idle soldiers a colony needs before it raids, and most it sends at once
+const MIN_RAID_PARTY: usize = 2;
+const MAX_RAID_PARTY: usize = 4;
+// Raids only go after colonies with at least this much food, and no
+// further than this share of a soldier's range
+const MIN_LOOT: f32 = 10.0;
+const RAID_RANGE_FRACTION: f32 = 0.9;
+// Raiders take from the store and drop loot at home this close to the nest
+const STORE_REACH: f32 = 2.0 * TILE_SIZE;
+// What a raider carries off
+const LOOT: FoodKind = FoodKind::Seeds;
+// Times a raider sets off for where it's going, since fights stop it short
+const RAID_ATTEMPTS: u32 = 3;
+
+#[derive(Resource)]
+pub struct RaidPlanner {
+    timer: Timer,
+}
+
+impl Default for RaidPlanner {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(RAID_PLAN_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+type RaiderQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+);
+
+fn is_raiding(ant: &Ant) -> bool {
+    matches!(
+        ant.soldier_state,
+        SoldierState::Raiding { .. } | SoldierState::CarryingLoot { .. }
+    )
+}
+
+// A colony running low on food sends a party of its idle soldiers to the
+// nearest colony within reach that has food to spare
+pub fn plan_raids(
+    time: Res<Time>,
+    mut planner: ResMut<RaidPlanner>,
+    colonies: Query<(Entity, &Colony)>,
+    mut soldiers: Query<RaiderQuery>,
+) {
+    if !planner.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+
+    for (colony_id, colony) in colonies.iter() {
+        if !colony.food_low {
+            continue;
+        }
+        let members = || {
+            soldiers.iter().filter(move |(_, _, ant, member, _)| {
+                member.colony_id == colony_id && ant.role == AntRole::Soldier
+            })
+        };
+        if members().any(|(_, _, ant, _, _)| is_raiding(ant)) {
+            continue;
+        }
+        let mut party: Vec<Entity> = members()
+            .filter(|(_, _, ant, _, carrying)| ant.behavior == Behavior::Work && carrying.is_none())
+            .map(|(entity, ..)| entity)
+            .collect();
+        if party.len() < MIN_RAID_PARTY {
+            continue;
+        }
+        party.sort();
+        party.truncate(MAX_RAID_PARTY);
+
+        let reach = AntRole::Soldier.stats().range * RAID_RANGE_FRACTION;
+        let target = colonies
+            .iter()
+            .filter(|&(other_id, other)| {
+                other_id != colony_id
+                    && other.food >= MIN_LOOT
+                    && other.position.distance(colony.position) <= reach
+            })
+            .min_by(|a, b| {
+                colony
+                    .position
+                    .distance(a.1.position)
+                    .total_cmp(&colony.position.distance(b.1.position))
+            });
+        let Some((target_id, target)) = target else {
+            continue;
+        };
+
+        println!(
+            "Colony {:?} is short of food and sends {} soldier(s) to raid colony {:?}",
+            colony_id,
+            party.len(),
+            target_id
+        );
+        for entity in party {
+            if let Ok((_, _, mut ant, _, _)) = soldiers.get_mut(entity) {
+                ant.soldier_state = SoldierState::Raiding {
+                    colony: target_id,
+                    attempts_left: RAID_ATTEMPTS - 1,
+                };
+                ant.head_to(target.position);
+            }
+        }
+    }
+}
+
+// Raiders make for the other colony's store, take what they can carry and
+// bring it home, setting off again whenever a fight stops them short. A
+// raider that still can't get there, or finds the store empty, goes back to
+// its patrol.
+pub fn raid(
+    mut commands: Commands,
+    mut colonies: Query<&mut Colony>,
+    mut soldiers: Query<RaiderQuery>,
+) {
+    for (entity, transform, mut ant, colony_member, carrying) in soldiers.iter_mut() {
+        if ant.role != AntRole::Soldier
+            || ant.behavior != Behavior::Work
+            || ant.target_position.is_some()
+        {
+            continue;
+        }
+        let position = transform.translation.truncate();
+
+        match ant.soldier_state {
+            SoldierState::Raiding {
+                colony: target_id,
+                attempts_left,
+            } => {
+                let Ok(mut target) = colonies.get_mut(target_id) else {
+                    ant.soldier_state = SoldierState::Patrolling { leg: 0 };
+                    continue;
+                };
+                if position.distance(target.position) > STORE_REACH && attempts_left > 0 {
+                    ant.soldier_state = SoldierState::Raiding {
+                        colony: target_id,
+                        attempts_left: attempts_left - 1,
+                    };
+                    ant.head_to(target.position);
+                    continue;
+                }
+                if position.distance(target.position) > STORE_REACH {
+                    println!(
+                        "Raider {:?} fell short of colony {:?} and turns back",
+                        entity, target_id
+                    );
+                    ant.soldier_state = SoldierState::Patrolling { leg: 0 };
+                    continue;
+                }
+                if target.food < LOOT.nutrition() {
+                    println!("Raider {:?} found nothing to take", entity);
+                    ant.soldier_state = SoldierState::Patrolling { leg: 0 };
+                    continue;
+                }
+                target.food -= LOOT.nutrition();
+                pick_up(&mut commands, entity, CarriedItem::Food(LOOT));
+                println!(
+                    "Raider {:?} stole {:.1} food from colony {:?}",
+                    entity,
+                    LOOT.nutrition(),
+                    target_id
+                );
+                ant.soldier_state = SoldierState::CarryingLoot {
+                    attempts_left: RAID_ATTEMPTS - 1,
+                };
+                if let Ok(home) = colonies.get(colony_member.colony_id) {
+                    ant.head_to(home.position);
+                }
+            }
+            SoldierState::CarryingLoot { attempts_left } => {
+                ant.soldier_state = SoldierState::Patrolling { leg: 0 };
+                // Hungry raiders eat their loot on the way
+                let Some(carrying) = carrying else {
+                    continue;
+                };
+                let Ok(mut home) = colonies.get_mut(colony_member.colony_id) else {
+                    continue;
+                };
+                if position.distance(home.position) > STORE_REACH && attempts_left > 0 {
+                    ant.soldier_state = SoldierState::CarryingLoot {
+                        attempts_left: attempts_left - 1,
+                    };
+                    ant.head_to(home.position);
+                    continue;
+                }
+                if position.distance(home.position) > STORE_REACH {
+                    // Couldn't get home, so the loot is dropped where it is
+                    drop_carried(&mut commands, entity, carrying);
+                    continue;
+                }
+                if let CarriedItem::Food(kind) = carrying.item {
+                    home.store_food(kind.nutrition());
+                    println!(
+                        "Raider {:?} brought loot home, colony store is now {:.1}",
+                        entity, home.food
+                    );
+                }
+                drop_carried(&mut commands, entity, carrying);
+            }
+            _ => {}
+        }
+    }
+}
@@ -0,0 +1,108 @@
+use super::foraging::{TRAIL_DEPOSIT_RATE, TRAIL_FADE_TIME};
+use crate::ant::components::{Ant, AntRole, Behavior, ScoutState, WORKER_WORK_RADIUS};
+use crate::colony::{Colony, ColonyMember};
+use crate::food::FoodSource;
+use crate::terrain::pheromone::{ColonyPheromones, PheromoneChannel};
+use crate::terrain::{ground_level, TileStore, TILE_SIZE, WORLD_HALF_SIZE};
+use bevy::prelude::*;
+use rand::Rng;
+
+// Scouts head for spots at least this far from the colony, well beyond where
+// workers go
+const SCOUT_MIN_DISTANCE: f32 = WORKER_WORK_RADIUS * 1.1;
+// Attempts at finding such a spot before settling for wherever was picked
+const MAX_DESTINATION_ATTEMPTS: usize = 10;
+// Scouts spot food from further away than foragers do
+const SCOUT_SENSE_RADIUS: f32 = 6.0 * TILE_SIZE;
+// Scent left on newly found food so the trail leads right up to it
+const FOOD_MARK_STRENGTH: f32 = 20.0;
+// Scouts have reported their find once back this close to the nest
+const REPORT_DISTANCE: f32 = 3.0 * TILE_SIZE;
+
+// Somewhere on the surface far from the colony
+fn pick_destination(colony: &Colony, tile_store: &TileStore, rng: &mut impl Rng) -> Vec2 {
+    let mut destination = colony.entrance;
+    for _ in 0..MAX_DESTINATION_ATTEMPTS {
+        let column = rng.gen_range(-WORLD_HALF_SIZE + 1..WORLD_HALF_SIZE);
+        destination = ground_level(tile_store, column);
+        if destination.distance(colony.position) >= SCOUT_MIN_DISTANCE {
+            break;
+        }
+    }
+    destination
+}
+
+// Scouts roam far over the surface. When one comes across food nobody has
+// found yet it marks it and heads straight home, laying a food trail behind
+// it that foragers can follow back out.
+pub fn scout(
+    time: Res<Time>,
+    tile_store: Res<TileStore>,
+    mut pheromones: ResMut<ColonyPheromones>,
+    colony_query: Query<&Colony>,
+    mut food_query: Query<(&Transform, &mut FoodSource), Without<Ant>>,
+    mut scouts: Query<(Entity, &Transform, &mut Ant, &ColonyMember)>,
+) {
+    let mut rng = rand::thread_rng();
+    let delta_seconds = time.delta_seconds();
+
+    for (entity, transform, mut ant, colony_member) in scouts.iter_mut() {
+        if ant.role != AntRole::Scout || ant.behavior != Behavior::Work {
+            continue;
+        }
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+
+        match ant.scout_state {
+            ScoutState::Exploring => {
+                let found = food_query.iter_mut().find(|(food_transform, food)| {
+                    !food.is_marked_by(colony_member.colony_id)
+                        && food.amount > 0
+                        && food_transform.translation.truncate().distance(position)
+                            <= SCOUT_SENSE_RADIUS
+                });
+
+                if let Some((food_transform, mut food)) = found {
+                    let food_position = food_transform.translation.truncate();
+                    food.mark(colony_member.colony_id);
+                    pheromones.colony_mut(colony_member.colony_id).deposit(
+                        food_position,
+                        PheromoneChannel::Food,
+                        FOOD_MARK_STRENGTH,
+                    );
+                    println!(
+                        "Scout {:?} found {:?} at {:?}, reporting back",
+                        entity, food.kind, food_position
+                    );
+                    ant.scout_state = ScoutState::ReportingFood { since_food: 0.0 };
+                    ant.head_to(colony.position);
+                } else if ant.target_position.is_none() {
+                    let destination = pick_destination(colony, &tile_store, &mut rng);
+                    println!("Scout {:?} heading out to {:?}", entity, destination);
+                    ant.head_to(destination);
+                }
+            }
+            ScoutState::ReportingFood { since_food } => {
+                let since_food = since_food + delta_seconds;
+                pheromones.colony_mut(colony_member.colony_id).deposit(
+                    position,
+                    PheromoneChannel::Food,
+                    TRAIL_DEPOSIT_RATE * delta_seconds * (-since_food / TRAIL_FADE_TIME).exp(),
+                );
+
+                if position.distance(colony.position) <= REPORT_DISTANCE {
+                    println!("Scout {:?} reported food to the colony", entity);
+                    ant.scout_state = ScoutState::Exploring;
+                } else {
+                    ant.scout_state = ScoutState::ReportingFood { since_food };
+                    // Pick the way home back up after being sent elsewhere
+                    if ant.target_position.is_none() {
+                        ant.head_to(colony.position);
+                    }
+                }
+            }
+        }
+    }
+}
@@ -0,0 +1,199 @@
+use super::painting::DesignationTool;
+use crate::ant::components::{Ant, AntRole, Selected};
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+
+// Clicking this close to an ant selects it
+const SELECT_RADIUS: f32 = 1.5 * TILE_SIZE;
+// A left-button drag shorter than this is taken as a click
+const BOX_SELECT_THRESHOLD: f32 = 1.0 * TILE_SIZE;
+// Selected ants are ringed this far out from their middle
+const HIGHLIGHT_RADIUS: f32 = 6.0;
+const HIGHLIGHT_COLOR: Color = Color::rgb(0.3, 1.0, 0.4);
+const SELECTION_BOX_COLOR: Color = Color::rgba(0.3, 1.0, 0.4, 0.6);
+
+const GROUP_KEYS: [KeyCode; 10] = [
+    KeyCode::Key0,
+    KeyCode::Key1,
+    KeyCode::Key2,
+    KeyCode::Key3,
+    KeyCode::Key4,
+    KeyCode::Key5,
+    KeyCode::Key6,
+    KeyCode::Key7,
+    KeyCode::Key8,
+    KeyCode::Key9,
+];
+
+// The ants saved under each number key
+#[derive(Resource, Default)]
+pub struct ControlGroups {
+    groups: [Vec<Entity>; 10],
+}
+
+// Where the current left-button drag started, in world coordinates
+#[derive(Resource, Default)]
+pub struct SelectionDrag {
+    start: Option<Vec2>,
+}
+
+pub fn cursor_world_position(
+    windows: &Query<&Window>,
+    camera_q: &Query<(&Camera, &GlobalTransform)>,
+) -> Option<Vec2> {
+    let cursor_pos = windows.get_single().ok()?.cursor_position()?;
+    let (camera, camera_transform) = camera_q.get_single().ok()?;
+    camera.viewport_to_world_2d(camera_transform, cursor_pos)
+}
+
+pub fn shift_held(keyboard_input: &Input<KeyCode>) -> bool {
+    keyboard_input.any_pressed([KeyCode::ShiftLeft, KeyCode::ShiftRight])
+}
+
+// Left click selects the ant under the cursor and dragging selects every ant
+// inside the box. Holding shift adds to the selection instead of replacing it.
+// The queen can't be given orders, so she is never selected. The left button
+// paints designations instead while a painting tool is active.
+#[allow(clippy::too_many_arguments)]
+pub fn handle_selection(
+    mut commands: Commands,
+    mouse_input: Res<Input<MouseButton>>,
+    keyboard_input: Res<Input<KeyCode>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    tool: Res<DesignationTool>,
+    ants: Query<(Entity, &Transform, &Ant, Has<Selected>)>,
+    mut drag: ResMut<SelectionDrag>,
+) {
+    if tool.active.is_some() {
+        drag.start = None;
+        return;
+    }
+    if mouse_input.just_pressed(MouseButton::Left) {
+        drag.start = cursor_world_position(&windows, &camera_q);
+    }
+    if !mouse_input.just_released(MouseButton::Left) {
+        return;
+    }
+    let (Some(start), Some(end)) = (
+        drag.start.take(),
+        cursor_world_position(&windows, &camera_q),
+    ) else {
+        return;
+    };
+
+    let picked: Vec<Entity> = if start.distance(end) < BOX_SELECT_THRESHOLD {
+        ants.iter()
+            .filter(|(_, _, ant, _)| ant.role != AntRole::Queen)
+            .map(|(entity, transform, ..)| (entity, transform.translation.truncate().distance(end)))
+            .filter(|&(_, distance)| distance <= SELECT_RADIUS)
+            .min_by(|a, b| a.1.total_cmp(&b.1))
+            .map(|(entity, _)| entity)
+            .into_iter()
+            .collect()
+    } else {
+        let area = Rect::from_corners(start, end);
+        ants.iter()
+            .filter(|(_, transform, ant, _)| {
+                ant.role != AntRole::Queen && area.contains(transform.translation.truncate())
+            })
+            .map(|(entity, ..)| entity)
+            .collect()
+    };
+
+    if !shift_held(&keyboard_input) {
+        for (entity, _, _, selected) in ants.iter() {
+            if selected && !picked.contains(&entity) {
+                commands.entity(entity).remove::<Selected>();
+            }
+        }
+    }
+    for entity in &picked {
+        commands.entity(*entity).insert(Selected);
+    }
+    println!("Selected {} ant(s)", picked.len());
+}
+
+// Ctrl with a number key saves the selection as that group; the number key on
+// its own selects the group again, or adds it to the selection with shift.
+// Escape clears the selection.
+pub fn handle_control_groups(
+    mut commands: Commands,
+    keyboard_input: Res<Input<KeyCode>>,
+    mut groups: ResMut<ControlGroups>,
+    ants: Query<(Entity, Has<Selected>), With<Ant>>,
+) {
+    if keyboard_input.just_pressed(KeyCode::Escape) {
+        for (entity, selected) in ants.iter() {
+            if selected {
+                commands.entity(entity).remove::<Selected>();
+            }
+        }
+        return;
+    }
+
+    let Some(number) = GROUP_KEYS
+        .iter()
+        .position(|key| keyboard_input.just_pressed(*key))
+    else {
+        return;
+    };
+
+    if keyboard_input.any_pressed([KeyCode::ControlLeft, KeyCode::ControlRight]) {
+        groups.groups[number] = ants
+            .iter()
+            .filter(|&(_, selected)| selected)
+            .map(|(entity, _)| entity)
+            .collect();
+        println!(
+            "Saved {} ant(s) as group {}",
+            groups.groups[number].len(),
+            number
+        );
+        return;
+    }
+
+    // Ants that have died since the group was saved drop out of it
+    groups.groups[number].retain(|entity| ants.contains(*entity));
+    let group = &groups.groups[number];
+    if !shift_held(&keyboard_input) {
+        for (entity, selected) in ants.iter() {
+            if selected && !group.contains(&entity) {
+                commands.entity(entity).remove::<Selected>();
+            }
+        }
+    }
+    for entity in group {
+        commands.entity(*entity).insert(Selected);
+    }
+    println!("Selected group {} ({} ant(s))", number, group.len());
+}
+
+// Ring every selected ant, and draw the box while one is being dragged out
+pub fn draw_selection(
+    mut gizmos: Gizmos,
+    mouse_input: Res<Input<MouseButton>>,
+    windows: Query<&Window>,
+    camera_q: Query<(&Camera, &GlobalTransform)>,
+    drag: Res<SelectionDrag>,
+    selected: Query<&Transform, (With<Ant>, With<Selected>)>,
+) {
+    for transform in selected.iter() {
+        gizmos.circle_2d(
+            transform.translation.truncate(),
+            HIGHLIGHT_RADIUS,
+            HIGHLIGHT_COLOR,
+        );
+    }
+
+    if !mouse_input.pressed(MouseButton::Left) {
+        return;
+    }
+    let (Some(start), Some(end)) = (drag.start, cursor_world_position(&windows, &camera_q)) else {
+        return;
+    };
+    if start.distance(end) >= BOX_SELECT_THRESHOLD {
+        let area = Rect::from_corners(start, end);
+        gizmos.rect_2d(area.center(), 0.0, area.size(), SELECTION_BOX_COLOR);
+    }
+}
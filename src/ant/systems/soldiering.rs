@@ -0,0 +1,110 @@
+use crate::ant::components::{Ant, AntRole, Behavior, SoldierState};
+use crate::colony::{Colony, ColonyMember};
+use crate::terrain::pheromone::{ColonyPheromones, PheromoneChannel};
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// Seconds a soldier stands guard at the entrance between patrols
+const GUARD_TIME: f32 = 30.0;
+// Soldiers smell alarm scent this many tiles away, and respond to it once
+// it's at least this strong
+const ALARM_SENSE_RADIUS: i32 = 8;
+const ALARM_THRESHOLD: f32 = 0.5;
+// A new source of alarm closer than this to the one a soldier is already
+// answering is the same trouble
+const ALARM_SAME_SOURCE_DISTANCE: f32 = 2.0 * TILE_SIZE;
+
+// Soldiers count as having reached a stop on their patrol this close to it
+const PATROL_STOP_DISTANCE: f32 = 2.0 * TILE_SIZE;
+
+// Stops along a soldier's patrol, starting with the guard post at the entrance
+fn patrol_route(colony: &Colony) -> [Vec2; 3] {
+    [colony.entrance, colony.position, colony.royal_chamber]
+}
+
+// Soldiers guard the nest entrance, patrol down through the nest and back,
+// and drop everything to go wherever alarm scent is coming from. Raiders are
+// left to `raid`.
+pub fn soldier_duty(
+    time: Res<Time>,
+    pheromones: Res<ColonyPheromones>,
+    colony_query: Query<&Colony>,
+    mut soldiers: Query<(Entity, &Transform, &mut Ant, &mut Velocity, &ColonyMember)>,
+) {
+    for (entity, transform, mut ant, mut velocity, colony_member) in soldiers.iter_mut() {
+        if ant.role != AntRole::Soldier
+            || ant.behavior != Behavior::Work
+            || matches!(
+                ant.soldier_state,
+                SoldierState::Raiding { .. } | SoldierState::CarryingLoot { .. }
+            )
+        {
+            continue;
+        }
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+        let position = transform.translation.truncate();
+
+        let alarm = pheromones
+            .colony(colony_member.colony_id)
+            .and_then(|grid| {
+                grid.strongest_near(position, ALARM_SENSE_RADIUS, PheromoneChannel::Alarm)
+            })
+            .filter(|&(_, strength)| strength >= ALARM_THRESHOLD);
+        if let Some((source, _)) = alarm {
+            let already_answering = matches!(
+                ant.soldier_state,
+                SoldierState::RespondingToAlarm(current)
+                    if current.distance(source) < ALARM_SAME_SOURCE_DISTANCE
+            );
+            if !already_answering {
+                println!("Soldier {:?} responding to alarm at {:?}", entity, source);
+                ant.soldier_state = SoldierState::RespondingToAlarm(source);
+                ant.head_to(source);
+            }
+        }
+
+        if ant.target_position.is_some() {
+            continue;
+        }
+
+        let route = patrol_route(colony);
+        match ant.soldier_state {
+            SoldierState::RespondingToAlarm(_) => {
+                // Hold the spot until the alarm dies down
+                if alarm.is_some() {
+                    velocity.linvel = Vec2::ZERO;
+                } else {
+                    ant.soldier_state = SoldierState::Patrolling { leg: 0 };
+                    ant.head_to(route[0]);
+                }
+            }
+            SoldierState::Guarding { remaining } => {
+                velocity.linvel = Vec2::ZERO;
+                let remaining = remaining - time.delta_seconds();
+                if remaining > 0.0 {
+                    ant.soldier_state = SoldierState::Guarding { remaining };
+                } else {
+                    ant.soldier_state = SoldierState::Patrolling { leg: 1 };
+                    ant.head_to(route[1]);
+                }
+            }
+            SoldierState::Patrolling { leg } => {
+                if position.distance(route[leg]) > PATROL_STOP_DISTANCE {
+                    ant.head_to(route[leg]);
+                } else if leg == 0 {
+                    ant.soldier_state = SoldierState::Guarding {
+                        remaining: GUARD_TIME,
+                    };
+                } else {
+                    let leg = (leg + 1) % route.len();
+                    ant.soldier_state = SoldierState::Patrolling { leg };
+                    ant.head_to(route[leg]);
+                }
+            }
+            SoldierState::Raiding { .. } | SoldierState::CarryingLoot { .. } => {}
+        }
+    }
+}
@@ -0,0 +1,123 @@
+use crate::ant::carrying::drop_carried;
+use crate::ant::components::{Ant, Behavior, CarriedItem, Carrying, WorkerState};
+use crate::ant::excavation::DIG_REACH;
+use crate::ant::pathfinding::GridPos;
+use crate::colony::{Colony, ColonyMember};
+use crate::terrain::{place_tile, DirtTile, TileStore, TileUpdateEvent, TILE_SIZE};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// Spoil is spread over this many columns either side of the midden
+const MOUND_RADIUS: i32 = 12;
+// The mound's sides rise one tile for every this many columns
+const MOUND_SLOPE_RUN: i32 = 2;
+// Don't look for the top of the mound further up than this
+const MAX_MOUND_HEIGHT: i32 = 32;
+
+// Pick where the next load of spoil goes: on top of the lowest column around
+// the midden, with columns near the middle allowed to be a little higher, so
+// the spoil builds up into a low cone-shaped mound. Tiles for which
+// `is_blocked` returns true are passed over.
+pub fn find_mound_tile(
+    midden: Vec2,
+    tile_store: &TileStore,
+    is_blocked: impl Fn(GridPos) -> bool,
+) -> Option<Vec2> {
+    let center = GridPos::from_vec2(midden);
+
+    (-MOUND_RADIUS..=MOUND_RADIUS)
+        .map(|dx| {
+            let x = center.x + dx;
+            let mut y = center.y;
+            while y < center.y + MAX_MOUND_HEIGHT
+                && tile_store.is_solid(&GridPos { x, y: y + 1 }.to_vec2())
+            {
+                y += 1;
+            }
+            let height = y - center.y;
+            (GridPos { x, y: y + 1 }, height * MOUND_SLOPE_RUN + dx.abs())
+        })
+        .filter(|&(tile, _)| !is_blocked(tile))
+        .min_by_key(|&(tile, score)| (score, (tile.x - center.x).abs()))
+        .map(|(tile, _)| tile.to_vec2())
+}
+
+// Ants carrying dirt take it up to the colony's midden and add it to the
+// mound there, then go back to looking for work. Any ant may have some to
+// get rid of, since any ant can be ordered to dig.
+pub fn haul_spoil(
+    mut commands: Commands,
+    mut tile_store: ResMut<TileStore>,
+    mut tile_update_events: EventWriter<TileUpdateEvent>,
+    colony_query: Query<&Colony>,
+    positions: Query<(Entity, &Transform), With<Ant>>,
+    mut ants: Query<(
+        Entity,
+        &Transform,
+        &mut Ant,
+        &mut Velocity,
+        &Carrying,
+        &ColonyMember,
+    )>,
+) {
+    let ant_cells: Vec<(Entity, GridPos)> = positions
+        .iter()
+        .map(|(entity, transform)| (entity, GridPos::from_vec2(transform.translation.truncate())))
+        .collect();
+
+    for (entity, transform, mut ant, mut velocity, carrying, colony_member) in ants.iter_mut() {
+        if carrying.item != CarriedItem::Dirt
+            || ant.behavior != Behavior::Work
+            || ant.target_position.is_some()
+        {
+            continue;
+        }
+
+        // Ants that were sent somewhere else still have their load to get rid of
+        if ant.worker_state == WorkerState::SearchingForDigSite {
+            ant.worker_state = WorkerState::HaulingSpoil;
+        }
+        if ant.worker_state != WorkerState::HaulingSpoil {
+            continue;
+        }
+
+        let Ok(colony) = colony_query.get(colony_member.colony_id) else {
+            continue;
+        };
+
+        // Never drop spoil onto an ant, or right next to another one, which
+        // could end up walled in by the mound
+        let is_blocked = |tile: GridPos| {
+            ant_cells.iter().any(|&(other, cell)| {
+                if other == entity {
+                    cell == tile
+                } else {
+                    (cell.x - tile.x).abs() <= 1 && (cell.y - tile.y).abs() <= 1
+                }
+            })
+        };
+        let Some(tile) = find_mound_tile(colony.midden, &tile_store, is_blocked) else {
+            velocity.linvel = Vec2::ZERO;
+            continue;
+        };
+
+        // Drop the load once next to the top of the mound
+        let current_pos = transform.translation.truncate();
+        if current_pos.distance(tile) <= DIG_REACH {
+            place_tile(
+                &mut tile_store,
+                &mut tile_update_events,
+                tile,
+                Box::new(DirtTile),
+            );
+            drop_carried(&mut commands, entity, carrying);
+            println!("Ant {:?} deposited spoil at {:?}", entity, tile);
+
+            velocity.linvel = Vec2::ZERO;
+            ant.worker_state = WorkerState::SearchingForDigSite;
+            continue;
+        }
+
+        ant.head_to(tile + Vec2::new(0.0, TILE_SIZE));
+    }
+}
@@ -0,0 +1,495 @@
+use super::brood::BROOD_PER_NURSE;
+use super::change_role;
+use crate::ant::components::{
+    Ant, AntRole, Behavior, Brood, Carrying, ForagerState, NurseState, ResponseThresholds,
+    ScoutState, SoldierState, Vitals, WorkerState,
+};
+use crate::ant::excavation::ExcavationPlans;
+use crate::colony::{Colony, ColonyMember};
+use crate::food::{FoodSource, FoodStockEmptyEvent, FoodStockLowEvent};
+use crate::terrain::clock::{Season, SimClock};
+use crate::terrain::pheromone::{ColonyPheromones, PheromoneChannel, PheromoneGrid};
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use std::collections::HashMap;
+
+// Seconds between the colony taking stock of what needs doing
+const ALLOCATION_INTERVAL: f32 = 5.0;
+// Tasks an ant can be moved between. The queen only ever lays eggs.
+const TASKS: [AntRole; 5] = [
+    AntRole::Worker,
+    AntRole::Forager,
+    AntRole::Nurse,
+    AntRole::Soldier,
+    AntRole::Scout,
+];
+
+// Innate thresholds vary this much either side of 1.0, and are this much
+// lower for the task an ant hatched into
+const THRESHOLD_SPREAD: f32 = 0.3;
+const SPECIALIST_FACTOR: f32 = 0.5;
+// Doing a task lowers an ant's threshold for it a little each time the
+// colony takes stock, and raises the others, within these limits
+const LEARNING_RATE: f32 = 0.01;
+const FORGETTING_RATE: f32 = 0.005;
+const MIN_THRESHOLD: f32 = 0.4;
+const MAX_THRESHOLD: f32 = 2.0;
+
+// Each task's stimulus starts out here, and rises by this much each time the
+// colony takes stock while the task is short of every ant it wants, or falls
+// as much while it has that many too many, within these limits
+const INITIAL_STIMULUS: f32 = 1.0;
+const STIMULUS_STEP: f32 = 0.5;
+const MAX_STIMULUS: f32 = 4.0;
+// A warning that the store is running low raises the call for foragers by
+// this much at once. Once it's empty foragers and scouts are called for as
+// strongly as they can be.
+const LOW_FOOD_STIMULUS: f32 = 1.0;
+// An ant only switches to a task it responds to this much more strongly than
+// to its own, and then only some of the time so the colony shifts gradually
+const SWITCH_MARGIN: f32 = 0.15;
+const SWITCH_CHANCE: f32 = 0.5;
+
+// Ants wanted for each job. The colony always keeps a digger, a guard and a
+// scout at work.
+const STANDING_DIGGERS: f32 = 1.0;
+const DIG_JOBS_PER_WORKER: f32 = 4.0;
+const FOOD_PER_FORAGER: f32 = 10.0;
+// Foragers wanted when the store is empty, fewer the fuller it is up to this
+const HUNGRY_COLONY_FORAGERS: f32 = 3.0;
+const WELL_STOCKED_FOOD: f32 = 100.0;
+const NEGLECTED_BROOD_PER_NURSE: f32 = 3.0;
+const STANDING_GUARDS: f32 = 1.0;
+// Alarm scent this many tiles from the nest or its entrance calls up this
+// many more soldiers per unit of scent, up to a limit
+const ALARM_SENSE_RADIUS: i32 = 10;
+const SOLDIERS_PER_ALARM: f32 = 2.0;
+const MAX_ALARM_SOLDIERS: f32 = 10.0;
+const STANDING_SCOUTS: f32 = 1.0;
+// Extra scouts sent out when the colony knows of no food left to collect
+const SCOUTS_WHEN_NO_FOOD: f32 = 2.0;
+// Foraging and digging wanted at night and through the seasons, as a share of
+// the usual. Ants dig in the dark, stock up in autumn and lie low in winter.
+const NIGHT_FORAGING: f32 = 0.3;
+const NIGHT_DIGGING: f32 = 1.5;
+const AUTUMN_FORAGING: f32 = 1.5;
+const WINTER_FORAGING: f32 = 0.2;
+const WINTER_DIGGING: f32 = 0.5;
+
+type AllocationQuery = (
+    Entity,
+    &'static mut Ant,
+    &'static mut Sprite,
+    &'static mut Collider,
+    &'static mut ResponseThresholds,
+    &'static Vitals,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+);
+
+// How strongly each colony's ants are being called to each of `TASKS`
+#[derive(Resource)]
+pub struct TaskAllocator {
+    timer: Timer,
+    stimuli: HashMap<Entity, [f32; TASKS.len()]>,
+}
+
+impl Default for TaskAllocator {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(ALLOCATION_INTERVAL, TimerMode::Repeating),
+            stimuli: HashMap::new(),
+        }
+    }
+}
+
+// How many ants a colony wants on each task
+#[derive(Debug, Default, Clone, Copy)]
+struct TaskDemand {
+    dig: f32,
+    forage: f32,
+    nurse: f32,
+    defend: f32,
+    scout: f32,
+}
+
+impl TaskDemand {
+    fn wanted(&self, role: AntRole) -> f32 {
+        match role {
+            AntRole::Worker => self.dig,
+            AntRole::Forager => self.forage,
+            AntRole::Nurse => self.nurse,
+            AntRole::Soldier => self.defend,
+            AntRole::Scout => self.scout,
+            AntRole::Queen => 0.0,
+        }
+    }
+}
+
+// Thresholds a newly hatched ant starts with, lowest for the task it
+// hatched into
+pub fn innate_thresholds(role: AntRole, rng: &mut impl Rng) -> ResponseThresholds {
+    let mut spread = || rng.gen_range(1.0 - THRESHOLD_SPREAD..=1.0 + THRESHOLD_SPREAD);
+    let mut thresholds = ResponseThresholds {
+        dig: spread(),
+        forage: spread(),
+        nurse: spread(),
+        defend: spread(),
+        scout: spread(),
+    };
+    if let Some(threshold) = thresholds.for_role_mut(role) {
+        *threshold *= SPECIALIST_FACTOR;
+    }
+    thresholds
+}
+
+// Young ants take to nursing more readily and old ants to work outside the
+// nest. `age_fraction` runs from 0.0 at hatching to 1.0 at the end of the
+// ant's lifespan.
+fn age_bias(role: AntRole, age_fraction: f32) -> f32 {
+    match role {
+        AntRole::Nurse => 0.5 + 1.5 * age_fraction,
+        AntRole::Forager | AntRole::Scout | AntRole::Soldier => 1.5 - age_fraction,
+        AntRole::Worker | AntRole::Queen => 1.0,
+    }
+}
+
+// How strongly an ant responds to a stimulus, from 0.0 to 1.0. It's even odds
+// when the stimulus matches the threshold.
+fn response(stimulus: f32, threshold: f32) -> f32 {
+    let stimulus = stimulus * stimulus;
+    stimulus / (stimulus + threshold * threshold)
+}
+
+fn task_index(role: AntRole) -> usize {
+    TASKS.iter().position(|&task| task == role).unwrap_or(0)
+}
+
+// A colony warned that its food is running out doesn't wait for the next
+// stock-take to call for more foragers, and sends out scouts to find new food
+// once the store is empty
+pub fn answer_food_shortage(
+    mut allocator: ResMut<TaskAllocator>,
+    mut low_events: EventReader<FoodStockLowEvent>,
+    mut empty_events: EventReader<FoodStockEmptyEvent>,
+) {
+    for event in low_events.read() {
+        let stimuli = allocator
+            .stimuli
+            .entry(event.colony)
+            .or_insert([INITIAL_STIMULUS; TASKS.len()]);
+        let forage = &mut stimuli[task_index(AntRole::Forager)];
+        *forage = (*forage + LOW_FOOD_STIMULUS).min(MAX_STIMULUS);
+        println!(
+            "Colony {:?} is calling for foragers with {:.1} food left",
+            event.colony, event.food
+        );
+    }
+
+    for event in empty_events.read() {
+        let stimuli = allocator
+            .stimuli
+            .entry(event.colony)
+            .or_insert([INITIAL_STIMULUS; TASKS.len()]);
+        stimuli[task_index(AntRole::Forager)] = MAX_STIMULUS;
+        stimuli[task_index(AntRole::Scout)] = MAX_STIMULUS;
+        println!(
+            "Colony {:?} is calling for every forager and scout it can",
+            event.colony
+        );
+    }
+}
+
+// Ants are only moved to a new task between jobs, never in the middle of one
+fn is_between_jobs(ant: &Ant, carrying: Option<&Carrying>) -> bool {
+    if ant.behavior != Behavior::Work || carrying.is_some() {
+        return false;
+    }
+    match ant.role {
+        AntRole::Worker => ant.worker_state == WorkerState::SearchingForDigSite,
+        AntRole::Forager => matches!(ant.forager_state, ForagerState::Exploring { .. }),
+        AntRole::Nurse => ant.nurse_state == NurseState::Idle,
+        AntRole::Soldier => matches!(
+            ant.soldier_state,
+            SoldierState::Guarding { .. } | SoldierState::Patrolling { .. }
+        ),
+        AntRole::Scout => ant.scout_state == ScoutState::Exploring,
+        AntRole::Queen => false,
+    }
+}
+
+// How much foraging and digging matter at this time of day and year
+fn time_priorities(clock: &SimClock) -> (f32, f32) {
+    let (mut forage, mut dig) = match clock.season() {
+        Season::Autumn => (AUTUMN_FORAGING, 1.0),
+        Season::Winter => (WINTER_FORAGING, WINTER_DIGGING),
+        Season::Spring | Season::Summer => (1.0, 1.0),
+    };
+    if clock.is_night() {
+        forage *= NIGHT_FORAGING;
+        dig *= NIGHT_DIGGING;
+    }
+    (forage, dig)
+}
+
+fn colony_demand(
+    colony: &Colony,
+    dig_jobs: usize,
+    brood: &[&Brood],
+    known_food: u32,
+    pheromones: Option<&PheromoneGrid>,
+    clock: &SimClock,
+) -> TaskDemand {
+    // Food beyond what the storage chambers hold would only go to waste
+    let well_stocked = WELL_STOCKED_FOOD.min(colony.food_capacity()).max(1.0);
+    let shortfall = (1.0 - colony.food / well_stocked).clamp(0.0, 1.0);
+    let neglected = brood.iter().filter(|brood| brood.care <= 0.0).count();
+    let alarm = [colony.entrance, colony.position]
+        .iter()
+        .filter_map(|&place| {
+            pheromones?.strongest_near(place, ALARM_SENSE_RADIUS, PheromoneChannel::Alarm)
+        })
+        .map(|(_, strength)| strength)
+        .fold(0.0, f32::max);
+    let (foraging, digging) = time_priorities(clock);
+
+    TaskDemand {
+        dig: (STANDING_DIGGERS + dig_jobs as f32 / DIG_JOBS_PER_WORKER) * digging,
+        forage: (known_food as f32 / FOOD_PER_FORAGER + shortfall * HUNGRY_COLONY_FORAGERS)
+            * foraging,
+        nurse: brood.len().div_ceil(BROOD_PER_NURSE) as f32
+            + neglected as f32 / NEGLECTED_BROOD_PER_NURSE,
+        defend: STANDING_GUARDS + (alarm * SOLDIERS_PER_ALARM).min(MAX_ALARM_SOLDIERS),
+        scout: STANDING_SCOUTS
+            + if known_food == 0 {
+                SCOUTS_WHEN_NO_FOOD
+            } else {
+                0.0
+            },
+    }
+}
+
+// Every so often each colony weighs up what needs doing: tunnels still to
+// dig, food found but not yet brought in, brood going short of care and
+// threats at the nest, with foraging and digging weighed by the time of day
+// and year. The stimulus of a task that's short of ants builds up until
+// enough respond to it. Ants between jobs compare how strongly they respond
+// to each task, given its stimulus, their own thresholds and their age, and
+// some switch to one that still wants more ants.
+#[allow(clippy::too_many_arguments)]
+pub fn allocate_tasks(
+    time: Res<Time>,
+    clock: Res<SimClock>,
+    mut allocator: ResMut<TaskAllocator>,
+    plans: Res<ExcavationPlans>,
+    pheromones: Res<ColonyPheromones>,
+    colony_query: Query<(Entity, &Colony)>,
+    brood_query: Query<&Brood>,
+    food_query: Query<&FoodSource>,
+    mut ants: Query<AllocationQuery>,
+) {
+    if !allocator.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let mut rng = rand::thread_rng();
+
+    let mut counts: HashMap<(Entity, usize), usize> = HashMap::new();
+    for (_, ant, _, _, _, _, colony_member, _) in ants.iter() {
+        if let Some(task) = TASKS.iter().position(|&task| task == ant.role) {
+            *counts.entry((colony_member.colony_id, task)).or_default() += 1;
+        }
+    }
+
+    let mut demands: HashMap<Entity, TaskDemand> = HashMap::new();
+    for (colony_id, colony) in colony_query.iter() {
+        let brood: Vec<&Brood> = brood_query
+            .iter()
+            .filter(|brood| brood.colony_id == colony_id)
+            .collect();
+        // Food the colony has found and laid a trail to but not yet collected
+        let known_food: u32 = food_query
+            .iter()
+            .filter(|food| food.is_marked_by(colony_id))
+            .map(|food| food.amount)
+            .sum();
+        let demand = colony_demand(
+            colony,
+            plans.jobs_of(colony_id).count(),
+            &brood,
+            known_food,
+            pheromones.colony(colony_id),
+            &clock,
+        );
+
+        let stimuli = allocator
+            .stimuli
+            .entry(colony_id)
+            .or_insert([INITIAL_STIMULUS; TASKS.len()]);
+        for (task, stimulus) in stimuli.iter_mut().enumerate() {
+            let wanted = demand.wanted(TASKS[task]);
+            let count = counts.get(&(colony_id, task)).copied().unwrap_or(0) as f32;
+            let shortage = (wanted - count) / wanted.max(1.0);
+            *stimulus = (*stimulus + STIMULUS_STEP * shortage).clamp(0.0, MAX_STIMULUS);
+        }
+        demands.insert(colony_id, demand);
+    }
+    let allocator = &*allocator;
+
+    for (
+        entity,
+        mut ant,
+        mut sprite,
+        mut collider,
+        mut thresholds,
+        vitals,
+        colony_member,
+        carrying,
+    ) in ants.iter_mut()
+    {
+        let Some(current) = TASKS.iter().position(|&task| task == ant.role) else {
+            continue;
+        };
+        let (Some(demand), Some(stimuli)) = (
+            demands.get(&colony_member.colony_id),
+            allocator.stimuli.get(&colony_member.colony_id),
+        ) else {
+            continue;
+        };
+
+        for (task, &role) in TASKS.iter().enumerate() {
+            if let Some(threshold) = thresholds.for_role_mut(role) {
+                let change = if task == current {
+                    -LEARNING_RATE
+                } else {
+                    FORGETTING_RATE
+                };
+                *threshold = (*threshold + change).clamp(MIN_THRESHOLD, MAX_THRESHOLD);
+            }
+        }
+
+        if !is_between_jobs(&ant, carrying) {
+            continue;
+        }
+
+        let age_fraction = (vitals.age / vitals.lifespan).clamp(0.0, 1.0);
+        let respond = |task: usize| {
+            let role = TASKS[task];
+            let threshold = thresholds.for_role(role).unwrap_or(MAX_THRESHOLD);
+            response(stimuli[task], threshold * age_bias(role, age_fraction))
+        };
+
+        // Tasks that already have all the ants they want take no more
+        let current_response = respond(current);
+        let best = (0..TASKS.len())
+            .filter(|&task| {
+                let count = counts.get(&(colony_member.colony_id, task)).copied();
+                task != current && (count.unwrap_or(0) as f32) < demand.wanted(TASKS[task])
+            })
+            .map(|task| (task, respond(task)))
+            .max_by(|a, b| a.1.total_cmp(&b.1));
+
+        let Some((task, best_response)) = best else {
+            continue;
+        };
+        if best_response < current_response + SWITCH_MARGIN || !rng.gen_bool(SWITCH_CHANCE as f64) {
+            continue;
+        }
+
+        let role = TASKS[task];
+        println!(
+            "Ant {:?} switched from {:?} to {:?} at {:.0}% of its lifespan",
+            entity,
+            ant.role,
+            role,
+            age_fraction * 100.0
+        );
+        if let Some(count) = counts.get_mut(&(colony_member.colony_id, current)) {
+            *count = count.saturating_sub(1);
+        }
+        *counts.entry((colony_member.colony_id, task)).or_default() += 1;
+        change_role(&mut ant, &mut sprite, &mut collider, role);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    #[test]
+    fn response_is_even_odds_when_the_stimulus_matches_the_threshold() {
+        for threshold in [0.4, 1.0, 2.0] {
+            assert!((response(threshold, threshold) - 0.5).abs() < 1e-6);
+        }
+        assert_eq!(response(0.0, 1.0), 0.0);
+    }
+
+    #[test]
+    fn response_grows_with_the_stimulus_and_falls_with_the_threshold() {
+        let mut last = 0.0;
+        for step in 1..=8 {
+            let current = response(step as f32 * 0.5, 1.0);
+            assert!(current > last && current < 1.0);
+            last = current;
+        }
+        assert!(response(1.0, MIN_THRESHOLD) > response(1.0, MAX_THRESHOLD));
+    }
+
+    #[test]
+    fn innate_thresholds_are_lowest_for_the_task_an_ant_hatched_into() {
+        let mut rng = StdRng::seed_from_u64(7);
+        for _ in 0..100 {
+            for role in TASKS {
+                let thresholds = innate_thresholds(role, &mut rng);
+                let own = thresholds.for_role(role).unwrap();
+                assert!(own <= (1.0 + THRESHOLD_SPREAD) * SPECIALIST_FACTOR);
+                for other in TASKS.iter().filter(|&&other| other != role) {
+                    let threshold = thresholds.for_role(*other).unwrap();
+                    assert!((1.0 - THRESHOLD_SPREAD..=1.0 + THRESHOLD_SPREAD).contains(&threshold));
+                    assert!(own < threshold);
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn young_ants_nurse_and_old_ants_work_outside() {
+        assert!(age_bias(AntRole::Nurse, 0.0) < age_bias(AntRole::Nurse, 1.0));
+        for role in [AntRole::Forager, AntRole::Scout, AntRole::Soldier] {
+            assert!(age_bias(role, 0.0) > age_bias(role, 1.0));
+            // A young ant is pulled towards nursing, an old one away from it
+            assert!(age_bias(AntRole::Nurse, 0.0) < age_bias(role, 0.0));
+            assert!(age_bias(AntRole::Nurse, 1.0) > age_bias(role, 1.0));
+        }
+        assert_eq!(age_bias(AntRole::Worker, 0.3), 1.0);
+    }
+
+    #[test]
+    fn food_warnings_call_for_foragers_and_then_scouts() {
+        let mut app = App::new();
+        app.init_resource::<TaskAllocator>()
+            .add_event::<FoodStockLowEvent>()
+            .add_event::<FoodStockEmptyEvent>()
+            .add_systems(Update, answer_food_shortage);
+        let colony = app.world.spawn_empty().id();
+        let stimulus = |app: &App, role: AntRole| {
+            app.world.resource::<TaskAllocator>().stimuli[&colony][task_index(role)]
+        };
+
+        app.world
+            .send_event(FoodStockLowEvent { colony, food: 2.0 });
+        app.update();
+        assert_eq!(
+            stimulus(&app, AntRole::Forager),
+            INITIAL_STIMULUS + LOW_FOOD_STIMULUS
+        );
+        assert_eq!(stimulus(&app, AntRole::Scout), INITIAL_STIMULUS);
+
+        app.world.send_event(FoodStockEmptyEvent { colony });
+        app.update();
+        assert_eq!(stimulus(&app, AntRole::Forager), MAX_STIMULUS);
+        assert_eq!(stimulus(&app, AntRole::Scout), MAX_STIMULUS);
+    }
+}
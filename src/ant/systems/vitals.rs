@@ -0,0 +1,213 @@
+use crate::ant::behavior::HUNGRY_THRESHOLD;
+use crate::ant::carrying::drop_carried;
+use crate::ant::components::{
+    Ant, AntRole, CarriedItem, Carrying, Corpse, Health, Vitals, WorkerState, CORPSE_COLOR,
+};
+use crate::colony::ColonyMember;
+use crate::terrain::climate::Climate;
+use crate::terrain::clock::SimClock;
+use crate::terrain::weather::Weather;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+
+// Satiety lost per second just from being alive, and extra at full speed
+const HUNGER_RATE: f32 = 1.0 / 300.0;
+const MOVING_HUNGER_RATE: f32 = 1.0 / 600.0;
+// Energy spent per second walking at full speed, and while digging
+const MOVING_ENERGY_COST: f32 = 1.0 / 120.0;
+const DIGGING_ENERGY_COST: f32 = 1.0 / 40.0;
+// Energy regained per second while standing still
+const REST_RECOVERY_RATE: f32 = 1.0 / 20.0;
+// Below this speed an ant counts as resting
+const RESTING_SPEED: f32 = 5.0;
+// Worn out ants can only crawl along
+const EXHAUSTED_SPEED_FACTOR: f32 = 0.4;
+// Satiety gained per unit of food eaten
+pub const SATIETY_PER_FOOD: f32 = 0.5;
+
+type VitalsQuery = (
+    Entity,
+    &'static Transform,
+    &'static Ant,
+    &'static mut Velocity,
+    &'static mut Vitals,
+    &'static ColonyMember,
+    Option<&'static Carrying>,
+    Option<&'static Health>,
+);
+
+pub fn spawn_corpse(commands: &mut Commands, position: Vec2, in_midden: bool) {
+    commands.spawn((
+        Corpse {
+            claimed_by: None,
+            in_midden,
+        },
+        SpriteBundle {
+            sprite: Sprite {
+                color: CORPSE_COLOR,
+                custom_size: Some(Vec2::new(5.0, 3.0)),
+                ..default()
+            },
+            transform: Transform::from_xyz(position.x, position.y, 6.0),
+            ..default()
+        },
+    ));
+}
+
+pub fn place_corpse_in_midden(commands: &mut Commands, position: Vec2) {
+    spawn_corpse(commands, position, true);
+}
+
+// Remove a dead ant, leaving its body where it fell along with any brood it
+// was carrying
+pub fn die(
+    commands: &mut Commands,
+    entity: Entity,
+    role: AntRole,
+    colony_id: Entity,
+    position: Vec2,
+    cause: &str,
+) {
+    println!(
+        "{:?} ant {:?} died of {} at {:?}",
+        role, entity, cause, position
+    );
+    if role == AntRole::Queen {
+        println!(
+            "The queen of colony {:?} is dead, it will lay no more eggs",
+            colony_id
+        );
+    }
+    // Carried brood is hidden at the spot it was picked up from. Nurses let go
+    // of it once its carrier is gone, so it only needs moving to the body.
+    commands.add(move |world: &mut World| {
+        let Some(&Carrying {
+            item: CarriedItem::Brood(brood),
+            ..
+        }) = world.get::<Carrying>(entity)
+        else {
+            return;
+        };
+        if let Some(mut transform) = world.get_mut::<Transform>(brood) {
+            transform.translation.x = position.x;
+            transform.translation.y = position.y;
+        }
+    });
+    commands.entity(entity).despawn_recursive();
+    spawn_corpse(commands, position, false);
+}
+
+// Ants get hungry and tired as they move and dig. Hungry ants eat food they
+// are carrying; tired ones slow down until they've had a rest. The cold and
+// the dark slow them down too, as does wading through flood water, and ants
+// slowed by the cold burn less food, so a colony sits out the winter in its
+// nest. Ants that starve or grow too old die and leave a corpse behind.
+pub fn update_vitals(
+    mut commands: Commands,
+    time: Res<Time>,
+    clock: Res<SimClock>,
+    climate: Res<Climate>,
+    weather: Res<Weather>,
+    mut ants: Query<VitalsQuery>,
+) {
+    let delta_seconds = time.delta_seconds();
+
+    for (entity, transform, ant, mut velocity, mut vitals, colony_member, carrying, health) in
+        ants.iter_mut()
+    {
+        // Killed in a fight this frame, and already dealt with
+        if health.is_some_and(|health| health.current <= 0.0) {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        let exertion = (velocity.linvel.length() / ant.speed).min(1.0);
+        let activity = clock.activity(&climate, position);
+
+        vitals.age += delta_seconds;
+        vitals.satiety -= (HUNGER_RATE * activity + MOVING_HUNGER_RATE * exertion) * delta_seconds;
+
+        let digging = matches!(ant.worker_state, WorkerState::Digging(_));
+        if digging {
+            vitals.energy -= DIGGING_ENERGY_COST * delta_seconds;
+        } else if velocity.linvel.length() < RESTING_SPEED {
+            vitals.energy += REST_RECOVERY_RATE * delta_seconds;
+        } else {
+            vitals.energy -= MOVING_ENERGY_COST * exertion * delta_seconds;
+        }
+        vitals.energy = vitals.energy.clamp(0.0, 1.0);
+
+        if vitals.satiety <= 0.0 || vitals.age >= vitals.lifespan {
+            let cause = if vitals.satiety <= 0.0 {
+                "starvation"
+            } else {
+                "old age"
+            };
+            die(
+                &mut commands,
+                entity,
+                ant.role,
+                colony_member.colony_id,
+                position,
+                cause,
+            );
+            continue;
+        }
+
+        if vitals.energy <= 0.0 {
+            velocity.linvel *= EXHAUSTED_SPEED_FACTOR;
+        }
+        velocity.linvel *= activity * weather.wading(position);
+
+        // Hungry ants eat what they're carrying rather than go home for it
+        if vitals.satiety >= HUNGRY_THRESHOLD {
+            continue;
+        }
+        if let Some(carrying) = carrying {
+            match carrying.item {
+                CarriedItem::Food(kind) if kind.is_edible() => {
+                    vitals.satiety =
+                        (vitals.satiety + kind.nutrition() * SATIETY_PER_FOOD).min(1.0);
+                    drop_carried(&mut commands, entity, carrying);
+                    println!("Ant {:?} ate the {:?} it was carrying", entity, kind);
+                }
+                _ => {}
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy::ecs::system::CommandQueue;
+
+    #[test]
+    fn brood_carried_by_a_dying_ant_is_left_at_its_body() {
+        let mut world = World::new();
+        let colony = world.spawn_empty().id();
+        let brood = world.spawn(Transform::from_xyz(0.0, 0.0, 7.0)).id();
+        let visual = world.spawn_empty().id();
+        let nurse = world
+            .spawn(Carrying {
+                item: CarriedItem::Brood(brood),
+                visual,
+            })
+            .id();
+
+        let mut queue = CommandQueue::default();
+        let mut commands = Commands::new(&mut queue, &world);
+        die(
+            &mut commands,
+            nurse,
+            AntRole::Nurse,
+            colony,
+            Vec2::new(40.0, -16.0),
+            "old age",
+        );
+        queue.apply(&mut world);
+
+        assert!(world.get_entity(nurse).is_none());
+        let transform = world.get::<Transform>(brood).unwrap();
+        assert_eq!(transform.translation, Vec3::new(40.0, -16.0, 7.0));
+    }
+}
@@ -0,0 +1,328 @@
+use crate::ant::components::{Ant, AntRole, Behavior, Carrying, ForagerState};
+use crate::colony::ColonyMember;
+use crate::food::{FoodKind, FoodSource};
+use crate::terrain::climate::Climate;
+use crate::terrain::clock::SimClock;
+use crate::terrain::surface::Plant;
+use crate::terrain::TILE_SIZE;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use rand::Rng;
+use std::collections::HashSet;
+
+// Chance each plant starts out with a herd of aphids on it
+const HERD_CHANCE: f64 = 0.5;
+// Aphids in a herd nobody tends, which ladybirds keep from growing, and the
+// most a tended herd grows to
+const WILD_HERD_SIZE: u32 = 3;
+const MAX_HERD_SIZE: u32 = 12;
+// Seconds between a tended herd gaining an aphid, or an untended one losing
+// one to ladybirds
+const BREED_INTERVAL: f32 = 30.0;
+// Loads of honeydew each aphid gives per second when ants stroke it for it,
+// and the share of that it lets drip away by itself when left alone
+const HONEYDEW_RATE: f32 = 0.005;
+const UNTENDED_HONEYDEW: f32 = 0.25;
+// Loads of honeydew a herd holds before the rest drips away unused
+const MAX_HONEYDEW: u32 = 5;
+// Tending ants this close to their herd are looking after it, and it stays
+// tended for this many seconds after they leave
+const TEND_RADIUS: f32 = 2.0 * TILE_SIZE;
+const TENDED_TIME: f32 = 20.0;
+// Exploring foragers notice a herd nobody of theirs is tending from this far
+// away, and stay with it this many seconds before exploring on
+const HERD_SENSE_RADIUS: f32 = 6.0 * TILE_SIZE;
+const TENDING_TIME: f32 = 30.0;
+const APHID_COLOR: Color = Color::rgb(0.6, 0.85, 0.3);
+
+// A herd of aphids living on a surface plant. Ants that tend it protect it
+// from ladybirds so it grows, and milk it for honeydew, which foragers
+// collect from its `FoodSource`.
+#[derive(Component)]
+pub struct AphidHerd {
+    pub aphids: u32,
+    // Honeydew made towards the next load
+    honeydew: f32,
+    // Seconds the herd stays tended without an ant nearby
+    tended_for: f32,
+    breed_timer: Timer,
+}
+
+impl AphidHerd {
+    pub fn is_tended(&self) -> bool {
+        self.tended_for > 0.0
+    }
+}
+
+fn herd_size(aphids: u32) -> Vec2 {
+    Vec2::splat(2.0 + aphids as f32 * 0.4)
+}
+
+// Put aphid herds on some of the plants grown on the surface
+pub fn spawn_aphids(mut commands: Commands, plants: Query<(&Plant, &Transform)>) {
+    let mut rng = rand::thread_rng();
+    for (plant, transform) in plants.iter() {
+        if !rng.gen_bool(HERD_CHANCE) {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        commands.spawn((
+            AphidHerd {
+                aphids: WILD_HERD_SIZE,
+                honeydew: 0.0,
+                tended_for: 0.0,
+                breed_timer: Timer::from_seconds(BREED_INTERVAL, TimerMode::Repeating),
+            },
+            FoodSource {
+                kind: FoodKind::Honeydew,
+                amount: 0,
+                marked_by: Vec::new(),
+                renewable: true,
+            },
+            SpriteBundle {
+                sprite: Sprite {
+                    color: APHID_COLOR,
+                    custom_size: Some(herd_size(WILD_HERD_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, 4.5),
+                ..default()
+            },
+        ));
+        println!(
+            "A herd of aphids lives on the {:?} at {:?}",
+            plant.kind, plant.root
+        );
+    }
+}
+
+type TenderQuery = (
+    Entity,
+    &'static Transform,
+    &'static mut Ant,
+    &'static mut Velocity,
+    &'static ColonyMember,
+    Has<Carrying>,
+);
+
+// An exploring forager that passes a herd none of its colony is tending
+// walks over and stays with it a while, stroking the aphids for honeydew and
+// keeping ladybirds off, then explores on from there. One called away or
+// unable to get there gives up on the herd.
+pub fn send_tenders(
+    time: Res<Time>,
+    herds: Query<(Entity, &Transform), With<AphidHerd>>,
+    mut ants: Query<TenderQuery>,
+) {
+    let mut tended: HashSet<(Entity, Entity)> = ants
+        .iter()
+        .filter_map(|(_, _, ant, _, member, _)| match ant.forager_state {
+            ForagerState::TendingAphids { herd, .. } => Some((member.colony_id, herd)),
+            _ => None,
+        })
+        .collect();
+
+    for (entity, transform, mut ant, mut velocity, member, carrying) in ants.iter_mut() {
+        if ant.role != AntRole::Forager || ant.behavior != Behavior::Work {
+            continue;
+        }
+        let position = transform.translation.truncate();
+
+        match ant.forager_state {
+            ForagerState::Exploring { since_home } => {
+                if carrying || ant.target_position.is_some() {
+                    continue;
+                }
+                let Some((herd, herd_position)) = herds
+                    .iter()
+                    .map(|(herd, herd_transform)| (herd, herd_transform.translation.truncate()))
+                    .filter(|&(herd, herd_position)| {
+                        herd_position.distance(position) <= HERD_SENSE_RADIUS
+                            && !tended.contains(&(member.colony_id, herd))
+                    })
+                    .min_by(|(_, a), (_, b)| a.distance(position).total_cmp(&b.distance(position)))
+                else {
+                    continue;
+                };
+                println!(
+                    "Forager {:?} is going to tend the aphids at {:?}",
+                    entity, herd_position
+                );
+                tended.insert((member.colony_id, herd));
+                ant.forager_state = ForagerState::TendingAphids {
+                    herd,
+                    remaining: TENDING_TIME,
+                    since_home,
+                };
+                ant.head_to(herd_position);
+            }
+            ForagerState::TendingAphids {
+                herd,
+                remaining,
+                since_home,
+            } => {
+                if ant.target_position.is_some() {
+                    continue;
+                }
+                let near_herd = herds.get(herd).is_ok_and(|(_, herd_transform)| {
+                    herd_transform.translation.truncate().distance(position) <= TEND_RADIUS
+                });
+                let remaining = remaining - time.delta_seconds();
+                if !near_herd || remaining <= 0.0 {
+                    ant.forager_state = ForagerState::Exploring { since_home };
+                    continue;
+                }
+                velocity.linvel = Vec2::ZERO;
+                ant.forager_state = ForagerState::TendingAphids {
+                    herd,
+                    remaining,
+                    since_home,
+                };
+            }
+            ForagerState::ReturningWithFood { .. } => {}
+        }
+    }
+}
+
+// Aphids make honeydew, much faster when ants are there to tend them, and
+// slow down in the cold like the ants do. A tended herd grows; one left
+// alone is whittled back down by ladybirds. Only ants staying with the herd
+// to tend it count, not ones passing by. They learn where it is, so their
+// colony counts its honeydew as food it knows of.
+pub fn tend_aphids(
+    time: Res<Time>,
+    clock: Res<SimClock>,
+    climate: Res<Climate>,
+    ants: Query<(&Transform, &Ant, &ColonyMember)>,
+    mut herds: Query<(
+        Entity,
+        &Transform,
+        &mut AphidHerd,
+        &mut FoodSource,
+        &mut Sprite,
+    )>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for (herd_entity, transform, mut herd, mut food, mut sprite) in herds.iter_mut() {
+        let position = transform.translation.truncate();
+        let tending = ants
+            .iter()
+            .find(|(ant_transform, ant, _)| {
+                matches!(
+                    ant.forager_state,
+                    ForagerState::TendingAphids { herd, .. } if herd == herd_entity
+                ) && ant_transform.translation.truncate().distance(position) <= TEND_RADIUS
+            })
+            .map(|(_, _, member)| member);
+        if let Some(member) = tending {
+            if !herd.is_tended() {
+                println!(
+                    "Colony {:?} is tending the aphids at {:?}",
+                    member.colony_id, position
+                );
+            }
+            herd.tended_for = TENDED_TIME;
+            food.mark(member.colony_id);
+        } else {
+            herd.tended_for = (herd.tended_for - delta_seconds).max(0.0);
+        }
+
+        let activity = clock.activity(&climate, position);
+        let milking = if herd.is_tended() {
+            1.0
+        } else {
+            UNTENDED_HONEYDEW
+        };
+        herd.honeydew += HONEYDEW_RATE * herd.aphids as f32 * milking * activity * delta_seconds;
+        if herd.honeydew >= 1.0 {
+            herd.honeydew -= 1.0;
+            food.amount = (food.amount + 1).min(MAX_HONEYDEW);
+        }
+
+        if herd.breed_timer.tick(time.delta()).just_finished() {
+            if herd.is_tended() {
+                herd.aphids = (herd.aphids + 1).min(MAX_HERD_SIZE);
+            } else if herd.aphids > WILD_HERD_SIZE {
+                herd.aphids -= 1;
+            }
+            sprite.custom_size = Some(herd_size(herd.aphids));
+        }
+    }
+}
+
+pub struct AphidPlugin;
+
+impl Plugin for AphidPlugin {
+    fn build(&self, app: &mut App) {
+        // Plants are grown during startup, so the aphids settle on them once
+        // they exist
+        app.add_systems(PostStartup, spawn_aphids)
+            .add_systems(Update, (send_tenders, tend_aphids).chain());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    // Runs `tend_aphids` once with a forager of the given state standing on
+    // a herd, and returns whether the herd is being tended
+    fn is_tended_with(forager_state: impl FnOnce(Entity) -> ForagerState) -> bool {
+        let mut app = App::new();
+        app.init_resource::<Time>()
+            .init_resource::<SimClock>()
+            .init_resource::<Climate>()
+            .add_systems(Update, tend_aphids);
+        let herd = app
+            .world
+            .spawn((
+                Transform::default(),
+                AphidHerd {
+                    aphids: WILD_HERD_SIZE,
+                    honeydew: 0.0,
+                    tended_for: 0.0,
+                    breed_timer: Timer::from_seconds(BREED_INTERVAL, TimerMode::Repeating),
+                },
+                FoodSource {
+                    kind: FoodKind::Honeydew,
+                    amount: 0,
+                    marked_by: Vec::new(),
+                    renewable: true,
+                },
+                Sprite::default(),
+            ))
+            .id();
+        app.world.spawn((
+            Transform::default(),
+            Ant {
+                role: AntRole::Forager,
+                forager_state: forager_state(herd),
+                ..default()
+            },
+            ColonyMember {
+                colony_id: Entity::PLACEHOLDER,
+            },
+        ));
+
+        app.update();
+
+        app.world.get::<AphidHerd>(herd).unwrap().is_tended()
+    }
+
+    #[test]
+    fn only_ants_staying_to_tend_a_herd_count() {
+        assert!(!is_tended_with(|_| ForagerState::Exploring {
+            since_home: 0.0
+        }));
+        assert!(!is_tended_with(|_| ForagerState::TendingAphids {
+            herd: Entity::PLACEHOLDER,
+            remaining: TENDING_TIME,
+            since_home: 0.0,
+        }));
+        assert!(is_tended_with(|herd| ForagerState::TendingAphids {
+            herd,
+            remaining: TENDING_TIME,
+            since_home: 0.0,
+        }));
+    }
+}
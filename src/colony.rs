@@ -1,8 +1,169 @@
+use crate::food::FoodKind;
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::{setup_terrain, NestSites, ENTRANCE_WIDTH, SURFACE_Y, TILE_SIZE};
 use bevy::prelude::*;
 
+// Horizontal distance from the entrance shaft to the spoil heap on the surface,
+// far enough that the mound never spills into the shaft
+pub const MIDDEN_OFFSET: f32 = 16.0 * TILE_SIZE;
+// The royal chamber sits this far below the nest, with room for the queen
+// and her eggs
+pub const ROYAL_CHAMBER_DEPTH: f32 = 10.0 * TILE_SIZE;
+pub const ROYAL_CHAMBER_RADIUS: f32 = 3.0 * TILE_SIZE;
+// Size of the cavity hollowed out around the nest, which the colony uses as
+// its food store
+pub const CENTRAL_CAVITY_RADIUS: f32 = 5.0 * TILE_SIZE;
+// Food in a new colony's store, enough to get it started
+const INITIAL_COLONY_FOOD: f32 = 50.0;
+// Each colony is told apart by its color, given out in this order
+const COLONY_COLORS: [Color; 4] = [
+    Color::rgb(0.2, 0.4, 1.0),
+    Color::rgb(1.0, 0.3, 0.2),
+    Color::rgb(0.9, 0.8, 0.1),
+    Color::rgb(0.8, 0.3, 0.9),
+];
+
 #[derive(Component)]
 pub struct Colony {
     pub position: Vec2,
+    // Marks the nest and the colony's ants
+    pub color: Color,
+    // Where excavated dirt is piled up, next to the nest entrance
+    pub midden: Vec2,
+    // Where the queen lives and lays her eggs
+    pub royal_chamber: Vec2,
+    // Top of the shaft leading out of the nest, where soldiers stand guard
+    pub entrance: Vec2,
+    // Whether the entrance has been plugged with soil to keep the rain out
+    pub entrance_plugged: bool,
+    // Food stored in the nest, eaten by hungry colony members
+    pub food: f32,
+    // Whether the colony has already been warned about running low or out
+    pub food_low: bool,
+    pub food_empty: bool,
+    // Plant matter in the fungus gardens, waiting for the fungus to grow on it
+    pub leaves: f32,
+    // The chambers the nest has been dug into, found by `survey_chambers`
+    pub chambers: Vec<Chamber>,
+}
+
+impl Colony {
+    // A new colony around the nest at `position`, with its royal chamber
+    // below, its entrance straight above and its midden beside the entrance
+    pub fn new(position: Vec2, color: Color) -> Self {
+        Self {
+            position,
+            color,
+            midden: Vec2::new(position.x + MIDDEN_OFFSET, SURFACE_Y),
+            royal_chamber: position - Vec2::new(0.0, ROYAL_CHAMBER_DEPTH),
+            entrance: Vec2::new(
+                position.x + (ENTRANCE_WIDTH - 1) as f32 * TILE_SIZE / 2.0,
+                SURFACE_Y,
+            ),
+            entrance_plugged: false,
+            food: INITIAL_COLONY_FOOD,
+            food_low: false,
+            food_empty: false,
+            leaves: 0.0,
+            chambers: Vec::new(),
+        }
+    }
+
+    // Places nurses can keep brood: the royal chamber, deep and damp, the
+    // nest itself, nearer the warmer surface, and any nurseries
+    pub fn brood_sites(&self) -> Vec<Vec2> {
+        let mut sites = vec![self.royal_chamber, self.position];
+        sites.extend(
+            self.chambers_for(ChamberPurpose::Nursery)
+                .map(|chamber| chamber.center),
+        );
+        sites
+    }
+
+    pub fn chambers_for(&self, purpose: ChamberPurpose) -> impl Iterator<Item = &Chamber> {
+        self.chambers
+            .iter()
+            .filter(move |chamber| chamber.purpose == purpose)
+    }
+
+    // The chamber for `purpose` that contains `position`, if any
+    pub fn chamber_at(&self, purpose: ChamberPurpose, position: Vec2) -> Option<&Chamber> {
+        self.chambers_for(purpose)
+            .find(|chamber| chamber.contains(position))
+    }
+
+    pub fn chamber_with_room(&self, purpose: ChamberPurpose) -> Option<&Chamber> {
+        self.chambers_for(purpose)
+            .find(|chamber| chamber.has_room())
+    }
+
+    // Food the colony's storage chambers can hold between them
+    pub fn food_capacity(&self) -> f32 {
+        self.chambers_for(ChamberPurpose::FoodStorage)
+            .map(Chamber::capacity)
+            .sum()
+    }
+
+    // Put food in the store, as much as the storage chambers have room for.
+    // Returns how much was stored.
+    pub fn store_food(&mut self, amount: f32) -> f32 {
+        let stored = amount.min((self.food_capacity() - self.food).max(0.0));
+        self.food += stored;
+        stored
+    }
+
+    // Plant matter the colony's fungus gardens can hold between them
+    pub fn garden_capacity(&self) -> f32 {
+        self.chambers_for(ChamberPurpose::FungusGarden)
+            .map(Chamber::capacity)
+            .sum()
+    }
+
+    pub fn has_garden_room(&self) -> bool {
+        self.leaves < self.garden_capacity()
+    }
+
+    // Put a load brought home where it belongs: leaves in the fungus gardens
+    // and everything else in the store. Returns how much was kept.
+    pub fn bring_home(&mut self, kind: FoodKind) -> f32 {
+        if kind.is_edible() {
+            return self.store_food(kind.nutrition());
+        }
+        let stored = kind
+            .nutrition()
+            .min((self.garden_capacity() - self.leaves).max(0.0));
+        self.leaves += stored;
+        stored
+    }
+}
+
+// A space dug out of the nest and given over to one purpose
+#[derive(Debug, Clone)]
+pub struct Chamber {
+    pub purpose: ChamberPurpose,
+    // Middle of the chamber's open tiles
+    pub center: Vec2,
+    // Distance from the middle to the furthest open tile
+    pub radius: f32,
+    // Number of open tiles making up the chamber
+    pub tiles: usize,
+    // Brood in a nursery or the royal chamber, or bodies in a midden, as of
+    // the last survey
+    pub occupants: usize,
+}
+
+impl Chamber {
+    pub fn contains(&self, position: Vec2) -> bool {
+        position.distance(self.center) <= self.radius + TILE_SIZE / 2.0
+    }
+
+    pub fn capacity(&self) -> f32 {
+        self.tiles as f32 * self.purpose.capacity_per_tile()
+    }
+
+    pub fn has_room(&self) -> bool {
+        (self.occupants as f32) < self.capacity()
+    }
 }
 
 #[derive(Component)]
@@ -10,32 +171,45 @@ pub struct ColonyMember {
     pub colony_id: Entity,
 }
 
+// The colony whose nest is closest to `position`
+pub fn nearest_colony<'a>(
+    colonies: impl Iterator<Item = (Entity, &'a Colony)>,
+    position: Vec2,
+) -> Option<(Entity, &'a Colony)> {
+    colonies.min_by(|(_, a), (_, b)| {
+        a.position
+            .distance(position)
+            .total_cmp(&b.position.distance(position))
+    })
+}
+
 pub struct ColonyPlugin;
 
 impl Plugin for ColonyPlugin {
     fn build(&self, app: &mut App) {
-        app.add_systems(Startup, spawn_initial_colony);
+        app.add_systems(Startup, spawn_colonies.after(setup_terrain));
     }
 }
 
-fn spawn_initial_colony(mut commands: Commands) {
-    // Spawn the colony at the center with a blue sprite
-    let colony_id = commands
-        .spawn((
-            Colony {
-                position: Vec2::ZERO,
-            },
-            SpriteBundle {
-                sprite: Sprite {
-                    color: Color::BLUE,
-                    custom_size: Some(Vec2::new(10.0, 10.0)), // 2x the ant size (5x5)
+// Found a colony in every nest world generation dug out
+fn spawn_colonies(mut commands: Commands, nest_sites: Res<NestSites>) {
+    for (index, &position) in nest_sites.sites.iter().enumerate() {
+        let color = COLONY_COLORS[index % COLONY_COLORS.len()];
+        let colony_id = commands
+            .spawn((
+                Colony::new(position, color),
+                SpriteBundle {
+                    sprite: Sprite {
+                        color,
+                        custom_size: Some(Vec2::new(10.0, 10.0)), // 2x the ant size (5x5)
+                        ..default()
+                    },
+                    transform: Transform::from_xyz(position.x, position.y, 5.0), // Z=5 to be above terrain but below ants
                     ..default()
                 },
-                transform: Transform::from_xyz(0.0, 0.0, 5.0), // Z=5 to be above terrain but below ants
-                ..default()
-            },
-        ))
-        .id();
+            ))
+            .id();
 
-    println!("Colony spawned at (0,0) with id {:?}", colony_id);
+        println!("Colony spawned at {:?} with id {:?}", position, colony_id);
+    }
 }
@@ -0,0 +1,306 @@
+use crate::colony::{Colony, ColonyMember, MIDDEN_OFFSET};
+use crate::terrain::climate::Climate;
+use crate::terrain::designation::ChamberPurpose;
+use crate::terrain::weather::Weather;
+use crate::terrain::{
+    ground_level, setup_terrain, NestSites, TileStore, TILE_SIZE, WORLD_HALF_SIZE,
+};
+use bevy::prelude::*;
+use rand::Rng;
+
+// Rough estimate of the food each ant eats from the colony store per second
+pub const FOOD_CONSUMPTION_PER_ANT: f32 = 0.01;
+// The colony is warned when its store drops below this many seconds of food
+pub const LOW_FOOD_SECONDS: f32 = 120.0;
+// Plant matter each tile of a fungus garden turns into fungus per second, in
+// the best conditions, and the food each load of plant matter becomes
+const FUNGUS_GROWTH_RATE: f32 = 0.01;
+const FUNGUS_YIELD: f32 = 1.5;
+// The fungus grows best at this temperature, not at all this many degrees
+// either side of it, and needs soil at least this damp to grow at full speed
+const FUNGUS_BEST_TEMPERATURE: f32 = 25.0;
+const FUNGUS_TEMPERATURE_RANGE: f32 = 12.0;
+const FUNGUS_MOISTURE: f32 = 0.6;
+// Number of food sources scattered over the surface
+const FOOD_SOURCE_COUNT: usize = 8;
+// Food is kept at least this many tiles away from the entrance and the midden,
+// and this far apart from other food
+const FOOD_ENTRANCE_CLEARANCE: i32 = 8;
+const FOOD_MIDDEN_CLEARANCE: i32 = 8;
+const FOOD_SPACING: i32 = 6;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FoodKind {
+    Seeds,
+    Carcass,
+    Sugar,
+    // Cut from shrubs and carried home to feed the fungus gardens
+    Leaves,
+    // Milked from aphids herded on the surface plants
+    Honeydew,
+}
+
+impl FoodKind {
+    // The kinds scattered over the surface when the world is made
+    pub const SCATTERED: [FoodKind; 3] = [FoodKind::Seeds, FoodKind::Carcass, FoodKind::Sugar];
+
+    // Loads of food a fresh source holds
+    pub fn initial_amount(&self) -> u32 {
+        match self {
+            FoodKind::Seeds => 30,
+            FoodKind::Carcass => 60,
+            FoodKind::Sugar => 15,
+            FoodKind::Leaves => 10,
+            FoodKind::Honeydew => 5,
+        }
+    }
+
+    // Food added to the colony store for each load brought home. For leaves
+    // it's the plant matter added to the fungus gardens instead.
+    pub fn nutrition(&self) -> f32 {
+        match self {
+            FoodKind::Seeds => 1.0,
+            FoodKind::Carcass => 2.0,
+            FoodKind::Sugar => 3.0,
+            FoodKind::Leaves => 1.0,
+            FoodKind::Honeydew => 2.0,
+        }
+    }
+
+    // Ants can't digest leaves themselves, only the fungus grown on them
+    pub fn is_edible(&self) -> bool {
+        *self != FoodKind::Leaves
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            FoodKind::Seeds => Color::rgb(0.85, 0.75, 0.4),
+            FoodKind::Carcass => Color::rgb(0.6, 0.2, 0.15),
+            FoodKind::Sugar => Color::rgb(0.95, 0.95, 0.9),
+            FoodKind::Leaves => Color::rgb(0.45, 0.75, 0.2),
+            FoodKind::Honeydew => Color::rgb(0.95, 0.8, 0.4),
+        }
+    }
+
+    fn size(&self) -> f32 {
+        match self {
+            FoodKind::Seeds => 5.0,
+            FoodKind::Carcass => 8.0,
+            FoodKind::Sugar => 4.0,
+            FoodKind::Leaves => 6.0,
+            FoodKind::Honeydew => 3.0,
+        }
+    }
+}
+
+#[derive(Component)]
+pub struct FoodSource {
+    pub kind: FoodKind,
+    pub amount: u32,
+    // Colonies whose ants have already found it and laid a trail to it
+    pub marked_by: Vec<Entity>,
+    // Whether it fills up again by itself, so it stays put when emptied
+    pub renewable: bool,
+}
+
+impl FoodSource {
+    pub fn is_marked_by(&self, colony: Entity) -> bool {
+        self.marked_by.contains(&colony)
+    }
+
+    pub fn mark(&mut self, colony: Entity) {
+        if !self.is_marked_by(colony) {
+            self.marked_by.push(colony);
+        }
+    }
+}
+
+// Sent once when a colony's food store runs low, and again if it recovers
+// and then runs low another time
+#[derive(Event)]
+pub struct FoodStockLowEvent {
+    pub colony: Entity,
+    pub food: f32,
+}
+
+// Sent once when a colony has eaten its last food
+#[derive(Event)]
+pub struct FoodStockEmptyEvent {
+    pub colony: Entity,
+}
+
+pub fn spawn_food_source(commands: &mut Commands, kind: FoodKind, position: Vec2) -> Entity {
+    commands
+        .spawn((
+            FoodSource {
+                kind,
+                amount: kind.initial_amount(),
+                marked_by: Vec::new(),
+                renewable: false,
+            },
+            SpriteBundle {
+                sprite: Sprite {
+                    color: kind.color(),
+                    custom_size: Some(Vec2::splat(kind.size())),
+                    ..default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, 5.0),
+                ..default()
+            },
+        ))
+        .id()
+}
+
+pub struct FoodPlugin;
+
+impl Plugin for FoodPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_event::<FoodStockLowEvent>()
+            .add_event::<FoodStockEmptyEvent>()
+            // Food can only be set down once the terrain and nests are laid out
+            .add_systems(Startup, place_food_sources.after(setup_terrain))
+            .add_systems(Update, (grow_fungus, watch_food_stock).chain());
+    }
+}
+
+// Scatter seeds, carcasses and sugar over the surface, resting on the ground
+// and clear of every nest's entrance and midden
+fn place_food_sources(
+    mut commands: Commands,
+    tile_store: Res<TileStore>,
+    nest_sites: Res<NestSites>,
+) {
+    let mut rng = rand::thread_rng();
+    let nest_columns: Vec<i32> = nest_sites
+        .sites
+        .iter()
+        .map(|site| (site.x / TILE_SIZE).round() as i32)
+        .collect();
+    let midden_offset = (MIDDEN_OFFSET / TILE_SIZE).round() as i32;
+    let mut columns: Vec<i32> = Vec::new();
+
+    // Bounded so a crowded surface can't keep us looking forever
+    for _ in 0..FOOD_SOURCE_COUNT * 20 {
+        if columns.len() >= FOOD_SOURCE_COUNT {
+            break;
+        }
+
+        let x = rng.gen_range(-WORLD_HALF_SIZE + 1..WORLD_HALF_SIZE);
+        let too_close_to_nest = nest_columns.iter().any(|&nest| {
+            (x - nest).abs() < FOOD_ENTRANCE_CLEARANCE
+                || (x - nest - midden_offset).abs() < FOOD_MIDDEN_CLEARANCE
+        });
+        if too_close_to_nest
+            || columns
+                .iter()
+                .any(|&other| (other - x).abs() < FOOD_SPACING)
+        {
+            continue;
+        }
+
+        // Rest the food on top of whatever ground is in this column
+        let position = ground_level(&tile_store, x);
+
+        let kind = FoodKind::SCATTERED[rng.gen_range(0..FoodKind::SCATTERED.len())];
+        spawn_food_source(&mut commands, kind, position);
+        columns.push(x);
+        info!("Placed {:?} food source at {:?}", kind, position);
+    }
+
+    info!("Placed {} food sources", columns.len());
+}
+
+// Warn when a colony's store won't feed its members for much longer. The ants
+// themselves eat from the store when they get hungry.
+fn watch_food_stock(
+    mut colonies: Query<(Entity, &mut Colony)>,
+    members: Query<&ColonyMember>,
+    mut low_events: EventWriter<FoodStockLowEvent>,
+    mut empty_events: EventWriter<FoodStockEmptyEvent>,
+) {
+    for (colony_entity, mut colony) in colonies.iter_mut() {
+        let population = members
+            .iter()
+            .filter(|member| member.colony_id == colony_entity)
+            .count();
+        if population == 0 {
+            continue;
+        }
+
+        let low_threshold = population as f32 * FOOD_CONSUMPTION_PER_ANT * LOW_FOOD_SECONDS;
+        if colony.food < low_threshold {
+            if !colony.food_low {
+                colony.food_low = true;
+                println!(
+                    "Colony {:?} is running low on food ({:.1} left for {} ants)",
+                    colony_entity, colony.food, population
+                );
+                low_events.send(FoodStockLowEvent {
+                    colony: colony_entity,
+                    food: colony.food,
+                });
+            }
+        } else {
+            colony.food_low = false;
+        }
+
+        if colony.food <= 0.0 {
+            if !colony.food_empty {
+                colony.food_empty = true;
+                println!("Colony {:?} has run out of food", colony_entity);
+                empty_events.send(FoodStockEmptyEvent {
+                    colony: colony_entity,
+                });
+            }
+        } else {
+            colony.food_empty = false;
+        }
+    }
+}
+
+// How well the fungus grows at `position`, from 0.0 to 1.0. It wants warm,
+// damp soil and drowns under flood water.
+fn fungus_growth(climate: &Climate, weather: &Weather, position: Vec2) -> f32 {
+    if weather.is_flooded(position) {
+        return 0.0;
+    }
+    let warmth = 1.0
+        - (climate.temperature_at(position) - FUNGUS_BEST_TEMPERATURE).abs()
+            / FUNGUS_TEMPERATURE_RANGE;
+    let damp = climate.moisture_at(position) / FUNGUS_MOISTURE;
+    warmth.clamp(0.0, 1.0) * damp.clamp(0.0, 1.0)
+}
+
+// The fungus in each garden feeds on the plant matter the foragers have
+// brought in, and the colony eats the fungus. Each garden works through its
+// share of the plant matter as fast as its conditions allow, and stops when
+// the storage chambers are full. Plant matter a shrunken garden has no room
+// for any more rots away.
+fn grow_fungus(
+    time: Res<Time>,
+    climate: Res<Climate>,
+    weather: Res<Weather>,
+    mut colonies: Query<&mut Colony>,
+) {
+    let delta_seconds = time.delta_seconds();
+    for mut colony in colonies.iter_mut() {
+        let capacity = colony.garden_capacity();
+        colony.leaves = colony.leaves.min(capacity);
+        if colony.leaves <= 0.0 {
+            continue;
+        }
+
+        let digested: f32 = colony
+            .chambers_for(ChamberPurpose::FungusGarden)
+            .map(|garden| {
+                let share = colony.leaves * garden.capacity() / capacity;
+                let rate = FUNGUS_GROWTH_RATE
+                    * garden.tiles as f32
+                    * fungus_growth(&climate, &weather, garden.center);
+                share.min(rate * delta_seconds)
+            })
+            .sum();
+        let grown = colony.store_food(digested * FUNGUS_YIELD);
+        colony.leaves -= grown / FUNGUS_YIELD;
+    }
+}
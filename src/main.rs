@@ -1,7 +1,3 @@
-mod ant;
-mod colony;
-mod terrain;
-
 fn main() {
-    ant::run_app();
+    ant::ant::run_app();
 }
@@ -0,0 +1,469 @@
+use crate::ant::pathfinding::{find_path, smooth_path};
+use crate::colony::{ColonyMember, MIDDEN_OFFSET};
+use crate::food::{spawn_food_source, FoodKind};
+use crate::terrain::{
+    ground_level, setup_terrain, update_tile, AirTile, NestSites, TileStore, TileUpdateEvent,
+    TILE_SIZE, WORLD_HALF_SIZE,
+};
+use bevy::prelude::*;
+use rand::Rng;
+
+// How many of each predator the world keeps stocked
+const SPIDER_COUNT: usize = 1;
+const BEETLE_COUNT: usize = 1;
+const ANTLION_COUNT: usize = 2;
+// Seconds between topping up predators that have been killed
+const RESPAWN_INTERVAL: f32 = 120.0;
+// Predators turn up at least this many tiles from any nest entrance or midden
+const NEST_CLEARANCE: i32 = 10;
+// Seconds between a predator looking around, re-planning its path or digging
+const THINK_INTERVAL: f32 = 1.0;
+// A waypoint counts as reached this close to it
+const WAYPOINT_DISTANCE: f32 = 2.0;
+// Wandering predators stay within this many tiles of where they turned up
+const WANDER_RANGE: i32 = 25;
+// Predators bite anything this close to them
+const BITE_REACH: f32 = 1.5 * TILE_SIZE;
+// Seconds a predator that has eaten its fill rests before hunting again
+const REST_TIME: f32 = 60.0;
+// An antlion's pit is this many tiles deep, and drags in ants this close to
+// the bottom at this speed
+const PIT_DEPTH: i32 = 2;
+const PIT_RADIUS: f32 = 3.0 * TILE_SIZE;
+const PIT_PULL_SPEED: f32 = 25.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PredatorKind {
+    // Fast, and hunts ants anywhere it can walk
+    Spider,
+    // Slow and tough, and bites whatever ant wanders past
+    Beetle,
+    // Digs a pit in the surface and waits at the bottom for ants to slide in
+    Antlion,
+}
+
+// Everything that sets one kind of predator apart
+pub struct PredatorStats {
+    pub speed: f32,
+    pub size: f32,
+    pub color: Color,
+    pub max_health: f32,
+    pub attack: f32,
+    // Seconds between bites
+    pub bite_interval: f32,
+    // How far away it notices ants
+    pub sense_radius: f32,
+    // Damage it deals before it has eaten its fill and rests
+    pub appetite: f32,
+}
+
+impl PredatorKind {
+    pub fn stats(&self) -> PredatorStats {
+        match self {
+            PredatorKind::Spider => PredatorStats {
+                speed: 45.0,
+                size: 10.0,
+                color: Color::rgb(0.15, 0.12, 0.12),
+                max_health: 30.0,
+                attack: 3.0,
+                bite_interval: 2.0,
+                sense_radius: 12.0 * TILE_SIZE,
+                appetite: 9.0,
+            },
+            PredatorKind::Beetle => PredatorStats {
+                speed: 20.0,
+                size: 12.0,
+                color: Color::rgb(0.1, 0.25, 0.15),
+                max_health: 60.0,
+                attack: 4.0,
+                bite_interval: 3.0,
+                sense_radius: 3.0 * TILE_SIZE,
+                appetite: 12.0,
+            },
+            PredatorKind::Antlion => PredatorStats {
+                speed: 15.0,
+                size: 8.0,
+                color: Color::rgb(0.55, 0.45, 0.3),
+                max_health: 20.0,
+                attack: 4.0,
+                bite_interval: 1.0,
+                sense_radius: PIT_RADIUS,
+                appetite: 8.0,
+            },
+        }
+    }
+
+    fn count(&self) -> usize {
+        match self {
+            PredatorKind::Spider => SPIDER_COUNT,
+            PredatorKind::Beetle => BEETLE_COUNT,
+            PredatorKind::Antlion => ANTLION_COUNT,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub enum PredatorState {
+    Wandering,
+    Hunting(Entity),
+    Resting { remaining: f32 },
+    // Antlions dig their pit first, then lie in wait at the bottom
+    DiggingPit,
+    Lurking,
+}
+
+#[derive(Component)]
+pub struct Predator {
+    pub kind: PredatorKind,
+    pub health: f32,
+    pub state: PredatorState,
+    // Where it turned up; wanderers stay near it and antlions dig their pit here
+    home: Vec2,
+    // Damage dealt since it last rested
+    fed: f32,
+    bite_cooldown: f32,
+    think_timer: Timer,
+    path: Vec<Vec2>,
+    path_index: usize,
+}
+
+// Sent for every bite a predator lands on an ant; the ants' side of combat
+// deals the damage
+#[derive(Event)]
+pub struct PredatorBite {
+    pub predator: Entity,
+    pub kind: PredatorKind,
+    pub victim: Entity,
+    pub damage: f32,
+}
+
+#[derive(Resource)]
+pub struct PredatorSpawner {
+    timer: Timer,
+}
+
+impl Default for PredatorSpawner {
+    fn default() -> Self {
+        Self {
+            timer: Timer::from_seconds(RESPAWN_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+fn spawn_predator(commands: &mut Commands, kind: PredatorKind, position: Vec2) {
+    let stats = kind.stats();
+    commands.spawn((
+        Predator {
+            kind,
+            health: stats.max_health,
+            state: if kind == PredatorKind::Antlion {
+                PredatorState::DiggingPit
+            } else {
+                PredatorState::Wandering
+            },
+            home: position,
+            fed: 0.0,
+            bite_cooldown: 0.0,
+            think_timer: Timer::from_seconds(THINK_INTERVAL, TimerMode::Repeating),
+            path: Vec::new(),
+            path_index: 0,
+        },
+        SpriteBundle {
+            sprite: Sprite {
+                color: stats.color,
+                custom_size: Some(Vec2::splat(stats.size)),
+                ..default()
+            },
+            transform: Transform::from_xyz(position.x, position.y, 9.0),
+            ..default()
+        },
+    ));
+}
+
+// Bring every kind of predator back up to its count, each turning up on the
+// surface well away from the nests
+fn top_up_predators(
+    commands: &mut Commands,
+    tile_store: &TileStore,
+    nest_sites: &NestSites,
+    predators: &[PredatorKind],
+) {
+    let mut rng = rand::thread_rng();
+    let midden_offset = (MIDDEN_OFFSET / TILE_SIZE).round() as i32;
+    let nest_columns: Vec<i32> = nest_sites
+        .sites
+        .iter()
+        .map(|site| (site.x / TILE_SIZE).round() as i32)
+        .collect();
+
+    for kind in [
+        PredatorKind::Spider,
+        PredatorKind::Beetle,
+        PredatorKind::Antlion,
+    ] {
+        let existing = predators.iter().filter(|&&other| other == kind).count();
+        for _ in existing..kind.count() {
+            // Bounded so a crowded surface can't keep us looking forever
+            let column = (0..20)
+                .map(|_| rng.gen_range(-WORLD_HALF_SIZE + 1..WORLD_HALF_SIZE))
+                .find(|&x| {
+                    nest_columns.iter().all(|&nest| {
+                        (x - nest).abs() >= NEST_CLEARANCE
+                            && (x - nest - midden_offset).abs() >= NEST_CLEARANCE
+                    })
+                });
+            let Some(column) = column else {
+                continue;
+            };
+            let position = ground_level(tile_store, column);
+            spawn_predator(commands, kind, position);
+            println!("A {:?} turned up at {:?}", kind, position);
+        }
+    }
+}
+
+pub fn spawn_predators(
+    mut commands: Commands,
+    tile_store: Res<TileStore>,
+    nest_sites: Res<NestSites>,
+) {
+    top_up_predators(&mut commands, &tile_store, &nest_sites, &[]);
+}
+
+// Now and then new predators wander in to replace those the ants have killed
+pub fn respawn_predators(
+    mut commands: Commands,
+    time: Res<Time>,
+    mut spawner: ResMut<PredatorSpawner>,
+    tile_store: Res<TileStore>,
+    nest_sites: Res<NestSites>,
+    predators: Query<&Predator>,
+) {
+    if !spawner.timer.tick(time.delta()).just_finished() {
+        return;
+    }
+    let existing: Vec<PredatorKind> = predators.iter().map(|predator| predator.kind).collect();
+    top_up_predators(&mut commands, &tile_store, &nest_sites, &existing);
+}
+
+// The tiles an antlion digs out for its pit: a shaft under it, with the
+// ground either side of the top cut away into a funnel
+fn pit_tiles(home: Vec2) -> Vec<Vec2> {
+    let mut tiles: Vec<Vec2> = (1..=PIT_DEPTH)
+        .map(|depth| home - Vec2::new(0.0, depth as f32 * TILE_SIZE))
+        .collect();
+    tiles.push(home + Vec2::new(-TILE_SIZE, -TILE_SIZE));
+    tiles.push(home + Vec2::new(TILE_SIZE, -TILE_SIZE));
+    tiles
+}
+
+// Ants are whatever belongs to a colony
+type IsAnt = (With<ColonyMember>, Without<Predator>);
+
+fn plan_path(predator: &mut Predator, from: Vec2, to: Vec2, tile_store: &TileStore) {
+    let solid_tiles = tile_store.get_solid_tiles();
+    predator.path = find_path(from, to, &solid_tiles)
+        .map(|path| smooth_path(&path, |pos| tile_store.is_solid(&pos.to_vec2())))
+        .unwrap_or_default();
+    predator.path_index = 0;
+}
+
+// Predators wander near where they turned up until they notice an ant, then
+// hunt it down through open tiles and bite it until they've eaten their fill
+// and need a rest. Antlions dig a pit instead and wait at the bottom.
+pub fn hunt_ants(
+    time: Res<Time>,
+    mut tile_store: ResMut<TileStore>,
+    mut tile_update_events: EventWriter<TileUpdateEvent>,
+    mut bites: EventWriter<PredatorBite>,
+    mut predators: Query<(Entity, &mut Transform, &mut Predator)>,
+    ants: Query<(Entity, &Transform), IsAnt>,
+) {
+    let delta_seconds = time.delta_seconds();
+    let mut rng = rand::thread_rng();
+    let prey: Vec<(Entity, Vec2)> = ants
+        .iter()
+        .map(|(entity, transform)| (entity, transform.translation.truncate()))
+        .collect();
+
+    for (entity, mut transform, mut predator) in predators.iter_mut() {
+        let stats = predator.kind.stats();
+        let position = transform.translation.truncate();
+        let thinking = predator.think_timer.tick(time.delta()).just_finished();
+        predator.bite_cooldown = (predator.bite_cooldown - delta_seconds).max(0.0);
+
+        let nearest = prey
+            .iter()
+            .map(|&(ant, ant_position)| (ant, ant_position, position.distance(ant_position)))
+            .filter(|&(_, _, distance)| distance <= stats.sense_radius)
+            .min_by(|a, b| a.2.total_cmp(&b.2));
+
+        match predator.state {
+            PredatorState::Resting { remaining } => {
+                let remaining = remaining - delta_seconds;
+                predator.state = if remaining > 0.0 {
+                    PredatorState::Resting { remaining }
+                } else if predator.kind == PredatorKind::Antlion {
+                    PredatorState::Lurking
+                } else {
+                    PredatorState::Wandering
+                };
+                continue;
+            }
+            PredatorState::DiggingPit => {
+                if !thinking {
+                    continue;
+                }
+                let next = pit_tiles(predator.home)
+                    .into_iter()
+                    .find(|tile| tile_store.is_solid(tile));
+                match next {
+                    Some(tile) => {
+                        update_tile(
+                            &mut tile_store,
+                            &mut tile_update_events,
+                            tile,
+                            Box::new(AirTile),
+                        );
+                    }
+                    None => {
+                        let bottom = predator.home - Vec2::new(0.0, PIT_DEPTH as f32 * TILE_SIZE);
+                        transform.translation = bottom.extend(transform.translation.z);
+                        println!("Antlion {:?} has dug its pit at {:?}", entity, bottom);
+                        predator.state = PredatorState::Lurking;
+                    }
+                }
+                continue;
+            }
+            PredatorState::Lurking => {}
+            PredatorState::Wandering | PredatorState::Hunting(_) => match nearest {
+                Some((ant, ant_position, _)) => {
+                    if predator.state != PredatorState::Hunting(ant) {
+                        println!("{:?} {:?} is hunting ant {:?}", predator.kind, entity, ant);
+                        predator.state = PredatorState::Hunting(ant);
+                        plan_path(&mut predator, position, ant_position, &tile_store);
+                    } else if thinking {
+                        plan_path(&mut predator, position, ant_position, &tile_store);
+                    }
+                }
+                None => {
+                    predator.state = PredatorState::Wandering;
+                    if thinking && predator.path_index >= predator.path.len() {
+                        let home_column = (predator.home.x / TILE_SIZE).round() as i32;
+                        let column = (home_column + rng.gen_range(-WANDER_RANGE..=WANDER_RANGE))
+                            .clamp(-WORLD_HALF_SIZE + 1, WORLD_HALF_SIZE - 1);
+                        let destination = ground_level(&tile_store, column);
+                        plan_path(&mut predator, position, destination, &tile_store);
+                    }
+                }
+            },
+        }
+
+        if let Some((ant, ant_position, distance)) = nearest {
+            if distance <= BITE_REACH && predator.bite_cooldown <= 0.0 {
+                predator.bite_cooldown = stats.bite_interval;
+                predator.fed += stats.attack;
+                bites.send(PredatorBite {
+                    predator: entity,
+                    kind: predator.kind,
+                    victim: ant,
+                    damage: stats.attack,
+                });
+                if predator.fed >= stats.appetite {
+                    println!("{:?} {:?} has eaten its fill", predator.kind, entity);
+                    predator.fed = 0.0;
+                    predator.state = PredatorState::Resting {
+                        remaining: REST_TIME,
+                    };
+                    predator.path.clear();
+                }
+                continue;
+            }
+            // Close enough to lunge the rest of the way
+            if distance <= BITE_REACH * 2.0 && predator.kind != PredatorKind::Antlion {
+                let step = (ant_position - position).normalize_or_zero() * stats.speed;
+                transform.translation += (step * delta_seconds).extend(0.0);
+                continue;
+            }
+        }
+
+        // Follow the planned path
+        let Some(&waypoint) = predator.path.get(predator.path_index) else {
+            continue;
+        };
+        let to_waypoint = waypoint - position;
+        if to_waypoint.length() <= WAYPOINT_DISTANCE {
+            predator.path_index += 1;
+            continue;
+        }
+        let step =
+            to_waypoint.normalize() * (stats.speed * delta_seconds).min(to_waypoint.length());
+        transform.translation += step.extend(0.0);
+    }
+}
+
+// Ants that stray near the bottom of an antlion's pit slide down into it
+pub fn pull_into_pits(
+    time: Res<Time>,
+    predators: Query<(&Transform, &Predator)>,
+    mut ants: Query<&mut Transform, IsAnt>,
+) {
+    let pits: Vec<Vec2> = predators
+        .iter()
+        .filter(|(_, predator)| predator.state == PredatorState::Lurking)
+        .map(|(transform, _)| transform.translation.truncate())
+        .collect();
+    if pits.is_empty() {
+        return;
+    }
+
+    for mut transform in ants.iter_mut() {
+        let position = transform.translation.truncate();
+        for &pit in &pits {
+            let to_pit = pit - position;
+            if to_pit.length() > PIT_RADIUS || to_pit.length() <= WAYPOINT_DISTANCE {
+                continue;
+            }
+            let slide = to_pit.normalize() * PIT_PULL_SPEED * time.delta_seconds();
+            transform.translation += slide.extend(0.0);
+        }
+    }
+}
+
+// Predators the ants have killed are gone, leaving a carcass to be eaten
+pub fn remove_dead_predators(
+    mut commands: Commands,
+    predators: Query<(Entity, &Transform, &Predator)>,
+) {
+    for (entity, transform, predator) in predators.iter() {
+        if predator.health > 0.0 {
+            continue;
+        }
+        let position = transform.translation.truncate();
+        println!(
+            "{:?} {:?} was killed by ants at {:?}",
+            predator.kind, entity, position
+        );
+        commands.entity(entity).despawn_recursive();
+        spawn_food_source(&mut commands, FoodKind::Carcass, position);
+    }
+}
+
+pub struct PredatorPlugin;
+
+impl Plugin for PredatorPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<PredatorSpawner>()
+            .add_event::<PredatorBite>()
+            .add_systems(Startup, spawn_predators.after(setup_terrain))
+            .add_systems(
+                Update,
+                (
+                    hunt_ants,
+                    pull_into_pits,
+                    remove_dead_predators,
+                    respawn_predators,
+                ),
+            );
+    }
+}
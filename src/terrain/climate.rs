@@ -0,0 +1,46 @@
+use super::{SURFACE_Y, TILE_SIZE, WORLD_HALF_SIZE};
+use bevy::prelude::*;
+
+// Depth below the surface, in world units, at which the soil reaches its deep
+// temperature and moisture
+const DEEP_SOIL_DEPTH: f32 = 2.0 * WORLD_HALF_SIZE as f32 * TILE_SIZE;
+
+// Conditions underground. Near the surface the soil follows the weather and
+// is dry; further down it is steady, cool and damp.
+#[derive(Resource, Debug, Clone)]
+pub struct Climate {
+    // Degrees Celsius
+    pub surface_temperature: f32,
+    pub deep_temperature: f32,
+    // Fraction of saturation, from 0.0 (dry) to 1.0 (soaked)
+    pub surface_moisture: f32,
+    pub deep_moisture: f32,
+}
+
+impl Default for Climate {
+    fn default() -> Self {
+        Self {
+            surface_temperature: 28.0,
+            deep_temperature: 18.0,
+            surface_moisture: 0.2,
+            deep_moisture: 0.8,
+        }
+    }
+}
+
+impl Climate {
+    // How far `position` is from the surface towards deep soil, from 0.0 to 1.0
+    fn depth_fraction(position: Vec2) -> f32 {
+        ((SURFACE_Y - position.y) / DEEP_SOIL_DEPTH).clamp(0.0, 1.0)
+    }
+
+    pub fn temperature_at(&self, position: Vec2) -> f32 {
+        let depth = Self::depth_fraction(position);
+        self.surface_temperature + (self.deep_temperature - self.surface_temperature) * depth
+    }
+
+    pub fn moisture_at(&self, position: Vec2) -> f32 {
+        let depth = Self::depth_fraction(position);
+        self.surface_moisture + (self.deep_moisture - self.surface_moisture) * depth
+    }
+}
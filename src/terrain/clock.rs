@@ -0,0 +1,273 @@
+use super::climate::Climate;
+use super::surface::{Plant, Sky};
+use super::weather::Weather;
+use super::SURFACE_Y;
+use bevy::prelude::*;
+use bevy_rapier2d::prelude::*;
+use std::f32::consts::TAU;
+
+// Seconds of simulated time in a day, and days in each season
+pub const DAY_LENGTH: f32 = 240.0;
+pub const DAYS_PER_SEASON: u32 = 3;
+// The clock starts mid-morning on the first day of spring
+const START_TIME_OF_DAY: f32 = 0.35;
+// Below this much daylight it counts as night
+const NIGHT_DAYLIGHT: f32 = 0.25;
+const NIGHT_SKY_COLOR: Color = Color::rgb(0.03, 0.04, 0.12);
+// Rain clouds leave this much of the daylight
+const OVERCAST: f32 = 0.6;
+
+// Ants slow down as they cool below this temperature, down to this fraction
+// of their speed once they're as cold as the torpor temperature
+const ACTIVE_TEMPERATURE: f32 = 20.0;
+const TORPOR_TEMPERATURE: f32 = 5.0;
+const MIN_ACTIVITY: f32 = 0.15;
+// Ants out on the surface at night feel their way along this much slower
+const NIGHT_ACTIVITY: f32 = 0.5;
+
+// Time-lapse speeds run from this slow to this fast, stepping by doubling
+const MIN_TIME_SPEED: f32 = 0.25;
+const MAX_TIME_SPEED: f32 = 8.0;
+// Physics steps are never longer than this, however fast time runs
+const MAX_PHYSICS_STEP: f32 = 1.0 / 60.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Season {
+    Spring,
+    Summer,
+    Autumn,
+    Winter,
+}
+
+impl Season {
+    pub const ALL: [Season; 4] = [
+        Season::Spring,
+        Season::Summer,
+        Season::Autumn,
+        Season::Winter,
+    ];
+
+    // Surface temperature at midday and at midnight, and the deep soil's
+    // temperature, in degrees Celsius
+    fn temperatures(&self) -> (f32, f32, f32) {
+        match self {
+            Season::Spring => (22.0, 10.0, 16.0),
+            Season::Summer => (32.0, 20.0, 20.0),
+            Season::Autumn => (20.0, 8.0, 16.0),
+            Season::Winter => (6.0, -4.0, 10.0),
+        }
+    }
+
+    fn sky_color(&self) -> Color {
+        match self {
+            Season::Spring => Color::rgb(0.55, 0.75, 0.95),
+            Season::Summer => Color::rgb(0.45, 0.7, 1.0),
+            Season::Autumn => Color::rgb(0.65, 0.7, 0.8),
+            Season::Winter => Color::rgb(0.75, 0.8, 0.85),
+        }
+    }
+
+    // How much of each of red, green and blue the season leaves in leaves
+    fn foliage(&self) -> [f32; 3] {
+        match self {
+            Season::Spring => [1.0, 1.0, 1.0],
+            Season::Summer => [1.2, 1.0, 0.8],
+            Season::Autumn => [2.2, 1.0, 0.5],
+            Season::Winter => [1.6, 0.9, 1.2],
+        }
+    }
+}
+
+// How long the simulation has been running, in simulated seconds. Days and
+// seasons follow from it.
+#[derive(Resource, Debug, Clone)]
+pub struct SimClock {
+    pub elapsed: f32,
+}
+
+impl Default for SimClock {
+    fn default() -> Self {
+        Self {
+            elapsed: START_TIME_OF_DAY * DAY_LENGTH,
+        }
+    }
+}
+
+impl SimClock {
+    pub fn day(&self) -> u32 {
+        (self.elapsed / DAY_LENGTH) as u32
+    }
+
+    // Fraction of the day gone, from 0.0 at midnight through 0.5 at noon
+    pub fn time_of_day(&self) -> f32 {
+        (self.elapsed / DAY_LENGTH).fract()
+    }
+
+    pub fn season(&self) -> Season {
+        Season::ALL[(self.day() / DAYS_PER_SEASON) as usize % Season::ALL.len()]
+    }
+
+    // From 0.0 at midnight to 1.0 at noon
+    pub fn daylight(&self) -> f32 {
+        (1.0 - (self.time_of_day() * TAU).cos()) / 2.0
+    }
+
+    pub fn is_night(&self) -> bool {
+        self.daylight() < NIGHT_DAYLIGHT
+    }
+
+    // How lively an ant at `position` is, as a fraction of its full speed.
+    // Ants are cold-blooded, so they slow down in the cold and all but stop
+    // in winter, and they go slowly on the surface in the dark.
+    pub fn activity(&self, climate: &Climate, position: Vec2) -> f32 {
+        let warmth = (climate.temperature_at(position) - TORPOR_TEMPERATURE)
+            / (ACTIVE_TEMPERATURE - TORPOR_TEMPERATURE);
+        let light = if self.is_night() && position.y > SURFACE_Y {
+            NIGHT_ACTIVITY
+        } else {
+            1.0
+        };
+        warmth.clamp(MIN_ACTIVITY, 1.0) * light
+    }
+}
+
+// Move the clock on and let the surface warm up by day and cool off at night,
+// around the season's temperatures. Deep soil only follows the seasons.
+pub fn advance_clock(time: Res<Time>, mut clock: ResMut<SimClock>, mut climate: ResMut<Climate>) {
+    let (day, season) = (clock.day(), clock.season());
+    clock.elapsed += time.delta_seconds();
+    if clock.season() != season {
+        println!("{:?} has come", clock.season());
+    } else if clock.day() != day {
+        println!("Day {} of {:?}", clock.day() + 1, clock.season());
+    }
+
+    let (noon, midnight, deep) = clock.season().temperatures();
+    climate.surface_temperature = midnight + (noon - midnight) * clock.daylight();
+    climate.deep_temperature = deep;
+}
+
+// Darken the sky at night and under rain clouds, and change its color and the
+// plants' with the season
+pub fn tint_world(
+    clock: Res<SimClock>,
+    weather: Res<Weather>,
+    mut sky: Query<&mut Sprite, With<Sky>>,
+    mut plants: Query<(&Plant, &mut Sprite), Without<Sky>>,
+) {
+    let season = clock.season();
+    let daylight = clock.daylight() * if weather.is_raining() { OVERCAST } else { 1.0 };
+    let day_sky = season.sky_color().as_rgba_f32();
+    let night_sky = NIGHT_SKY_COLOR.as_rgba_f32();
+    let mix =
+        |channel: usize| night_sky[channel] + (day_sky[channel] - night_sky[channel]) * daylight;
+    for mut sprite in sky.iter_mut() {
+        sprite.color = Color::rgb(mix(0), mix(1), mix(2));
+    }
+
+    let foliage = season.foliage();
+    for (plant, mut sprite) in plants.iter_mut() {
+        let [red, green, blue, _] = plant.kind.color().as_rgba_f32();
+        let shade = 0.4 + 0.6 * daylight;
+        sprite.color = Color::rgb(
+            (red * foliage[0] * shade).min(1.0),
+            (green * foliage[1] * shade).min(1.0),
+            (blue * foliage[2] * shade).min(1.0),
+        );
+    }
+}
+
+// Speed time up or slow it down for time-lapse runs, or pause it. Physics
+// takes more, shorter steps when time runs fast so ants don't skip through
+// each other.
+pub fn handle_time_keys(
+    keyboard: Res<Input<KeyCode>>,
+    mut time: ResMut<Time<Virtual>>,
+    mut rapier_config: ResMut<RapierConfiguration>,
+) {
+    if keyboard.just_pressed(KeyCode::P) {
+        if time.is_paused() {
+            time.unpause();
+            println!("Time resumed");
+        } else {
+            time.pause();
+            println!("Time paused");
+        }
+    }
+
+    let speed = time.relative_speed();
+    let new_speed = if keyboard.just_pressed(KeyCode::BracketRight) {
+        (speed * 2.0).min(MAX_TIME_SPEED)
+    } else if keyboard.just_pressed(KeyCode::BracketLeft) {
+        (speed / 2.0).max(MIN_TIME_SPEED)
+    } else {
+        return;
+    };
+    if new_speed == speed {
+        return;
+    }
+
+    time.set_relative_speed(new_speed);
+    rapier_config.timestep_mode = TimestepMode::Variable {
+        max_dt: MAX_PHYSICS_STEP * new_speed.max(1.0),
+        time_scale: 1.0,
+        substeps: new_speed.max(1.0).ceil() as usize,
+    };
+    println!("Time runs at {}x", new_speed);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at_day(day: f32) -> SimClock {
+        SimClock {
+            elapsed: day * DAY_LENGTH,
+        }
+    }
+
+    #[test]
+    fn seasons_turn_over_every_few_days_and_come_round_again() {
+        let last_of_spring = (DAYS_PER_SEASON - 1) as f32 + 0.99;
+        assert_eq!(at_day(last_of_spring).season(), Season::Spring);
+        assert_eq!(at_day(DAYS_PER_SEASON as f32).season(), Season::Summer);
+        assert_eq!(
+            at_day((DAYS_PER_SEASON * 3) as f32).season(),
+            Season::Winter
+        );
+        assert_eq!(
+            at_day((DAYS_PER_SEASON * 4) as f32).season(),
+            Season::Spring
+        );
+    }
+
+    #[test]
+    fn it_is_brightest_at_noon_and_dark_at_midnight() {
+        let noon = at_day(1.5);
+        assert!((noon.daylight() - 1.0).abs() < 1e-4);
+        assert!(!noon.is_night());
+
+        let midnight = at_day(2.0);
+        assert!(midnight.daylight() < 1e-4);
+        assert!(midnight.is_night());
+    }
+
+    #[test]
+    fn cold_ants_slow_down_but_never_stop() {
+        let noon = at_day(0.5);
+        let underground = Vec2::new(0.0, SURFACE_Y - 100.0);
+        let frozen = Climate {
+            surface_temperature: -10.0,
+            deep_temperature: -10.0,
+            ..default()
+        };
+        assert_eq!(noon.activity(&frozen, underground), MIN_ACTIVITY);
+
+        let warm = Climate {
+            surface_temperature: ACTIVE_TEMPERATURE + 5.0,
+            deep_temperature: ACTIVE_TEMPERATURE + 5.0,
+            ..default()
+        };
+        assert_eq!(noon.activity(&warm, underground), 1.0);
+    }
+}
@@ -0,0 +1,253 @@
+use super::TILE_SIZE;
+use bevy::prelude::*;
+use std::collections::{HashMap, HashSet};
+
+// Designated tiles are tinted this strongly over the terrain
+const OVERLAY_ALPHA: f32 = 0.35;
+// Drawn above the terrain but below food, the colony and ants
+const OVERLAY_Z: f32 = 1.0;
+
+// What a chamber is dug out for
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum ChamberPurpose {
+    Nursery,
+    FoodStorage,
+    Royal,
+    Midden,
+    FungusGarden,
+}
+
+impl ChamberPurpose {
+    pub const ALL: [ChamberPurpose; 5] = [
+        ChamberPurpose::Nursery,
+        ChamberPurpose::FoodStorage,
+        ChamberPurpose::Royal,
+        ChamberPurpose::Midden,
+        ChamberPurpose::FungusGarden,
+    ];
+
+    pub fn color(&self) -> Color {
+        match self {
+            ChamberPurpose::Nursery => Color::rgb(0.95, 0.9, 0.6),
+            ChamberPurpose::FoodStorage => Color::rgb(0.3, 0.8, 0.3),
+            ChamberPurpose::Royal => Color::rgb(0.7, 0.3, 0.9),
+            ChamberPurpose::Midden => Color::rgb(0.5, 0.4, 0.3),
+            ChamberPurpose::FungusGarden => Color::rgb(0.9, 0.9, 0.9),
+        }
+    }
+
+    // How much each open tile of the chamber holds: food in a store, brood in
+    // a nursery or the royal chamber, bodies in a midden and plant matter in
+    // a fungus garden
+    pub fn capacity_per_tile(&self) -> f32 {
+        match self {
+            ChamberPurpose::Nursery => 1.0,
+            ChamberPurpose::FoodStorage => 4.0,
+            ChamberPurpose::Royal => 1.0,
+            ChamberPurpose::Midden => 1.0,
+            ChamberPurpose::FungusGarden => 2.0,
+        }
+    }
+}
+
+// What the player has marked a tile for
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Designation {
+    // Dig the tile out
+    Dig,
+    // Never dig the tile
+    NoDig,
+    // Dig the tile out as part of a chamber
+    Chamber(ChamberPurpose),
+}
+
+impl Designation {
+    pub fn color(&self) -> Color {
+        match self {
+            Designation::Dig => Color::rgb(1.0, 0.8, 0.1),
+            Designation::NoDig => Color::rgb(0.9, 0.15, 0.1),
+            Designation::Chamber(purpose) => purpose.color(),
+        }
+    }
+
+    pub fn wants_digging(&self) -> bool {
+        !matches!(self, Designation::NoDig)
+    }
+}
+
+// Tiles the player has painted designations on, keyed by the same tile grid
+// as TileStore
+#[derive(Resource, Default)]
+pub struct Designations {
+    tiles: HashMap<IVec2, Designation>,
+}
+
+fn cell_of(position: Vec2) -> IVec2 {
+    (position / TILE_SIZE).round().as_ivec2()
+}
+
+impl Designations {
+    // Paint every tile between two corners, or clear them when `designation`
+    // is None
+    pub fn paint(&mut self, from: Vec2, to: Vec2, designation: Option<Designation>) {
+        let (from, to) = (cell_of(from), cell_of(to));
+        let (min, max) = (from.min(to), from.max(to));
+        for x in min.x..=max.x {
+            for y in min.y..=max.y {
+                let cell = IVec2::new(x, y);
+                match designation {
+                    Some(designation) => {
+                        self.tiles.insert(cell, designation);
+                    }
+                    None => {
+                        self.tiles.remove(&cell);
+                    }
+                }
+            }
+        }
+    }
+
+    pub fn get(&self, position: Vec2) -> Option<Designation> {
+        self.tiles.get(&cell_of(position)).copied()
+    }
+
+    // Every designated tile, as the position of its centre
+    pub fn iter(&self) -> impl Iterator<Item = (Vec2, Designation)> + '_ {
+        self.tiles
+            .iter()
+            .map(|(cell, designation)| (cell.as_vec2() * TILE_SIZE, *designation))
+    }
+
+    // Every chamber footprint: touching tiles painted with the same purpose,
+    // as the positions of their centres
+    pub fn chamber_footprints(&self) -> Vec<(ChamberPurpose, Vec<Vec2>)> {
+        let mut seen = HashSet::new();
+        let mut footprints = Vec::new();
+        for (&first, designation) in &self.tiles {
+            let Designation::Chamber(purpose) = *designation else {
+                continue;
+            };
+            if !seen.insert(first) {
+                continue;
+            }
+            let mut cells = vec![first];
+            let mut index = 0;
+            while index < cells.len() {
+                let cell = cells[index];
+                index += 1;
+                for offset in [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y] {
+                    let next = cell + offset;
+                    if self.tiles.get(&next) == Some(designation) && seen.insert(next) {
+                        cells.push(next);
+                    }
+                }
+            }
+            footprints.push((
+                purpose,
+                cells
+                    .into_iter()
+                    .map(|cell| cell.as_vec2() * TILE_SIZE)
+                    .collect(),
+            ));
+        }
+        footprints
+    }
+}
+
+#[derive(Component)]
+pub struct DesignationOverlay;
+
+// Tint designated tiles, redrawn whenever the designations change
+pub fn update_designation_overlay(
+    mut commands: Commands,
+    designations: Res<Designations>,
+    overlays: Query<Entity, With<DesignationOverlay>>,
+) {
+    if !designations.is_changed() {
+        return;
+    }
+
+    for entity in overlays.iter() {
+        commands.entity(entity).despawn();
+    }
+    for (position, designation) in designations.iter() {
+        commands.spawn((
+            DesignationOverlay,
+            SpriteBundle {
+                sprite: Sprite {
+                    color: designation.color().with_a(OVERLAY_ALPHA),
+                    custom_size: Some(Vec2::splat(TILE_SIZE)),
+                    ..default()
+                },
+                transform: Transform::from_xyz(position.x, position.y, OVERLAY_Z),
+                ..default()
+            },
+        ));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn at(x: i32, y: i32) -> Vec2 {
+        Vec2::new(x as f32, y as f32) * TILE_SIZE
+    }
+
+    #[test]
+    fn painting_fills_the_box_between_the_corners() {
+        let mut designations = Designations::default();
+        // Corners given in either order cover the same box
+        designations.paint(at(2, 3), at(0, 1), Some(Designation::Dig));
+
+        assert_eq!(designations.iter().count(), 9);
+        for x in 0..=2 {
+            for y in 1..=3 {
+                assert_eq!(designations.get(at(x, y)), Some(Designation::Dig));
+            }
+        }
+        assert_eq!(designations.get(at(3, 1)), None);
+        assert_eq!(designations.get(at(0, 0)), None);
+    }
+
+    #[test]
+    fn painting_none_erases_only_the_box() {
+        let mut designations = Designations::default();
+        designations.paint(at(0, 0), at(3, 0), Some(Designation::NoDig));
+        designations.paint(at(1, 0), at(2, 0), None);
+
+        assert_eq!(designations.get(at(0, 0)), Some(Designation::NoDig));
+        assert_eq!(designations.get(at(1, 0)), None);
+        assert_eq!(designations.get(at(2, 0)), None);
+        assert_eq!(designations.get(at(3, 0)), Some(Designation::NoDig));
+    }
+
+    #[test]
+    fn footprints_split_into_touching_tiles_of_one_purpose() {
+        let nursery = Some(Designation::Chamber(ChamberPurpose::Nursery));
+        let store = Some(Designation::Chamber(ChamberPurpose::FoodStorage));
+        let mut designations = Designations::default();
+        // Two nurseries kept apart by a gap, with a store touching the first
+        designations.paint(at(0, 0), at(1, 1), nursery);
+        designations.paint(at(4, 0), at(4, 0), nursery);
+        designations.paint(at(2, 0), at(2, 1), store);
+        // Plain digging is never part of a chamber
+        designations.paint(at(0, 2), at(1, 2), Some(Designation::Dig));
+
+        let mut footprints: Vec<(ChamberPurpose, usize)> = designations
+            .chamber_footprints()
+            .into_iter()
+            .map(|(purpose, tiles)| (purpose, tiles.len()))
+            .collect();
+        footprints.sort_by_key(|&(purpose, size)| (purpose as u8, size));
+
+        assert_eq!(
+            footprints,
+            vec![
+                (ChamberPurpose::Nursery, 1),
+                (ChamberPurpose::Nursery, 4),
+                (ChamberPurpose::FoodStorage, 2),
+            ]
+        );
+    }
+}
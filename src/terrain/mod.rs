@@ -1,9 +1,41 @@
+pub mod climate;
+pub mod clock;
+pub mod designation;
+pub mod pheromone;
+pub mod surface;
+pub mod weather;
+
+use crate::colony::{
+    CENTRAL_CAVITY_RADIUS, MIDDEN_OFFSET, ROYAL_CHAMBER_DEPTH, ROYAL_CHAMBER_RADIUS,
+};
 use bevy::prelude::*;
 use bevy::sprite::MaterialMesh2dBundle;
+use climate::Climate;
+use clock::{advance_clock, handle_time_keys, tint_world, SimClock};
+use designation::{update_designation_overlay, Designations};
+use pheromone::{update_pheromones, ColonyPheromones, PheromoneSettings};
+use rand::Rng;
 use std::collections::HashMap;
 use std::hash::{Hash, Hasher};
+use surface::{drop_litter, spawn_surface};
+use weather::{draw_water, flow_water, update_weather, CaveIn, Weather};
 
 pub const TILE_SIZE: f32 = 8.0;
+// The world spans this many tiles either side of the origin
+pub const WORLD_HALF_SIZE: i32 = 50;
+// Height of the topmost row of tiles, where the ground meets the open surface
+pub const SURFACE_Y: f32 = WORLD_HALF_SIZE as f32 * TILE_SIZE;
+// Width (in tiles) of the shaft leading from the nest up to the surface
+pub const ENTRANCE_WIDTH: i32 = 2;
+// Number of colonies the world is generated with
+const COLONY_COUNT: usize = 2;
+// Nests are placed at least this many tiles apart, and at least this many
+// tiles in from the sides of the world
+const NEST_SPACING: i32 = 30;
+const NEST_EDGE_MARGIN: i32 = 4;
+// Nests are dug out anywhere between this many tiles below the middle of the
+// world and the middle itself
+const MAX_NEST_DEPTH: i32 = 8;
 
 // Define a trait for different tile types
 pub trait TileType: Send + Sync {
@@ -35,6 +67,28 @@ impl TileType for DirtTile {
     }
 }
 
+// The top row of the world, where the ground meets the sky
+#[derive(Component, Clone, Copy)]
+pub struct GrassTile;
+
+impl TileType for GrassTile {
+    fn is_solid(&self) -> bool {
+        true
+    }
+
+    fn color(&self) -> Color {
+        Color::rgb(0.3, 0.6, 0.2)
+    }
+
+    fn name(&self) -> &str {
+        "Grass"
+    }
+
+    fn clone_box(&self) -> Box<dyn TileType> {
+        Box::new(*self)
+    }
+}
+
 #[derive(Component, Clone, Copy)]
 pub struct AirTile;
 
@@ -101,12 +155,14 @@ impl Clone for Tile {
 #[derive(Resource, Default)]
 pub struct TileStore {
     tiles: HashMap<TilePosition, Tile>,
+    entities: HashMap<TilePosition, Entity>,
 }
 
 impl TileStore {
     pub fn new() -> Self {
         Self {
             tiles: HashMap::new(),
+            entities: HashMap::new(),
         }
     }
 
@@ -143,7 +199,7 @@ impl TileStore {
         let tile_pos = TilePosition(*position);
         self.tiles
             .get(&tile_pos)
-            .map_or(false, |tile| tile.tile_type.is_solid())
+            .is_some_and(|tile| tile.tile_type.is_solid())
     }
 
     pub fn get_solid_tiles(&self) -> Vec<Vec2> {
@@ -154,6 +210,14 @@ impl TileStore {
             .collect()
     }
 
+    pub fn set_entity(&mut self, position: Vec2, entity: Entity) {
+        self.entities.insert(TilePosition(position), entity);
+    }
+
+    pub fn get_entity(&self, position: &Vec2) -> Option<Entity> {
+        self.entities.get(&TilePosition(*position)).copied()
+    }
+
     pub fn count(&self) -> usize {
         self.tiles.len()
     }
@@ -168,19 +232,57 @@ impl TileStore {
 
 #[derive(Event)]
 pub struct TileUpdateEvent {
-    pub entity: Entity,
+    pub position: Vec2,
     pub new_type: Box<dyn TileType>,
 }
 
+// Change a tile's type in the store and notify its entity so the visual follows
+pub fn update_tile(
+    tile_store: &mut TileStore,
+    tile_update_events: &mut EventWriter<TileUpdateEvent>,
+    position: Vec2,
+    tile_type: Box<dyn TileType>,
+) -> bool {
+    if tile_store.get_tile(&position).is_none() {
+        return false;
+    }
+    tile_update_events.send(TileUpdateEvent {
+        position,
+        new_type: tile_type.clone_box(),
+    });
+    tile_store.set_tile_type(&position, tile_type)
+}
+
+// Like update_tile, but adds the tile to the store (and spawns an entity for
+// it) when nothing exists at that position yet, e.g. outside the generated map
+pub fn place_tile(
+    tile_store: &mut TileStore,
+    tile_update_events: &mut EventWriter<TileUpdateEvent>,
+    position: Vec2,
+    tile_type: Box<dyn TileType>,
+) {
+    tile_update_events.send(TileUpdateEvent {
+        position,
+        new_type: tile_type.clone_box(),
+    });
+    if !tile_store.set_tile_type(&position, tile_type.clone_box()) {
+        tile_store.add_tile(position, tile_type);
+    }
+}
+
 #[derive(Resource, Default)]
 pub struct TerrainMaterials {
     dirt: Handle<ColorMaterial>,
+    grass: Handle<ColorMaterial>,
     air: Handle<ColorMaterial>,
+    quad_mesh: Handle<Mesh>,
 }
 
 impl TerrainMaterials {
     pub fn get_material(&self, tile_type: &dyn TileType) -> Handle<ColorMaterial> {
-        if tile_type.is_solid() {
+        if tile_type.name() == GrassTile.name() {
+            self.grass.clone()
+        } else if tile_type.is_solid() {
             self.dirt.clone()
         } else {
             self.air.clone()
@@ -188,65 +290,157 @@ impl TerrainMaterials {
     }
 }
 
+// Where world generation hollowed out a nest for each colony, as the middle
+// of its central cavity
+#[derive(Resource, Default)]
+pub struct NestSites {
+    pub sites: Vec<Vec2>,
+}
+
 pub struct TerrainPlugin;
 
 impl Plugin for TerrainPlugin {
     fn build(&self, app: &mut App) {
         app.init_resource::<TileStore>()
             .init_resource::<TerrainMaterials>()
+            .init_resource::<ColonyPheromones>()
+            .init_resource::<PheromoneSettings>()
+            .init_resource::<Climate>()
+            .init_resource::<SimClock>()
+            .init_resource::<Weather>()
+            .init_resource::<Designations>()
+            .init_resource::<NestSites>()
             .add_event::<TileUpdateEvent>()
+            .add_event::<CaveIn>()
             .add_systems(
                 Startup,
                 (
                     setup_terrain_materials,
                     setup_terrain.after(setup_terrain_materials),
                     spawn_tile_entities.after(setup_terrain),
+                    spawn_surface.after(setup_terrain),
                 )
                     .chain(),
             )
-            .add_systems(Update, handle_tile_updates);
+            .add_systems(
+                Update,
+                (
+                    handle_tile_updates,
+                    update_pheromones,
+                    update_designation_overlay,
+                    drop_litter,
+                    handle_time_keys,
+                    advance_clock,
+                    tint_world.after(advance_clock),
+                    (update_weather, flow_water, draw_water).chain(),
+                ),
+            );
+
+        #[cfg(feature = "debug")]
+        app.add_systems(Update, weather::handle_weather_keys);
     }
 }
 
 fn setup_terrain_materials(
     mut materials: ResMut<Assets<ColorMaterial>>,
+    mut meshes: ResMut<Assets<Mesh>>,
     mut terrain_materials: ResMut<TerrainMaterials>,
 ) {
     info!("Setting up terrain materials");
-    terrain_materials.dirt = materials.add(ColorMaterial::from(Color::rgb(0.8, 0.6, 0.4)));
+    terrain_materials.dirt = materials.add(ColorMaterial::from(DirtTile.color()));
+    terrain_materials.grass = materials.add(ColorMaterial::from(GrassTile.color()));
     terrain_materials.air = materials.add(ColorMaterial::from(Color::rgba(0.0, 0.0, 0.0, 0.0)));
+    terrain_materials.quad_mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
+        TILE_SIZE, TILE_SIZE,
+    ))));
     info!("Terrain materials initialized");
 }
 
-pub fn setup_terrain(mut tile_store: ResMut<TileStore>) {
+pub fn setup_terrain(mut tile_store: ResMut<TileStore>, mut nest_sites: ResMut<NestSites>) {
     info!("Starting terrain setup");
-    // First pass: Create the initial terrain
-    for y in -50..=50 {
-        for x in -50..=50 {
+    // First pass: Create the initial terrain, dirt topped with a row of grass
+    for y in -WORLD_HALF_SIZE..=WORLD_HALF_SIZE {
+        for x in -WORLD_HALF_SIZE..=WORLD_HALF_SIZE {
             let pos = Vec2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE);
-            let tile = Box::new(DirtTile);
+            let tile: Box<dyn TileType> = if y == WORLD_HALF_SIZE {
+                Box::new(GrassTile)
+            } else {
+                Box::new(DirtTile)
+            };
             tile_store.add_tile(pos, tile);
         }
     }
     info!("Initial terrain created with {} tiles", tile_store.count());
 
-    // Second pass: Create the cavity
-    create_central_cavity(&mut tile_store);
+    // Second pass: Hollow out a nest for every colony
+    nest_sites.sites = pick_nest_sites(&mut rand::thread_rng());
+    for &center in &nest_sites.sites {
+        create_central_cavity(&mut tile_store, center);
+        create_entrance_shaft(&mut tile_store, center);
+        create_royal_chamber(&mut tile_store, center);
+    }
     info!(
         "Terrain setup complete with {} solid tiles",
         tile_store.solid_count()
     );
 }
 
-pub fn create_central_cavity(tile_store: &mut ResMut<TileStore>) {
-    let center = Vec2::new(0.0, 0.0);
-    let radius = 40.0;
+// Spread the colonies' nests out across the world, each with room to the
+// right of its entrance for the midden. There's always a nest for every
+// colony.
+fn pick_nest_sites(rng: &mut impl Rng) -> Vec<Vec2> {
+    let midden_columns = (MIDDEN_OFFSET / TILE_SIZE).round() as i32;
+    let columns = -WORLD_HALF_SIZE + NEST_EDGE_MARGIN
+        ..WORLD_HALF_SIZE - NEST_EDGE_MARGIN - midden_columns - ENTRANCE_WIDTH;
+    let mut sites: Vec<IVec2> = Vec::new();
+
+    // Bounded so a crowded world can't keep us looking forever
+    for _ in 0..COLONY_COUNT * 50 {
+        if sites.len() >= COLONY_COUNT {
+            break;
+        }
+        let site = IVec2::new(
+            rng.gen_range(columns.clone()),
+            rng.gen_range(-MAX_NEST_DEPTH..=0),
+        );
+        if sites
+            .iter()
+            .any(|other| (other.x - site.x).abs() < NEST_SPACING)
+        {
+            continue;
+        }
+        sites.push(site);
+    }
+
+    // Out of luck, so share the world out evenly instead, a nest in the
+    // middle of each share
+    if sites.len() < COLONY_COUNT {
+        let width = columns.end - columns.start;
+        sites = (0..COLONY_COUNT as i32)
+            .map(|share| {
+                IVec2::new(
+                    columns.start + (2 * share + 1) * width / (2 * COLONY_COUNT as i32),
+                    rng.gen_range(-MAX_NEST_DEPTH..=0),
+                )
+            })
+            .collect();
+    }
+
+    info!("Placed {} nests", sites.len());
+    sites
+        .into_iter()
+        .map(|site| site.as_vec2() * TILE_SIZE)
+        .collect()
+}
+
+pub fn create_central_cavity(tile_store: &mut ResMut<TileStore>, center: Vec2) {
+    let radius = CENTRAL_CAVITY_RADIUS;
     let mut converted_count = 0;
 
     // Create a list of positions to convert to air
     let mut positions_to_convert = Vec::new();
-    for y in -50..=50 {
-        for x in -50..=50 {
+    for y in -WORLD_HALF_SIZE..=WORLD_HALF_SIZE {
+        for x in -WORLD_HALF_SIZE..=WORLD_HALF_SIZE {
             let pos = Vec2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE);
             let distance = pos.distance(center);
             if distance <= radius {
@@ -277,10 +471,75 @@ pub fn create_central_cavity(tile_store: &mut ResMut<TileStore>) {
     info!("Remaining solid tiles: {}", tile_store.solid_count());
 }
 
+// Open a vertical shaft from the cavity up through the top of the map, so the
+// colony has a way out to the surface
+pub fn create_entrance_shaft(tile_store: &mut ResMut<TileStore>, center: Vec2) {
+    let center = (center / TILE_SIZE).round().as_ivec2();
+    let mut converted_count = 0;
+    for y in center.y..=WORLD_HALF_SIZE {
+        for x in center.x..center.x + ENTRANCE_WIDTH {
+            let pos = Vec2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE);
+            if let Some(tile) = tile_store.get_tile_mut(&pos) {
+                if tile.tile_type.is_solid() {
+                    tile.tile_type = Box::new(AirTile);
+                    converted_count += 1;
+                }
+            }
+        }
+    }
+
+    info!(
+        "Created entrance shaft by converting {} tiles to air",
+        converted_count
+    );
+}
+
+// Hollow out the queen's chamber below the central cavity, with a passage
+// leading down to it as wide as the entrance shaft
+pub fn create_royal_chamber(tile_store: &mut ResMut<TileStore>, nest: Vec2) {
+    let center = nest - Vec2::new(0.0, ROYAL_CHAMBER_DEPTH);
+    let depth = (ROYAL_CHAMBER_DEPTH / TILE_SIZE).round() as i32;
+    let reach = (ROYAL_CHAMBER_RADIUS / TILE_SIZE).ceil() as i32;
+    let mut converted_count = 0;
+
+    for y in -depth - reach..=0 {
+        for x in -reach..=reach {
+            let pos = nest + Vec2::new(x as f32 * TILE_SIZE, y as f32 * TILE_SIZE);
+            let in_passage = (0..ENTRANCE_WIDTH).contains(&x) && y >= -depth;
+            if pos.distance(center) > ROYAL_CHAMBER_RADIUS && !in_passage {
+                continue;
+            }
+            if let Some(tile) = tile_store.get_tile_mut(&pos) {
+                if tile.tile_type.is_solid() {
+                    tile.tile_type = Box::new(AirTile);
+                    converted_count += 1;
+                }
+            }
+        }
+    }
+
+    info!(
+        "Created royal chamber by converting {} tiles to air",
+        converted_count
+    );
+}
+
+// The open cell resting on top of the ground in a column, on the surface or
+// on top of anything piled up there
+pub fn ground_level(tile_store: &TileStore, column: i32) -> Vec2 {
+    let mut y = WORLD_HALF_SIZE;
+    while tile_store.is_solid(&Vec2::new(
+        column as f32 * TILE_SIZE,
+        (y + 1) as f32 * TILE_SIZE,
+    )) {
+        y += 1;
+    }
+    Vec2::new(column as f32 * TILE_SIZE, (y + 1) as f32 * TILE_SIZE)
+}
+
 pub fn spawn_tile_entities(
     mut commands: Commands,
-    tile_store: Res<TileStore>,
-    mut meshes: ResMut<Assets<Mesh>>,
+    mut tile_store: ResMut<TileStore>,
     terrain_materials: Res<TerrainMaterials>,
 ) {
     info!("Starting to spawn tile entities");
@@ -290,37 +549,116 @@ pub fn spawn_tile_entities(
         terrain_materials.is_added()
     );
 
-    let quad_mesh = meshes.add(Mesh::from(shape::Quad::new(Vec2::new(
-        TILE_SIZE, TILE_SIZE,
-    ))));
-    info!("Created quad mesh for tiles");
-
-    let mut spawned_count = 0;
+    let mut spawned = Vec::with_capacity(tile_store.count());
     for (_, tile) in tile_store.tiles.iter() {
         let material = terrain_materials.get_material(tile.tile_type.as_ref());
-        commands.spawn((
-            MaterialMesh2dBundle {
-                mesh: quad_mesh.clone().into(),
-                material,
-                transform: Transform::from_xyz(tile.position.x, tile.position.y, 0.0),
-                ..default()
-            },
-            tile.clone(),
-        ));
-        spawned_count += 1;
+        let entity = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: terrain_materials.quad_mesh.clone().into(),
+                    material,
+                    transform: Transform::from_xyz(tile.position.x, tile.position.y, 0.0),
+                    ..default()
+                },
+                tile.clone(),
+            ))
+            .id();
+        spawned.push((tile.position, entity));
+    }
+
+    // Remember which entity renders each tile so updates can find it directly
+    let spawned_count = spawned.len();
+    for (position, entity) in spawned {
+        tile_store.set_entity(position, entity);
     }
     info!("Finished spawning {} tile entities", spawned_count);
 }
 
 fn handle_tile_updates(
+    mut commands: Commands,
     mut tile_update_events: EventReader<TileUpdateEvent>,
+    mut tile_store: ResMut<TileStore>,
     mut tiles: Query<(&mut Tile, &mut Handle<ColorMaterial>)>,
     terrain_materials: Res<TerrainMaterials>,
 ) {
     for event in tile_update_events.read() {
-        if let Ok((mut tile, mut material)) = tiles.get_mut(event.entity) {
-            tile.tile_type = event.new_type.clone_box();
-            *material = terrain_materials.get_material(tile.tile_type.as_ref());
+        let material = terrain_materials.get_material(event.new_type.as_ref());
+        if let Some(entity) = tile_store.get_entity(&event.position) {
+            if let Ok((mut tile, mut tile_material)) = tiles.get_mut(entity) {
+                tile.tile_type = event.new_type.clone_box();
+                *tile_material = material;
+            }
+            continue;
+        }
+
+        // Tiles placed outside the generated map don't have an entity yet
+        let entity = commands
+            .spawn((
+                MaterialMesh2dBundle {
+                    mesh: terrain_materials.quad_mesh.clone().into(),
+                    material,
+                    transform: Transform::from_xyz(event.position.x, event.position.y, 0.0),
+                    ..default()
+                },
+                Tile {
+                    position: event.position,
+                    tile_type: event.new_type.clone_box(),
+                },
+            ))
+            .id();
+        tile_store.set_entity(event.position, entity);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::rngs::StdRng;
+    use rand::SeedableRng;
+
+    // Always picks the same column, so only the fallback can place the rest
+    struct StuckRng;
+
+    impl rand::RngCore for StuckRng {
+        fn next_u32(&mut self) -> u32 {
+            0
+        }
+
+        fn next_u64(&mut self) -> u64 {
+            0
         }
+
+        fn fill_bytes(&mut self, dest: &mut [u8]) {
+            dest.fill(0);
+        }
+
+        fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+            dest.fill(0);
+            Ok(())
+        }
+    }
+
+    fn assert_spread_out(sites: &[Vec2]) {
+        assert_eq!(sites.len(), COLONY_COUNT);
+        for (i, a) in sites.iter().enumerate() {
+            for b in &sites[i + 1..] {
+                assert!((a.x - b.x).abs() >= (NEST_SPACING as f32 * TILE_SIZE));
+            }
+            let column = (a.x / TILE_SIZE).round() as i32;
+            assert!((-WORLD_HALF_SIZE + NEST_EDGE_MARGIN..WORLD_HALF_SIZE).contains(&column));
+        }
+    }
+
+    #[test]
+    fn every_colony_gets_a_nest_site() {
+        for seed in 0..500 {
+            let sites = pick_nest_sites(&mut StdRng::seed_from_u64(seed));
+            assert_spread_out(&sites);
+        }
+    }
+
+    #[test]
+    fn nest_sites_are_shared_out_evenly_when_none_can_be_found() {
+        assert_spread_out(&pick_nest_sites(&mut StuckRng));
     }
 }
@@ -0,0 +1,400 @@
+use super::surface::SKY_HEIGHT;
+use super::{TileStore, TILE_SIZE, WORLD_HALF_SIZE};
+use bevy::prelude::*;
+use std::collections::HashMap;
+
+// Pheromones are updated at 7.5 FPS rather than every frame
+pub const PHEROMONE_UPDATE_INTERVAL: f32 = 1.0 / 7.5;
+// Cells weaker than this are dropped from the grid
+pub const MIN_PHEROMONE_STRENGTH: f32 = 0.01;
+// No cell holds more than this much of a single pheromone
+pub const MAX_PHEROMONE_STRENGTH: f32 = 100.0;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum PheromoneChannel {
+    // Laid by foragers carrying food home, leads to food
+    Food,
+    // Laid by ants leaving the nest, leads home
+    Home,
+    // Released by ants under attack
+    Alarm,
+    // Calls nestmates to help with a task
+    Recruitment,
+}
+
+impl PheromoneChannel {
+    pub const COUNT: usize = 4;
+    pub const ALL: [PheromoneChannel; Self::COUNT] = [
+        PheromoneChannel::Food,
+        PheromoneChannel::Home,
+        PheromoneChannel::Alarm,
+        PheromoneChannel::Recruitment,
+    ];
+
+    fn index(self) -> usize {
+        self as usize
+    }
+}
+
+// How quickly a channel fades and spreads, both as fractions per second
+#[derive(Debug, Clone, Copy)]
+pub struct ChannelSettings {
+    pub evaporation: f32,
+    pub diffusion: f32,
+}
+
+#[derive(Resource, Debug, Clone)]
+pub struct PheromoneSettings {
+    pub channels: [ChannelSettings; PheromoneChannel::COUNT],
+}
+
+impl PheromoneSettings {
+    pub fn channel(&self, channel: PheromoneChannel) -> &ChannelSettings {
+        &self.channels[channel.index()]
+    }
+
+    pub fn channel_mut(&mut self, channel: PheromoneChannel) -> &mut ChannelSettings {
+        &mut self.channels[channel.index()]
+    }
+}
+
+impl Default for PheromoneSettings {
+    fn default() -> Self {
+        Self {
+            channels: [
+                // Food trails last long enough to be followed and reinforced
+                ChannelSettings {
+                    evaporation: 0.03,
+                    diffusion: 0.05,
+                },
+                // Home trails are laid by everyone and fade slowly
+                ChannelSettings {
+                    evaporation: 0.02,
+                    diffusion: 0.05,
+                },
+                // Alarm spreads fast and is gone quickly
+                ChannelSettings {
+                    evaporation: 0.5,
+                    diffusion: 0.8,
+                },
+                ChannelSettings {
+                    evaporation: 0.2,
+                    diffusion: 0.3,
+                },
+            ],
+        }
+    }
+}
+
+// One colony's per-tile pheromone concentrations, keyed by the same tile
+// grid as TileStore. Only tiles that actually hold pheromone are stored.
+#[derive(Default)]
+pub struct PheromoneGrid {
+    cells: HashMap<IVec2, [f32; PheromoneChannel::COUNT]>,
+}
+
+// Every colony's pheromones, kept apart: ants lay and follow only the scent
+// of their own colony
+#[derive(Resource)]
+pub struct ColonyPheromones {
+    grids: HashMap<Entity, PheromoneGrid>,
+    update_timer: Timer,
+}
+
+impl Default for ColonyPheromones {
+    fn default() -> Self {
+        Self {
+            grids: HashMap::new(),
+            update_timer: Timer::from_seconds(PHEROMONE_UPDATE_INTERVAL, TimerMode::Repeating),
+        }
+    }
+}
+
+impl ColonyPheromones {
+    // The colony's pheromones, or None before it has laid any
+    pub fn colony(&self, colony: Entity) -> Option<&PheromoneGrid> {
+        self.grids.get(&colony)
+    }
+
+    pub fn colony_mut(&mut self, colony: Entity) -> &mut PheromoneGrid {
+        self.grids.entry(colony).or_default()
+    }
+
+    pub fn clear(&mut self) {
+        self.grids.clear();
+    }
+}
+
+fn cell_of(position: Vec2) -> IVec2 {
+    (position / TILE_SIZE).round().as_ivec2()
+}
+
+fn cell_center(cell: IVec2) -> Vec2 {
+    cell.as_vec2() * TILE_SIZE
+}
+
+const NEIGHBOR_OFFSETS: [IVec2; 4] = [IVec2::X, IVec2::NEG_X, IVec2::Y, IVec2::NEG_Y];
+
+// Pheromone only lingers in open tiles of the map and the sky above it
+fn holds_scent(cell: IVec2, tile_store: &TileStore) -> bool {
+    let in_bounds = cell.x.abs() <= WORLD_HALF_SIZE
+        && cell.y >= -WORLD_HALF_SIZE
+        && cell.y <= WORLD_HALF_SIZE + SKY_HEIGHT;
+    in_bounds && !tile_store.is_solid(&cell_center(cell))
+}
+
+impl PheromoneGrid {
+    // Add pheromone to the tile containing `position`
+    pub fn deposit(&mut self, position: Vec2, channel: PheromoneChannel, amount: f32) {
+        let cell = self
+            .cells
+            .entry(cell_of(position))
+            .or_insert([0.0; PheromoneChannel::COUNT]);
+        let value = &mut cell[channel.index()];
+        *value = (*value + amount).min(MAX_PHEROMONE_STRENGTH);
+    }
+
+    // Lay pheromone along the stretch an ant covered this frame, `amount` per
+    // tile crossed, so fast ants don't leave gaps in their trail
+    pub fn deposit_along(&mut self, from: Vec2, to: Vec2, channel: PheromoneChannel, amount: f32) {
+        let steps = (from.distance(to) / TILE_SIZE).ceil().max(1.0) as usize;
+        let mut last = None;
+        for step in 0..=steps {
+            let cell = cell_of(from.lerp(to, step as f32 / steps as f32));
+            if last != Some(cell) {
+                self.deposit(cell_center(cell), channel, amount);
+                last = Some(cell);
+            }
+        }
+    }
+
+    // Strength of a channel at the tile containing `position`
+    pub fn strength(&self, position: Vec2, channel: PheromoneChannel) -> f32 {
+        self.cells
+            .get(&cell_of(position))
+            .map_or(0.0, |cell| cell[channel.index()])
+    }
+
+    // Direction in which a channel gets stronger around `position`, from the
+    // difference between opposite neighbouring tiles. Zero when flat.
+    pub fn gradient(&self, position: Vec2, channel: PheromoneChannel) -> Vec2 {
+        let center = cell_center(cell_of(position));
+        let sample = |offset: IVec2| self.strength(center + offset.as_vec2() * TILE_SIZE, channel);
+        Vec2::new(
+            sample(IVec2::X) - sample(IVec2::NEG_X),
+            sample(IVec2::Y) - sample(IVec2::NEG_Y),
+        ) / 2.0
+    }
+
+    // The tile with the most of a channel within `radius` tiles of
+    // `position`, and how much it holds
+    pub fn strongest_near(
+        &self,
+        position: Vec2,
+        radius: i32,
+        channel: PheromoneChannel,
+    ) -> Option<(Vec2, f32)> {
+        let center = cell_of(position);
+        (-radius..=radius)
+            .flat_map(|dx| (-radius..=radius).map(move |dy| center + IVec2::new(dx, dy)))
+            .filter_map(|cell| {
+                let value = self.cells.get(&cell)?[channel.index()];
+                (value > 0.0).then(|| (cell_center(cell), value))
+            })
+            .max_by(|a, b| a.1.total_cmp(&b.1))
+    }
+
+    // Strengths sensed by an ant's left antenna, straight ahead and right
+    // antenna, `distance` ahead of it and `spread` radians either side
+    pub fn sense(
+        &self,
+        position: Vec2,
+        heading: Vec2,
+        distance: f32,
+        spread: f32,
+        channel: PheromoneChannel,
+    ) -> [f32; 3] {
+        let heading = heading.normalize_or_zero();
+        [spread, 0.0, -spread].map(|angle| {
+            let direction = Vec2::from_angle(angle).rotate(heading);
+            self.strength(position + direction * distance, channel)
+        })
+    }
+
+    pub fn clear(&mut self) {
+        self.cells.clear();
+    }
+
+    pub fn cell_count(&self) -> usize {
+        self.cells.len()
+    }
+
+    // Evaporate and diffuse every channel over `delta_seconds`. Pheromone only
+    // spreads into open tiles within the map, never into solid ground, and is
+    // lost from tiles that have been filled in.
+    pub fn step(
+        &mut self,
+        settings: &PheromoneSettings,
+        tile_store: &TileStore,
+        delta_seconds: f32,
+    ) {
+        let mut next: HashMap<IVec2, [f32; PheromoneChannel::COUNT]> =
+            HashMap::with_capacity(self.cells.len());
+
+        for (&cell, values) in self.cells.iter() {
+            if !holds_scent(cell, tile_store) {
+                continue;
+            }
+            let open_neighbors: Vec<IVec2> = NEIGHBOR_OFFSETS
+                .iter()
+                .map(|&offset| cell + offset)
+                .filter(|&neighbor| holds_scent(neighbor, tile_store))
+                .collect();
+
+            for channel in PheromoneChannel::ALL {
+                let value = values[channel.index()];
+                if value <= 0.0 {
+                    continue;
+                }
+
+                let channel_settings = settings.channel(channel);
+                let remaining = value * (-channel_settings.evaporation * delta_seconds).exp();
+                let spread = if open_neighbors.is_empty() {
+                    0.0
+                } else {
+                    remaining * (channel_settings.diffusion * delta_seconds).min(1.0)
+                };
+
+                next.entry(cell).or_insert([0.0; PheromoneChannel::COUNT])[channel.index()] +=
+                    remaining - spread;
+                let share = spread / open_neighbors.len().max(1) as f32;
+                for &neighbor in &open_neighbors {
+                    next.entry(neighbor)
+                        .or_insert([0.0; PheromoneChannel::COUNT])[channel.index()] += share;
+                }
+            }
+        }
+
+        next.retain(|_, values| values.iter().any(|&value| value >= MIN_PHEROMONE_STRENGTH));
+        self.cells = next;
+    }
+}
+
+pub fn update_pheromones(
+    time: Res<Time>,
+    settings: Res<PheromoneSettings>,
+    mut pheromones: ResMut<ColonyPheromones>,
+    tile_store: Res<TileStore>,
+) {
+    pheromones.update_timer.tick(time.delta());
+    let updates = pheromones.update_timer.times_finished_this_tick();
+    for _ in 0..updates {
+        for grid in pheromones.grids.values_mut() {
+            grid.step(&settings, &tile_store, PHEROMONE_UPDATE_INTERVAL);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::terrain::DirtTile;
+
+    const CHANNEL: PheromoneChannel = PheromoneChannel::Food;
+
+    fn settings(evaporation: f32, diffusion: f32) -> PheromoneSettings {
+        let mut settings = PheromoneSettings::default();
+        *settings.channel_mut(CHANNEL) = ChannelSettings {
+            evaporation,
+            diffusion,
+        };
+        settings
+    }
+
+    fn total(grid: &PheromoneGrid) -> f32 {
+        grid.cells.values().map(|cell| cell[CHANNEL.index()]).sum()
+    }
+
+    #[test]
+    fn evaporation_decays_exponentially() {
+        let mut grid = PheromoneGrid::default();
+        grid.deposit(Vec2::ZERO, CHANNEL, 10.0);
+        grid.step(&settings(0.5, 0.0), &TileStore::new(), 1.0);
+
+        let expected = 10.0 * (-0.5f32).exp();
+        assert!((grid.strength(Vec2::ZERO, CHANNEL) - expected).abs() < 1e-4);
+        assert_eq!(grid.cell_count(), 1);
+    }
+
+    #[test]
+    fn weak_pheromone_evaporates_completely() {
+        let mut grid = PheromoneGrid::default();
+        grid.deposit(Vec2::ZERO, CHANNEL, MIN_PHEROMONE_STRENGTH * 1.5);
+        grid.step(&settings(1.0, 0.0), &TileStore::new(), 1.0);
+        assert_eq!(grid.cell_count(), 0);
+    }
+
+    #[test]
+    fn diffusion_conserves_the_total() {
+        let mut grid = PheromoneGrid::default();
+        grid.deposit(Vec2::ZERO, CHANNEL, MAX_PHEROMONE_STRENGTH);
+        let settings = settings(0.0, 0.4);
+        for _ in 0..2 {
+            grid.step(&settings, &TileStore::new(), PHEROMONE_UPDATE_INTERVAL);
+        }
+
+        // Both rings of neighbours are still well above MIN_PHEROMONE_STRENGTH,
+        // so nothing has been dropped yet
+        assert_eq!(grid.cell_count(), 13);
+        assert!((total(&grid) - MAX_PHEROMONE_STRENGTH).abs() < 1e-3);
+        assert!(grid.strength(Vec2::new(TILE_SIZE, 0.0), CHANNEL) > 0.0);
+    }
+
+    #[test]
+    fn diffusion_stays_on_the_map_and_out_of_solid_ground() {
+        let mut tile_store = TileStore::new();
+        let corner = IVec2::new(WORLD_HALF_SIZE, -WORLD_HALF_SIZE);
+        let wall = corner + IVec2::NEG_X;
+        tile_store.add_tile(cell_center(wall), Box::new(DirtTile));
+
+        let mut grid = PheromoneGrid::default();
+        grid.deposit(cell_center(corner), CHANNEL, 50.0);
+        let settings = settings(0.0, 0.8);
+        grid.step(&settings, &tile_store, PHEROMONE_UPDATE_INTERVAL);
+        assert!((total(&grid) - 50.0).abs() < 1e-3);
+        for _ in 0..50 {
+            grid.step(&settings, &tile_store, PHEROMONE_UPDATE_INTERVAL);
+        }
+
+        assert!(grid
+            .cells
+            .keys()
+            .all(|&cell| holds_scent(cell, &tile_store)));
+        assert_eq!(grid.strength(cell_center(wall), CHANNEL), 0.0);
+    }
+
+    #[test]
+    fn pheromone_in_a_filled_in_tile_is_lost() {
+        let mut tile_store = TileStore::new();
+        tile_store.add_tile(Vec2::ZERO, Box::new(DirtTile));
+
+        let mut grid = PheromoneGrid::default();
+        grid.deposit(Vec2::ZERO, CHANNEL, 10.0);
+        grid.step(&settings(0.0, 0.5), &tile_store, 1.0);
+        assert_eq!(grid.cell_count(), 0);
+    }
+
+    #[test]
+    fn gradient_points_towards_the_stronger_side() {
+        let mut grid = PheromoneGrid::default();
+        grid.deposit(Vec2::new(TILE_SIZE, 0.0), CHANNEL, 8.0);
+        grid.deposit(Vec2::new(-TILE_SIZE, 0.0), CHANNEL, 2.0);
+        grid.deposit(Vec2::new(0.0, -TILE_SIZE), CHANNEL, 4.0);
+
+        assert_eq!(grid.gradient(Vec2::ZERO, CHANNEL), Vec2::new(3.0, -2.0));
+        assert_eq!(
+            grid.gradient(Vec2::ZERO, PheromoneChannel::Alarm),
+            Vec2::ZERO
+        );
+    }
+}
@@ -0,0 +1,173 @@
+use super::{ground_level, NestSites, TileStore, SURFACE_Y, TILE_SIZE, WORLD_HALF_SIZE};
+use crate::colony::MIDDEN_OFFSET;
+use crate::food::{spawn_food_source, FoodKind, FoodSource};
+use bevy::prelude::*;
+use rand::Rng;
+
+// Rows of open sky above the surface
+pub const SKY_HEIGHT: i32 = 20;
+const SKY_COLOR: Color = Color::rgb(0.55, 0.75, 0.95);
+// Number of plants growing on the surface, at least this many tiles apart
+const PLANT_COUNT: usize = 12;
+const PLANT_SPACING: i32 = 4;
+// Plants keep this many tiles clear of every nest entrance and midden
+const PLANT_NEST_CLEARANCE: i32 = 4;
+// Seconds between a plant dropping its seeds or leaves, which it only does
+// while no food lies this close to it
+const SEED_INTERVAL: f32 = 90.0;
+const SEED_CLEARANCE: f32 = 3.0 * TILE_SIZE;
+
+// The open sky above the surface, drawn behind everything else
+#[derive(Component)]
+pub struct Sky;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlantKind {
+    Grass,
+    Shrub,
+}
+
+impl PlantKind {
+    pub const ALL: [PlantKind; 2] = [PlantKind::Grass, PlantKind::Shrub];
+
+    fn size(&self) -> Vec2 {
+        match self {
+            PlantKind::Grass => Vec2::new(2.0, 10.0),
+            PlantKind::Shrub => Vec2::new(6.0, 20.0),
+        }
+    }
+
+    pub fn color(&self) -> Color {
+        match self {
+            PlantKind::Grass => Color::rgb(0.35, 0.7, 0.25),
+            PlantKind::Shrub => Color::rgb(0.2, 0.5, 0.2),
+        }
+    }
+
+    // What the plant drops for the foragers: grass seeds, and leaves for
+    // colonies with fungus gardens to cut from the shrubs
+    fn litter(&self) -> FoodKind {
+        match self {
+            PlantKind::Grass => FoodKind::Seeds,
+            PlantKind::Shrub => FoodKind::Leaves,
+        }
+    }
+}
+
+// A plant rooted in the surface, which drops seeds or leaves for the foragers
+// now and then
+#[derive(Component)]
+pub struct Plant {
+    pub kind: PlantKind,
+    // The open cell at the plant's foot
+    pub root: Vec2,
+    seed_timer: Timer,
+}
+
+fn spawn_plant(commands: &mut Commands, kind: PlantKind, root: Vec2) {
+    let size = kind.size();
+    // Stand the plant on the ground rather than float it in the middle of the
+    // cell above
+    let position = root + Vec2::new(0.0, (size.y - TILE_SIZE) / 2.0);
+    commands.spawn((
+        Plant {
+            kind,
+            root,
+            seed_timer: Timer::from_seconds(SEED_INTERVAL, TimerMode::Repeating),
+        },
+        SpriteBundle {
+            sprite: Sprite {
+                color: kind.color(),
+                custom_size: Some(size),
+                ..default()
+            },
+            transform: Transform::from_xyz(position.x, position.y, 4.0),
+            ..default()
+        },
+    ));
+}
+
+// Put the sky above the world and grow plants on the surface, clear of every
+// nest's entrance and midden
+pub fn spawn_surface(
+    mut commands: Commands,
+    tile_store: Res<TileStore>,
+    nest_sites: Res<NestSites>,
+) {
+    let width = (2 * WORLD_HALF_SIZE + 1) as f32 * TILE_SIZE;
+    let height = SKY_HEIGHT as f32 * TILE_SIZE;
+    commands.spawn((
+        Sky,
+        SpriteBundle {
+            sprite: Sprite {
+                color: SKY_COLOR,
+                custom_size: Some(Vec2::new(width, height)),
+                ..default()
+            },
+            transform: Transform::from_xyz(0.0, SURFACE_Y + (height + TILE_SIZE) / 2.0, -1.0),
+            ..default()
+        },
+    ));
+
+    let mut rng = rand::thread_rng();
+    let midden_offset = (MIDDEN_OFFSET / TILE_SIZE).round() as i32;
+    let nest_columns: Vec<i32> = nest_sites
+        .sites
+        .iter()
+        .map(|site| (site.x / TILE_SIZE).round() as i32)
+        .collect();
+    let mut columns: Vec<i32> = Vec::new();
+
+    // Bounded so a crowded surface can't keep us looking forever
+    for _ in 0..PLANT_COUNT * 20 {
+        if columns.len() >= PLANT_COUNT {
+            break;
+        }
+        let x = rng.gen_range(-WORLD_HALF_SIZE + 1..WORLD_HALF_SIZE);
+        let too_close_to_nest = nest_columns.iter().any(|&nest| {
+            (x - nest).abs() < PLANT_NEST_CLEARANCE
+                || (x - nest - midden_offset).abs() < PLANT_NEST_CLEARANCE
+        });
+        if too_close_to_nest
+            || columns
+                .iter()
+                .any(|&other| (other - x).abs() < PLANT_SPACING)
+        {
+            continue;
+        }
+        let kind = PlantKind::ALL[rng.gen_range(0..PlantKind::ALL.len())];
+        spawn_plant(&mut commands, kind, ground_level(&tile_store, x));
+        columns.push(x);
+    }
+    info!("Grew {} plants on the surface", columns.len());
+}
+
+// Plants drop a fresh load of seeds or leaves beside them every so often, as
+// long as the last lot has been carried off. Aphid herds living on the plant
+// don't count.
+pub fn drop_litter(
+    mut commands: Commands,
+    time: Res<Time>,
+    tile_store: Res<TileStore>,
+    mut plants: Query<&mut Plant>,
+    food: Query<(&Transform, &FoodSource)>,
+) {
+    for mut plant in plants.iter_mut() {
+        if !plant.seed_timer.tick(time.delta()).just_finished() {
+            continue;
+        }
+        let root = plant.root;
+        let food_nearby = food
+            .iter()
+            .filter(|(_, food)| !food.renewable)
+            .any(|(transform, _)| transform.translation.truncate().distance(root) < SEED_CLEARANCE);
+        if food_nearby {
+            continue;
+        }
+        let column = (root.x / TILE_SIZE).round() as i32 + 1;
+        let position = ground_level(&tile_store, column.min(WORLD_HALF_SIZE));
+        let litter = plant.kind.litter();
+        spawn_food_source(&mut commands, litter, position);
+        println!("A {:?} dropped {:?} at {:?}", plant.kind, litter, position);
+    }
+}